@@ -0,0 +1,363 @@
+//! `AnimSet` and `AnimSequence` exports, with the compressed key formats of UE3's animation
+//! encoding decoded back to plain translations and rotations.
+
+use crate::archive::FArchive;
+use crate::file::GameFile;
+use crate::mesh::{ElementReader, FBulkArray, read_vector_array};
+use crate::package::UnPackage;
+use crate::properties::{FPropertyTag, find_property, read_int_array, read_name_array, read_object_array, read_object_properties};
+use crate::{Result, ParserError};
+
+const MAX_TRACKS: i32 = 1024;
+
+/// How a translation or rotation key is stored, `AnimationCompressionFormat` in UE3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EAnimationCompressionFormat {
+    None,
+    Float96NoW,
+    Fixed48NoW,
+    IntervalFixed32NoW,
+    Fixed32NoW,
+    Float32NoW,
+    Identity
+}
+
+impl EAnimationCompressionFormat {
+
+    fn from_name(name: &str) -> Result<Self> {
+        let format = match name {
+            "ACF_None" => Self::None,
+            "ACF_Float96NoW" => Self::Float96NoW,
+            "ACF_Fixed48NoW" => Self::Fixed48NoW,
+            "ACF_IntervalFixed32NoW" => Self::IntervalFixed32NoW,
+            "ACF_Fixed32NoW" => Self::Fixed32NoW,
+            "ACF_Float32NoW" => Self::Float32NoW,
+            "ACF_Identity" => Self::Identity,
+            other => return Err(Box::new(ParserError::new(&format!("Unknown animation compression format: {}", other))))
+        };
+
+        Ok(format)
+    }
+
+}
+
+/// How the keys of a track are spread over the sequence, `AnimationKeyFormat` in UE3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EAnimationKeyFormat {
+    /// Keys evenly spaced over the sequence.
+    ConstantKeyLerp,
+    /// Keys followed by a table of the frame each one is on.
+    VariableKeyLerp
+}
+
+/// The decoded keys of a bone, with their times in seconds.
+#[derive(Debug, Default, Clone)]
+pub struct FAnimTrack {
+    pub translation_times: Vec<f32>,
+    pub translations: Vec<[f32; 3]>,
+    pub rotation_times: Vec<f32>,
+    /// Quaternions as X, Y, Z, W.
+    pub rotations: Vec<[f32; 4]>
+}
+
+#[derive(Debug, Clone)]
+pub struct UAnimSequence {
+    pub properties: Vec<FPropertyTag>,
+    pub name: String,
+    pub length: f32,
+    pub num_frames: i32,
+    /// One track per bone, in the order of the anim set's `TrackBoneNames`.
+    pub tracks: Vec<FAnimTrack>
+}
+
+impl UAnimSequence {
+
+    /// Parses the serialized data of an `AnimSequence` export of `package` and decodes its tracks.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, data: Vec<u8>) -> Result<Self> {
+        let mut archive = package.object_archive(data);
+        let properties = read_object_properties(&mut archive, &package.names)?;
+
+        let name = find_property(&properties, "SequenceName").and_then(FPropertyTag::as_str).unwrap_or("None").to_owned();
+        let length = find_property(&properties, "SequenceLength").and_then(FPropertyTag::as_float).unwrap_or(0.0);
+        let num_frames = find_property(&properties, "NumFrames").and_then(FPropertyTag::as_int).unwrap_or(0);
+
+        let raw_tracks = read_raw_tracks(&mut archive)?;
+        let count = archive.read_i32()?;
+        let mut stream = vec![0u8; usize::try_from(count)?];
+        archive.read_bytes_vec(&mut stream)?;
+
+        let mut sequence = Self { properties, name, length, num_frames, tracks: vec![] };
+        sequence.tracks = if stream.is_empty() {
+            raw_tracks.into_iter().map(|(translations, rotations)| sequence.raw_track(translations, rotations)).collect()
+        } else {
+            sequence.decode_tracks(package, &stream, archive.is_big_endian())?
+        };
+
+        Ok(sequence)
+    }
+
+    fn format(&self, name: &str, default: EAnimationCompressionFormat) -> Result<EAnimationCompressionFormat> {
+        match find_property(&self.properties, name).and_then(FPropertyTag::as_str) {
+            Some(format) => EAnimationCompressionFormat::from_name(format),
+            None => Ok(default)
+        }
+    }
+
+    fn key_format(&self) -> Result<EAnimationKeyFormat> {
+        match find_property(&self.properties, "KeyEncodingFormat").and_then(FPropertyTag::as_str) {
+            None | Some("AKF_ConstantKeyLerp") => Ok(EAnimationKeyFormat::ConstantKeyLerp),
+            Some("AKF_VariableKeyLerp") => Ok(EAnimationKeyFormat::VariableKeyLerp),
+            Some(other) => Err(Box::new(ParserError::new(&format!("Unsupported animation key format: {}", other))))
+        }
+    }
+
+    /// The time of a key, keys of a constant track are spread evenly from the start to the end.
+    fn key_time(&self, key: usize, count: usize) -> f32 {
+        if count <= 1 {
+            return 0.0;
+        }
+
+        key as f32 * self.length / (count - 1) as f32
+    }
+
+    fn frame_time(&self, frame: usize) -> f32 {
+        if self.num_frames <= 1 {
+            return 0.0;
+        }
+
+        frame as f32 * self.length / (self.num_frames - 1) as f32
+    }
+
+    fn raw_track(&self, translations: Vec<[f32; 3]>, rotations: Vec<[f32; 4]>) -> FAnimTrack {
+        FAnimTrack {
+            translation_times: (0..translations.len()).map(|key| self.key_time(key, translations.len())).collect(),
+            translations,
+            rotation_times: (0..rotations.len()).map(|key| self.key_time(key, rotations.len())).collect(),
+            rotations
+        }
+    }
+
+    /// Decodes the compressed byte stream, `CompressedTrackOffsets` holds the offset and key
+    /// count of the translations and rotations of every track.
+    fn decode_tracks<F: GameFile>(&self, package: &UnPackage<F>, stream: &[u8], big_endian: bool) -> Result<Vec<FAnimTrack>> {
+        let offsets = match find_property(&self.properties, "CompressedTrackOffsets") {
+            Some(property) => read_int_array(package, property)?,
+            None => return Err(Box::new(ParserError::new("Compressed animation without track offsets")))
+        };
+
+        let translation_format = self.format("TranslationCompressionFormat", EAnimationCompressionFormat::None)?;
+        let rotation_format = self.format("RotationCompressionFormat", EAnimationCompressionFormat::Float96NoW)?;
+        let key_format = self.key_format()?;
+
+        let mut tracks = vec![];
+        for track in offsets.chunks_exact(4) {
+            let [translation_offset, translation_keys, rotation_offset, rotation_keys] = [track[0], track[1], track[2], track[3]].map(|value| usize::try_from(value).unwrap_or(0));
+
+            let mut result = FAnimTrack::default();
+            if translation_keys > 0 {
+                let mut reader = StreamReader::new(stream, translation_offset, big_endian)?;
+                let format = if translation_keys == 1 { EAnimationCompressionFormat::None } else { translation_format };
+                result.translations = read_translations(&mut reader, format, translation_keys)?;
+                result.translation_times = self.read_times(&mut reader, key_format, translation_keys)?;
+            }
+
+            if rotation_keys > 0 {
+                let mut reader = StreamReader::new(stream, rotation_offset, big_endian)?;
+                let format = if rotation_keys == 1 { EAnimationCompressionFormat::Float96NoW } else { rotation_format };
+                result.rotations = read_rotations(&mut reader, format, rotation_keys)?;
+                result.rotation_times = self.read_times(&mut reader, key_format, rotation_keys)?;
+            }
+
+            tracks.push(result);
+        }
+
+        Ok(tracks)
+    }
+
+    /// The times of the keys, variable tracks store the frame of each key after them, as bytes
+    /// when the sequence is short enough and shorts otherwise.
+    fn read_times(&self, reader: &mut StreamReader, key_format: EAnimationKeyFormat, count: usize) -> Result<Vec<f32>> {
+        if key_format == EAnimationKeyFormat::ConstantKeyLerp || count == 1 {
+            return Ok((0..count).map(|key| self.key_time(key, count)).collect());
+        }
+
+        reader.align(4);
+        (0..count).map(|_| {
+            let frame = if self.num_frames < 256 { usize::from(reader.u8()?) } else { usize::from(reader.u16()?) };
+            Ok(self.frame_time(frame))
+        }).collect()
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct UAnimSet {
+    pub properties: Vec<FPropertyTag>,
+    /// The sequence object references.
+    pub sequences: Vec<i32>,
+    /// The bone animated by each track of the sequences.
+    pub track_bone_names: Vec<String>
+}
+
+impl UAnimSet {
+
+    pub fn read<F: GameFile>(package: &UnPackage<F>, data: Vec<u8>) -> Result<Self> {
+        let mut archive = package.object_archive(data);
+        let properties = read_object_properties(&mut archive, &package.names)?;
+
+        let sequences = match find_property(&properties, "Sequences") {
+            Some(property) => read_object_array(package, property)?,
+            None => vec![]
+        };
+        let track_bone_names = match find_property(&properties, "TrackBoneNames") {
+            Some(property) => read_name_array(package, property)?,
+            None => vec![]
+        };
+
+        Ok(Self { properties, sequences, track_bone_names })
+    }
+
+}
+
+/// `RawAnimationData`, the uncompressed keys which cooking usually strips.
+#[allow(clippy::type_complexity)]
+fn read_raw_tracks<Ar: FArchive>(archive: &mut Ar) -> Result<Vec<(Vec<[f32; 3]>, Vec<[f32; 4]>)>> {
+    let count = archive.read_i32()?;
+    if !(0..=MAX_TRACKS).contains(&count) {
+        return Err(Box::new(ParserError::new(&format!("Invalid track count: {}", count))));
+    }
+
+    let big_endian = archive.is_big_endian();
+    let mut tracks = Vec::with_capacity(usize::try_from(count)?);
+    for _ in 0..count {
+        let translations = read_vector_array(archive, "translation key")?;
+        let rotations = FBulkArray::read_sized(archive, 16, "rotation key")?.elements()
+            .map(|element| {
+                let mut reader = ElementReader::new(element, big_endian);
+                Ok([reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?])
+            })
+            .collect::<Result<Vec<_>>>()?;
+        tracks.push((translations, rotations));
+    }
+
+    Ok(tracks)
+}
+
+/// A cursor over the compressed byte stream, which is in the package's byte order.
+struct StreamReader<'a> {
+    stream: &'a [u8],
+    position: usize,
+    big_endian: bool
+}
+
+impl<'a> StreamReader<'a> {
+
+    fn new(stream: &'a [u8], position: usize, big_endian: bool) -> Result<Self> {
+        if position > stream.len() {
+            return Err(Box::new(ParserError::new(&format!("Track offset {} is out of the {} byte stream", position, stream.len()))));
+        }
+
+        Ok(Self { stream, position, big_endian })
+    }
+
+    fn take(&mut self, size: usize) -> Result<&'a [u8]> {
+        let bytes = self.stream.get(self.position..self.position + size).ok_or_else(|| ParserError::new("Animation stream ended in a track"))?;
+        self.position += size;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        ElementReader::new(bytes, self.big_endian).u16()
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        ElementReader::new(bytes, self.big_endian).u32()
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        let bytes = self.take(4)?;
+        ElementReader::new(bytes, self.big_endian).f32()
+    }
+
+    fn vector(&mut self) -> Result<[f32; 3]> {
+        Ok([self.f32()?, self.f32()?, self.f32()?])
+    }
+
+    /// Skips the padding UE3 puts between the keys and the frame table.
+    fn align(&mut self, alignment: usize) {
+        self.position = self.position.next_multiple_of(alignment);
+    }
+
+}
+
+fn read_translations(reader: &mut StreamReader, format: EAnimationCompressionFormat, count: usize) -> Result<Vec<[f32; 3]>> {
+    match format {
+        EAnimationCompressionFormat::None | EAnimationCompressionFormat::Float96NoW => (0..count).map(|_| reader.vector()).collect(),
+        EAnimationCompressionFormat::IntervalFixed32NoW => {
+            let mins = reader.vector()?;
+            let ranges = reader.vector()?;
+            (0..count).map(|_| {
+                let packed = reader.u32()?;
+                let [x, y, z] = [unpack(packed & 0x3FF, 511), unpack((packed >> 10) & 0x7FF, 1023), unpack(packed >> 21, 1023)];
+                Ok([x * ranges[0] + mins[0], y * ranges[1] + mins[1], z * ranges[2] + mins[2]])
+            }).collect()
+        },
+        EAnimationCompressionFormat::Identity => Ok(vec![[0.0; 3]; count]),
+        other => Err(Box::new(ParserError::new(&format!("Unsupported translation format: {:?}", other))))
+    }
+}
+
+fn read_rotations(reader: &mut StreamReader, format: EAnimationCompressionFormat, count: usize) -> Result<Vec<[f32; 4]>> {
+    let (mins, ranges) = if format == EAnimationCompressionFormat::IntervalFixed32NoW {
+        (reader.vector()?, reader.vector()?)
+    } else {
+        ([0.0; 3], [1.0; 3])
+    };
+
+    (0..count).map(|_| {
+        let [x, y, z] = match format {
+            EAnimationCompressionFormat::None => return Ok([reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?]),
+            EAnimationCompressionFormat::Identity => return Ok([0.0, 0.0, 0.0, 1.0]),
+            EAnimationCompressionFormat::Float96NoW => reader.vector()?,
+            EAnimationCompressionFormat::Fixed48NoW => [reader.u16()?, reader.u16()?, reader.u16()?].map(|value| unpack(u32::from(value), 32767)),
+            EAnimationCompressionFormat::Fixed32NoW | EAnimationCompressionFormat::IntervalFixed32NoW => {
+                let packed = reader.u32()?;
+                let [x, y, z] = [unpack(packed >> 21, 1023), unpack((packed >> 10) & 0x7FF, 1023), unpack(packed & 0x3FF, 511)];
+                [x * ranges[0] + mins[0], y * ranges[1] + mins[1], z * ranges[2] + mins[2]]
+            },
+            EAnimationCompressionFormat::Float32NoW => {
+                let packed = reader.u32()?;
+                [unpack_float(packed >> 21, 3, 7), unpack_float((packed >> 10) & 0x7FF, 3, 7), unpack_float(packed & 0x3FF, 3, 6)]
+            }
+        };
+
+        Ok([x, y, z, (1.0 - x * x - y * y - z * z).max(0.0).sqrt()])
+    }).collect()
+}
+
+/// A fixed point component centered on `offset`, back to -1..1.
+fn unpack(value: u32, offset: u32) -> f32 {
+    (value as f32 - offset as f32) / offset as f32
+}
+
+/// A small float with an implicit one and an exponent biased for values up to 1, as packed by
+/// UE3's `TFloatPacker`.
+fn unpack_float(value: u32, exponent_bits: u32, mantissa_bits: u32) -> f32 {
+    let magnitude = value & ((1 << (exponent_bits + mantissa_bits)) - 1);
+    if magnitude == 0 {
+        return 0.0;
+    }
+
+    let mantissa = (magnitude & ((1 << mantissa_bits) - 1)) as f32 / (1 << mantissa_bits) as f32;
+    let exponent = (magnitude >> mantissa_bits) as i32 - ((1 << exponent_bits) - 1);
+    let sign = if value >> (exponent_bits + mantissa_bits) != 0 { -1.0 } else { 1.0 };
+
+    sign * (1.0 + mantissa) * 2f32.powi(exponent)
+}
@@ -1,6 +1,7 @@
 use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 
 use crate::package::FGuid;
+use crate::profile::GameProfile;
 use crate::ParserError;
 use crate::Result;
 
@@ -11,8 +12,42 @@ pub trait UESerializable {
     where Ar: FArchive;
 }
 
+/// Integer types that can be decoded from their raw bytes in either byte order.
+pub trait FromBytes<const SIZE: usize>: Sized {
+    fn from_le(bytes: [u8; SIZE]) -> Self;
+    fn from_be(bytes: [u8; SIZE]) -> Self;
+}
+
+macro_rules! impl_from_bytes {
+    ($($type:ty),*) => {
+        $(
+            impl FromBytes<{ std::mem::size_of::<$type>() }> for $type {
+                #[inline(always)]
+                fn from_le(bytes: [u8; std::mem::size_of::<$type>()]) -> Self {
+                    <$type>::from_le_bytes(bytes)
+                }
+
+                #[inline(always)]
+                fn from_be(bytes: [u8; std::mem::size_of::<$type>()]) -> Self {
+                    <$type>::from_be_bytes(bytes)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_bytes!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
 pub trait FArchive {
 
+    /// Whether integers are stored big-endian, as in console builds of UE3 games.
+    fn is_big_endian(&self) -> bool;
+    fn set_big_endian(&mut self, big_endian: bool);
+
+    /// The layout of the package being read, set once the summary's versions are known.
+    fn profile(&self) -> GameProfile;
+    fn set_profile(&mut self, profile: GameProfile);
+
     fn read_bytes(&mut self, buffer: &mut [u8]) -> Result<()>;
     fn read_bytes_vec(&mut self, buffer: &mut Vec<u8>) -> Result<()>;
 
@@ -24,15 +59,15 @@ pub trait FArchive {
 
     fn len(&mut self) -> usize;
 
-    fn read<Type, const SIZE: usize>(&mut self) -> Result<Type> {
-        unsafe {
-            let size = std::mem::size_of::<Type>();
-            assert!(SIZE == size, "invalid size. SIZE: {} std::mem::size_of: {}", SIZE, size);
-
-            let mut buffer = [0u8; SIZE];
-            self.read_bytes(&mut buffer)?;
+    fn read<Type, const SIZE: usize>(&mut self) -> Result<Type>
+    where Type: FromBytes<SIZE> {
+        let mut buffer = [0u8; SIZE];
+        self.read_bytes(&mut buffer)?;
 
-            Ok(std::mem::transmute_copy::<[u8; SIZE], Type>(&buffer))
+        if self.is_big_endian() {
+            Ok(Type::from_be(buffer))
+        } else {
+            Ok(Type::from_le(buffer))
         }
     }
 
@@ -41,6 +76,16 @@ pub trait FArchive {
         self.read::<i64, 8>()
     }
 
+    #[inline(always)]
+    fn read_u64(&mut self) -> Result<u64> {
+        self.read::<u64, 8>()
+    }
+
+    #[inline(always)]
+    fn read_f32(&mut self) -> Result<f32> {
+        self.read::<f32, 4>()
+    }
+
     #[inline(always)]
     fn read_i32(&mut self) -> Result<i32> {
         self.read::<i32, 4>()
@@ -77,12 +122,12 @@ pub trait FArchive {
                 panic!("Archive is corrupted.")
             }
 
-            let len = -length * 2;
-            let mut buffer: Vec<u8> = vec![0; usize::try_from(len)?];
-            self.read_bytes_vec(&mut buffer)?;
+            let mut buffer = Vec::with_capacity(usize::try_from(-length)?);
+            for _ in 0..-length {
+                buffer.push(self.read_u16()?);
+            }
 
-            //return Ok(String::from_utf8(buffer)?);
-            panic!("Unicode FString's are not supported yet.");
+            return Ok(String::from_utf16(&buffer)?);
         }
 
         let mut buffer = vec![0u8; usize::try_from(length)?];
@@ -107,6 +152,66 @@ pub trait FArchive {
         Ok(())
     }
 
+    #[inline(always)]
+    fn write_i64(&mut self, val: i64) -> Result<()> {
+        if self.is_big_endian() {
+            return self.write_all(&val.to_be_bytes());
+        }
+
+        self.write_all(&val.to_le_bytes())
+    }
+
+    #[inline(always)]
+    fn write_i32(&mut self, val: i32) -> Result<()> {
+        if self.is_big_endian() {
+            return self.write_all(&val.to_be_bytes());
+        }
+
+        self.write_all(&val.to_le_bytes())
+    }
+
+    #[inline(always)]
+    fn write_u32(&mut self, val: u32) -> Result<()> {
+        if self.is_big_endian() {
+            return self.write_all(&val.to_be_bytes());
+        }
+
+        self.write_all(&val.to_le_bytes())
+    }
+
+    #[inline(always)]
+    fn write_u16(&mut self, val: u16) -> Result<()> {
+        if self.is_big_endian() {
+            return self.write_all(&val.to_be_bytes());
+        }
+
+        self.write_all(&val.to_le_bytes())
+    }
+
+    fn write_fstring(&mut self, val: &str) -> Result<()> {
+        if val.is_empty() {
+            return self.write_i32(0);
+        }
+
+        // ANSI FStrings are stored with their null terminator included in the length.
+        let bytes = val.as_bytes();
+        if bytes.last() == Some(&0) {
+            self.write_i32(i32::try_from(bytes.len())?)?;
+            return self.write_all(bytes);
+        }
+
+        self.write_i32(i32::try_from(bytes.len() + 1)?)?;
+        self.write_all(bytes)?;
+        self.write_all(&[0])
+    }
+
+    fn write_guid(&mut self, guid: &FGuid) -> Result<()> {
+        self.write_u32(guid.a)?;
+        self.write_u32(guid.b)?;
+        self.write_u32(guid.c)?;
+        self.write_u32(guid.d)
+    }
+
 }
 
 #[allow(dead_code)]
@@ -156,7 +261,9 @@ where Ar: FArchive, T: UESerializable<Item = T> + Default {
 
 pub struct FByteArchive {
     pub cursor: Cursor<Vec<u8>>,
-    pub size: usize
+    pub size: usize,
+    big_endian: bool,
+    profile: GameProfile
 }
 
 impl FByteArchive {
@@ -165,11 +272,14 @@ impl FByteArchive {
         let size = data.len();
         Self {
             cursor: Cursor::new(data),
-            size
+            size,
+            big_endian: false,
+            profile: GameProfile::default()
         }
     }
 
     pub(crate) fn replace_cursor(&mut self, cursor: Cursor<Vec<u8>>) {
+        self.size = cursor.get_ref().len();
         self.cursor = cursor;
     }
 
@@ -177,6 +287,22 @@ impl FByteArchive {
 
 impl FArchive for FByteArchive {
 
+    fn is_big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    fn set_big_endian(&mut self, big_endian: bool) {
+        self.big_endian = big_endian;
+    }
+
+    fn profile(&self) -> GameProfile {
+        self.profile
+    }
+
+    fn set_profile(&mut self, profile: GameProfile) {
+        self.profile = profile;
+    }
+
     fn read_bytes(&mut self, buffer: &mut [u8]) -> Result<()> {
         self.cursor.read_exact(buffer)?;
         Ok(())
@@ -205,4 +331,67 @@ impl FArchive for FByteArchive {
         self.cursor.get_mut()
     }
  
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive(data: &[u8], big_endian: bool) -> FByteArchive {
+        let mut archive = FByteArchive::new(data.to_vec());
+        archive.set_big_endian(big_endian);
+
+        archive
+    }
+
+    #[test]
+    fn integers_follow_the_byte_order() {
+        let data = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+
+        let mut little = archive(&data, false);
+        assert_eq!(little.read_u32().unwrap(), 0x04030201);
+        assert_eq!(little.read_u16().unwrap(), 0x0605);
+        assert_eq!(little.read_16().unwrap(), 0x0807);
+
+        let mut big = archive(&data, true);
+        assert_eq!(big.read_u32().unwrap(), 0x01020304);
+        assert_eq!(big.read_u16().unwrap(), 0x0506);
+        assert_eq!(big.read_16().unwrap(), 0x0708);
+
+        assert_eq!(archive(&data, false).read_i64().unwrap(), 0x0807060504030201);
+        assert_eq!(archive(&data, true).read_u64().unwrap(), 0x0102030405060708);
+        assert_eq!(archive(&1.5f32.to_be_bytes(), true).read_f32().unwrap(), 1.5);
+    }
+
+    #[test]
+    fn writes_round_trip_in_both_orders() {
+        for big_endian in [false, true] {
+            let mut writer = archive(&[], big_endian);
+            writer.write_i32(-2).unwrap();
+            writer.write_u16(0xBEEF).unwrap();
+            writer.write_i64(1 << 40).unwrap();
+            writer.write_fstring("Engine").unwrap();
+            writer.write_guid(&FGuid { a: 1, b: 2, c: 3, d: 4 }).unwrap();
+
+            let data = std::mem::take(writer.get_mut());
+            let length = if big_endian { [0, 0, 0, 7] } else { [7, 0, 0, 0] };
+            assert_eq!(data[14..18], length);
+
+            let mut reader = archive(&data, big_endian);
+            assert_eq!(reader.read_i32().unwrap(), -2);
+            assert_eq!(reader.read_u16().unwrap(), 0xBEEF);
+            assert_eq!(reader.read_i64().unwrap(), 1 << 40);
+            assert_eq!(reader.read_fstring().unwrap(), "Engine\0");
+            let guid = reader.read_guid().unwrap();
+            assert_eq!((guid.a, guid.b, guid.c, guid.d), (1, 2, 3, 4));
+        }
+    }
+
+    #[test]
+    fn utf16_strings_follow_the_byte_order() {
+        let mut data = (-3i32).to_be_bytes().to_vec();
+        data.extend([0, b'O', 0, b'K', 0, 0]);
+
+        assert_eq!(archive(&data, true).read_fstring().unwrap(), "OK\0");
+    }
+
+}
@@ -0,0 +1,233 @@
+#![allow(non_upper_case_globals)]
+
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::archive::{FArchive, FByteArchive, read_serializable};
+use crate::compression::{FCompressedChunk, FCompressedChunkHeader, decompress_chunk};
+use crate::package::ECompressionFlags;
+use crate::{Result, ParserError};
+
+/// The payload is in another file, a texture file cache for textures.
+pub const BULKDATA_StoredInSeparateFile: u32 = 0x01;
+pub const BULKDATA_SerializeCompressedZLIB: u32 = 0x02;
+pub const BULKDATA_ForceSingleElementSerialization: u32 = 0x04;
+pub const BULKDATA_SingleUse: u32 = 0x08;
+pub const BULKDATA_SerializeCompressedLZO: u32 = 0x10;
+pub const BULKDATA_Unused: u32 = 0x20;
+/// The payload is elsewhere in the package, usually after the last export.
+pub const BULKDATA_StoredAsSeparateData: u32 = 0x40;
+pub const BULKDATA_SerializeCompressedLZX: u32 = 0x80;
+
+/// Where the payload of a bulk data is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EBulkDataLocation {
+    /// Right after the header, read along with it.
+    Inline,
+    /// At `offset_in_file` in the uncompressed package.
+    EndOfPackage,
+    /// At `offset_in_file` in a separate file.
+    External,
+    Empty
+}
+
+/// The header and payload of UE3's `FUntypedBulkData`, used by textures, sounds and meshes
+/// for their large blobs. Only inline payloads are read with the header, the others are
+/// loaded from their source with `load_from`.
+#[derive(Debug, Default, Clone)]
+pub struct FUntypedBulkData {
+    pub flags: u32,
+    pub element_count: i32,
+    pub size_on_disk: i32,
+    pub offset_in_file: i32,
+    /// The uncompressed payload, empty until it's loaded.
+    pub data: Vec<u8>
+}
+
+impl FUntypedBulkData {
+
+    /// Reads the header, and the payload when it's inline. The archive is left after the payload either way.
+    pub fn serialize<Ar: FArchive>(archive: &mut Ar) -> Result<Self> {
+        let mut bulk = Self {
+            flags: archive.read_u32()?,
+            element_count: archive.read_i32()?,
+            size_on_disk: archive.read_i32()?,
+            offset_in_file: archive.read_i32()?,
+            data: vec![]
+        };
+
+        if bulk.location() == EBulkDataLocation::Inline {
+            let start = archive.seek(SeekFrom::Current(0))?;
+            bulk.data = bulk.read_payload(archive)?;
+            archive.seek(SeekFrom::Start(start + u64::try_from(bulk.size_on_disk)?))?;
+        }
+
+        Ok(bulk)
+    }
+
+    pub fn location(&self) -> EBulkDataLocation {
+        if self.flags & BULKDATA_Unused != 0 || self.size_on_disk <= 0 {
+            EBulkDataLocation::Empty
+        } else if self.flags & BULKDATA_StoredInSeparateFile != 0 {
+            EBulkDataLocation::External
+        } else if self.flags & BULKDATA_StoredAsSeparateData != 0 {
+            EBulkDataLocation::EndOfPackage
+        } else {
+            EBulkDataLocation::Inline
+        }
+    }
+
+    pub fn compression(&self) -> ECompressionFlags {
+        match self.flags {
+            flags if flags & BULKDATA_SerializeCompressedZLIB != 0 => ECompressionFlags::Zlib,
+            flags if flags & BULKDATA_SerializeCompressedLZO != 0 => ECompressionFlags::Lzo,
+            flags if flags & BULKDATA_SerializeCompressedLZX != 0 => ECompressionFlags::Lzx,
+            _ => ECompressionFlags::None
+        }
+    }
+
+    /// Whether the payload is available, an empty bulk data always is.
+    pub fn is_loaded(&self) -> bool {
+        !self.data.is_empty() || self.location() == EBulkDataLocation::Empty
+    }
+
+    /// Reads the payload at `offset_in_file` from `source`: the package reader for payloads at
+    /// the end of the package, or the separate file for external ones.
+    pub fn load_from<R: Read + Seek>(&mut self, source: &mut R, big_endian: bool) -> Result<()> {
+        source.seek(SeekFrom::Start(u64::try_from(self.offset_in_file)?))?;
+        let mut payload = vec![0u8; usize::try_from(self.size_on_disk)?];
+        source.read_exact(&mut payload)?;
+
+        let mut archive = FByteArchive::new(payload);
+        archive.set_big_endian(big_endian);
+        self.data = self.read_payload(&mut archive)?;
+
+        Ok(())
+    }
+
+    /// Reads the `size_on_disk` bytes at the archive's position, inflating them when they're
+    /// compressed. Compressed payloads use the same chunk format as the package, and their byte
+    /// size comes from the chunk header: `element_count` counts elements, not bytes.
+    fn read_payload<Ar: FArchive>(&self, archive: &mut Ar) -> Result<Vec<u8>> {
+        let compression = self.compression();
+        if compression == ECompressionFlags::None {
+            let mut data = vec![0u8; usize::try_from(self.size_on_disk)?];
+            archive.read_bytes_vec(&mut data)?;
+            return Ok(data);
+        }
+
+        let start = archive.seek(SeekFrom::Current(0))?;
+        let header: FCompressedChunkHeader = read_serializable(archive)?;
+        archive.seek(SeekFrom::Start(start))?;
+
+        let chunk = FCompressedChunk {
+            uncompressed_offset: 0,
+            uncompressed_size: header.summary.uncompressed_size,
+            compressed_offset: i32::try_from(start)?,
+            compressed_size: self.size_on_disk
+        };
+
+        let data = decompress_chunk(archive, &chunk, compression)?;
+        let expected = usize::try_from(header.summary.uncompressed_size)?;
+        if data.len() != expected {
+            return Err(Box::new(ParserError::new(&format!("Bulk data inflated to {} bytes, expected {}", data.len(), expected))));
+        }
+
+        // Every element has the same size, so the payload has to split evenly between them.
+        if self.element_count > 0 && expected % usize::try_from(self.element_count)? != 0 {
+            return Err(Box::new(ParserError::new(&format!("Bulk data of {} bytes doesn't hold {} elements", expected, self.element_count))));
+        }
+
+        Ok(data)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::compression::compress_chunk;
+
+    fn header(flags: u32, element_count: i32, size_on_disk: i32, offset_in_file: i32) -> Vec<u8> {
+        [flags.to_le_bytes(), element_count.to_le_bytes(), size_on_disk.to_le_bytes(), offset_in_file.to_le_bytes()].concat()
+    }
+
+    #[test]
+    fn inline_payload_is_read_with_the_header() {
+        let bytes = [header(0, 4, 4, 16), vec![1, 2, 3, 4], vec![0xFF]].concat();
+        let mut archive = FByteArchive::new(bytes);
+        let bulk = FUntypedBulkData::serialize(&mut archive).unwrap();
+
+        assert_eq!(bulk.location(), EBulkDataLocation::Inline);
+        assert_eq!(bulk.data, [1, 2, 3, 4]);
+        assert_eq!(archive.read_u8().unwrap(), 0xFF);
+    }
+
+    #[test]
+    fn unused_payload_is_empty_and_loaded() {
+        let bytes = [header(BULKDATA_Unused, 0, 0, 16), vec![0xFF]].concat();
+        let mut archive = FByteArchive::new(bytes);
+        let bulk = FUntypedBulkData::serialize(&mut archive).unwrap();
+
+        assert_eq!(bulk.location(), EBulkDataLocation::Empty);
+        assert!(bulk.data.is_empty());
+        assert!(bulk.is_loaded());
+        assert_eq!(archive.read_u8().unwrap(), 0xFF);
+    }
+
+    #[test]
+    fn end_of_package_payload_is_loaded_from_its_offset() {
+        let bytes = [header(BULKDATA_StoredAsSeparateData, 3, 3, 20), vec![0xFF]].concat();
+        let mut archive = FByteArchive::new(bytes);
+        let mut bulk = FUntypedBulkData::serialize(&mut archive).unwrap();
+
+        assert_eq!(bulk.location(), EBulkDataLocation::EndOfPackage);
+        assert!(!bulk.is_loaded());
+        assert_eq!(archive.read_u8().unwrap(), 0xFF);
+
+        let package = [vec![0u8; 20], vec![7, 8, 9], vec![0u8; 4]].concat();
+        bulk.load_from(&mut Cursor::new(package), false).unwrap();
+        assert_eq!(bulk.data, [7, 8, 9]);
+    }
+
+    #[test]
+    fn separate_file_payload_is_external() {
+        let bulk = FUntypedBulkData::serialize(&mut FByteArchive::new(header(BULKDATA_StoredInSeparateFile, 8, 8, 0))).unwrap();
+        assert_eq!(bulk.location(), EBulkDataLocation::External);
+        assert!(!bulk.is_loaded());
+    }
+
+    #[test]
+    fn compressed_payload_is_sized_from_its_chunk() {
+        // 8 two-byte elements: the byte size is twice the element count.
+        let payload: Vec<u8> = (0..16).collect();
+        let chunk = compress_chunk(&payload, 6, false);
+        let bytes = [header(BULKDATA_SerializeCompressedZLIB, 8, chunk.len() as i32, 16), chunk, vec![0xFF]].concat();
+        let mut archive = FByteArchive::new(bytes);
+        let bulk = FUntypedBulkData::serialize(&mut archive).unwrap();
+
+        assert_eq!(bulk.compression(), ECompressionFlags::Zlib);
+        assert_eq!(bulk.data, payload);
+        assert_eq!(archive.read_u8().unwrap(), 0xFF);
+    }
+
+    #[test]
+    fn compressed_end_of_package_payload_is_inflated() {
+        let payload: Vec<u8> = (0..12).collect();
+        let chunk = compress_chunk(&payload, 0x20000, false);
+        let flags = BULKDATA_StoredAsSeparateData | BULKDATA_SerializeCompressedZLIB;
+        let mut bulk = FUntypedBulkData::serialize(&mut FByteArchive::new(header(flags, 3, chunk.len() as i32, 4))).unwrap();
+
+        bulk.load_from(&mut Cursor::new([vec![0u8; 4], chunk].concat()), false).unwrap();
+        assert_eq!(bulk.data, payload);
+    }
+
+    #[test]
+    fn compressed_payload_must_split_between_its_elements() {
+        let chunk = compress_chunk(&[0u8; 10], 0x20000, false);
+        let bytes = [header(BULKDATA_SerializeCompressedZLIB, 4, chunk.len() as i32, 16), chunk].concat();
+        assert!(FUntypedBulkData::serialize(&mut FByteArchive::new(bytes)).is_err());
+    }
+
+}
@@ -0,0 +1,151 @@
+//! The physics setup of car archetypes: the class defaults and archetypes of the car classes,
+//! with the properties that shape how the car moves and collides.
+
+use serde_json::{Map, Value, json};
+
+use crate::file::GameFile;
+use crate::json::PropertySerializer;
+use crate::package::{FObjectExport, UnPackage, RF_ArchetypeObject, RF_ClassDefaultObject};
+use crate::properties::FPropertyTag;
+use crate::reader::FPackageReader;
+use crate::Result;
+
+/// Classes whose archetypes are cars, their subclasses declared in the same package count too.
+pub const CAR_CLASSES: [&str; 2] = ["Car_TA", "Vehicle_TA"];
+
+/// The properties that are kept, subobjects like the `VehicleSim` with its wheels are kept whole.
+const PHYSICS_PROPERTIES: [&str; 16] = [
+    "VehicleSim", "Mass", "CenterOfMassOffset", "InertiaTensorScale", "MaxLinearSpeed", "MaxAngularSpeed",
+    "HitboxOffset", "HitboxExtent", "CollisionComponent", "CylinderComponent", "CollisionType", "Physics",
+    "AddedCarForceMultiplier", "AddedBallForceMultiplier", "bDisableSleeping", "RBCollideWithChannels"
+];
+
+const MAX_SUPER_DEPTH: usize = 64;
+const MAX_ARCHETYPE_DEPTH: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct FCarArchetype {
+    /// The path of the archetype in its package.
+    pub path: String,
+    pub class: String,
+    /// The physics properties, with their subobjects expanded.
+    pub properties: Map<String, Value>
+}
+
+impl FCarArchetype {
+
+    pub fn to_json(&self) -> Value {
+        json!({ "path": self.path, "class": self.class, "properties": self.properties })
+    }
+
+}
+
+/// Reads the class defaults and archetypes of the car classes exported by `package`. Exports only
+/// store the properties that differ from their templates, so the properties of their archetypes
+/// and class default object are read first and overridden by their own.
+pub fn read_car_archetypes<F: GameFile>(package: &UnPackage<F>, reader: &mut FPackageReader) -> Result<Vec<FCarArchetype>> {
+    let indices: Vec<i32> = (1..=package.exports.len())
+        .filter(|index| is_car_archetype(package, &package.exports[index - 1]))
+        .map(i32::try_from)
+        .collect::<std::result::Result<_, _>>()?;
+
+    let mut serializer = PropertySerializer::new(package, reader);
+    let mut archetypes = vec![];
+    for index in indices {
+        let path = package.get_object_path(index).unwrap_or_default();
+        let mut properties = Map::new();
+        for template in templates(package, index).into_iter().rev() {
+            match physics_properties(&mut serializer, template) {
+                Ok(inherited) => properties.extend(inherited),
+                Err(err) => log::warn!("skipped the template {} of {}: {}", package.get_object_path(template).unwrap_or_default(), path, err)
+            }
+        }
+        properties.extend(physics_properties(&mut serializer, index)?);
+
+        archetypes.push(FCarArchetype {
+            path,
+            class: package.get_class_name(&package.exports[usize::try_from(index - 1)?]).unwrap_or_default(),
+            properties
+        });
+    }
+
+    Ok(archetypes)
+}
+
+/// The physics properties an export sets itself, with the subobjects it owns expanded.
+fn physics_properties<F: GameFile>(serializer: &mut PropertySerializer<F>, index: i32) -> Result<Map<String, Value>> {
+    let properties: Vec<FPropertyTag> = serializer.read_export_properties(index)?.into_iter()
+        .filter(|property| PHYSICS_PROPERTIES.iter().any(|name| name.eq_ignore_ascii_case(&property.name)))
+        .collect();
+
+    serializer.owned_properties(index, &properties)
+}
+
+/// The exports the properties of an export are inherited from, nearest first: its chain of
+/// archetypes, which ends at the default object of its class when it's in the package, and the
+/// super class default objects behind it. Templates in other packages are left out.
+fn templates<F: GameFile>(package: &UnPackage<F>, index: i32) -> Vec<i32> {
+    let mut templates: Vec<i32> = vec![];
+    let mut default_object = export(package, index).and_then(|export| find_default_object(package, export.class_index));
+    let mut current = archetype(package, index);
+    while templates.len() < MAX_ARCHETYPE_DEPTH {
+        if current <= 0 || current == index || templates.contains(&current) {
+            match default_object.take() {
+                Some(default_object) => current = default_object,
+                None => break
+            }
+            continue;
+        }
+
+        templates.push(current);
+        current = archetype(package, current);
+    }
+
+    templates
+}
+
+fn archetype<F: GameFile>(package: &UnPackage<F>, index: i32) -> i32 {
+    export(package, index).map_or(0, |export| export.archetype_index)
+}
+
+fn find_default_object<F: GameFile>(package: &UnPackage<F>, class_index: i32) -> Option<i32> {
+    (1..=package.exports.len())
+        .filter_map(|index| i32::try_from(index).ok())
+        .find(|index| export(package, *index).is_some_and(|export| export.class_index == class_index && is_default_object(package, export)))
+}
+
+fn export<F: GameFile>(package: &UnPackage<F>, index: i32) -> Option<&FObjectExport> {
+    usize::try_from(index - 1).ok().and_then(|index| package.exports.get(index))
+}
+
+fn is_default_object<F: GameFile>(package: &UnPackage<F>, export: &FObjectExport) -> bool {
+    export.object_flags & RF_ClassDefaultObject != 0
+        || package.get_name(&export.object_name).is_some_and(|name| name.starts_with("Default__"))
+}
+
+fn is_car_archetype<F: GameFile>(package: &UnPackage<F>, export: &FObjectExport) -> bool {
+    let is_template = export.object_flags & RF_ArchetypeObject != 0 || is_default_object(package, export);
+
+    is_template && is_car_class(package, export.class_index)
+}
+
+/// Whether the class reference is a car class, following the superclasses of exported classes.
+pub fn is_car_class<F: GameFile>(package: &UnPackage<F>, class_index: i32) -> bool {
+    let mut current = class_index;
+    for _ in 0..MAX_SUPER_DEPTH {
+        if current == 0 {
+            return false;
+        }
+
+        if package.get_object_name(current).is_some_and(|name| CAR_CLASSES.contains(&name.as_str())) {
+            return true;
+        }
+
+        current = match usize::try_from(current - 1).ok().and_then(|index| package.exports.get(index)) {
+            Some(class) => class.super_index,
+            None => return false
+        };
+    }
+
+    false
+}
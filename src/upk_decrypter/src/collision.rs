@@ -0,0 +1,236 @@
+//! The collision geometry of a map as a single triangle soup in world space, for physics
+//! simulations. Meshes collide per polygon with the triangles of their collision-enabled
+//! sections, which is what the arena surfaces use.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::rc::Rc;
+
+use crate::file::{GameFile, OsGameFile};
+use crate::level::{FTransform, ULevel};
+use crate::package::UnPackage;
+use crate::properties::{FPropertyTag, find_property, read_object_array, read_object_properties};
+use crate::reader::FPackageReader;
+use crate::staticmesh::UStaticMesh;
+use crate::{DefaultFileProvider, Result, ParserError};
+
+/// The actor properties that can hold a static mesh component.
+const COMPONENT_PROPERTIES: [&str; 2] = ["StaticMeshComponent", "CollisionComponent"];
+
+#[derive(Debug, Default, Clone)]
+pub struct FCollisionMesh {
+    /// Positions in Unreal units and axes.
+    pub vertices: Vec<[f32; 3]>,
+    /// Three indices per triangle.
+    pub indices: Vec<u32>
+}
+
+impl FCollisionMesh {
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Appends the collision-enabled triangles of the mesh's first LOD, placed by `transforms`
+    /// from the innermost to the outermost. Returns how many triangles were added.
+    pub fn append_static_mesh(&mut self, mesh: &UStaticMesh, transforms: &[FTransform]) -> Result<usize> {
+        let model = match mesh.lods.first() {
+            Some(model) => model,
+            None => return Ok(0)
+        };
+
+        let base = u32::try_from(self.vertices.len())?;
+        for position in &model.positions {
+            self.vertices.push(transforms.iter().fold(*position, |point, transform| transform.apply(point)));
+        }
+
+        let mirrored = transforms.iter().filter(|transform| transform.is_mirrored()).count() % 2 == 1;
+        let mut added = 0;
+        for section in model.sections.iter().filter(|section| section.enable_collision) {
+            let first = usize::try_from(section.first_index)?;
+            let count = usize::try_from(section.num_triangles)? * 3;
+            let indices = model.indices.get(first..first + count)
+                .ok_or_else(|| ParserError::new("Section is out of the index buffer"))?;
+
+            for triangle in indices.chunks_exact(3) {
+                let triangle = if mirrored { [triangle[0], triangle[2], triangle[1]] } else { [triangle[0], triangle[1], triangle[2]] };
+                self.indices.extend(triangle.map(|index| base + index));
+                added += 1;
+            }
+        }
+
+        Ok(added)
+    }
+
+    /// Writes a Wavefront OBJ with the vertices and faces only.
+    pub fn write_obj<W: Write>(&self, writer: &mut W) -> Result<()> {
+        for [x, y, z] in &self.vertices {
+            writeln!(writer, "v {} {} {}", x, y, z)?;
+        }
+
+        for triangle in self.indices.chunks_exact(3) {
+            writeln!(writer, "f {} {} {}", triangle[0] + 1, triangle[1] + 1, triangle[2] + 1)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the little-endian vertex and triangle counts, then the vertices as three floats
+    /// and the triangles as three 32-bit indices.
+    pub fn write_binary<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&u32::try_from(self.vertices.len())?.to_le_bytes())?;
+        writer.write_all(&u32::try_from(self.triangle_count())?.to_le_bytes())?;
+        for component in self.vertices.iter().flatten() {
+            writer.write_all(&component.to_le_bytes())?;
+        }
+
+        for index in &self.indices {
+            writer.write_all(&index.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+}
+
+/// Builds the collision of the map package `name`, from the static mesh components of the
+/// actors of its levels. Meshes imported from other packages are loaded through `provider`.
+pub fn build_level_collision(provider: &DefaultFileProvider, name: &str) -> Result<FCollisionMesh> {
+    let (package, mut reader) = provider.open_package_reader(name)?;
+    let mut loader = MeshLoader { provider, packages: HashMap::new(), meshes: HashMap::new() };
+    let mut collision = FCollisionMesh::default();
+
+    for export in package.exports.iter().filter(|export| package.get_class_name(export).as_deref() == Some("Level")) {
+        let level = ULevel::read(&package, reader.read_export(export)?)?;
+        for actor in level.actors.iter().copied().filter(|actor| *actor > 0) {
+            // An actor or component that can't be read only loses its own collision, as a mesh does.
+            let actor_properties = read_export_properties(&package, &mut reader, actor)
+                .and_then(|properties| Ok((FTransform::from_actor(&package, &properties)?, actor_components(&package, &properties)?)));
+            let (actor_transform, components) = match actor_properties {
+                Ok(actor_properties) => actor_properties,
+                Err(err) => {
+                    log::warn!("skipped the actor {}: {}", package.get_object_path(actor).unwrap_or_default(), err);
+                    continue;
+                }
+            };
+
+            for component in components {
+                let properties = match read_export_properties(&package, &mut reader, component) {
+                    Ok(properties) => properties,
+                    Err(err) => {
+                        log::warn!("skipped the collision of {}: {}", package.get_object_path(component).unwrap_or_default(), err);
+                        continue;
+                    }
+                };
+
+                let blocks = |name: &str| find_property(&properties, name).and_then(FPropertyTag::as_bool) != Some(false);
+                if !blocks("CollideActors") || !blocks("BlockRigidBody") {
+                    continue;
+                }
+
+                let mesh = match find_property(&properties, "StaticMesh").and_then(FPropertyTag::as_object) {
+                    Some(mesh) if mesh != 0 => mesh,
+                    _ => continue
+                };
+
+                let mesh = loader.load(&package, &mut reader, mesh)
+                    .and_then(|mesh| Ok((mesh, FTransform::from_component(&package, &properties)?)));
+                let (mesh, component_transform) = match mesh {
+                    Ok(mesh) => mesh,
+                    Err(err) => {
+                        log::warn!("skipped the collision of {}: {}", package.get_object_path(component).unwrap_or_default(), err);
+                        continue;
+                    }
+                };
+
+                collision.append_static_mesh(&mesh, &[component_transform, actor_transform])?;
+            }
+        }
+    }
+
+    Ok(collision)
+}
+
+fn read_export_properties(package: &UnPackage<OsGameFile>, reader: &mut FPackageReader, index: i32) -> Result<Vec<FPropertyTag>> {
+    let export = package.exports.get(usize::try_from(index - 1)?)
+        .ok_or_else(|| ParserError::new(&format!("Invalid export index {}", index)))?;
+
+    read_object_properties(&mut package.object_archive(reader.read_export(export)?), &package.names)
+}
+
+/// The static mesh components an actor points at, through its component properties and its `Components` array.
+fn actor_components(package: &UnPackage<OsGameFile>, properties: &[FPropertyTag]) -> Result<Vec<i32>> {
+    let mut components: Vec<i32> = COMPONENT_PROPERTIES.iter()
+        .filter_map(|name| find_property(properties, name).and_then(FPropertyTag::as_object))
+        .collect();
+
+    if let Some(property) = find_property(properties, "Components") {
+        components.extend(read_object_array(package, property)?);
+    }
+
+    let mut unique = vec![];
+    for component in components {
+        let is_mesh = usize::try_from(component - 1).ok()
+            .and_then(|index| package.exports.get(index))
+            .is_some_and(|export| package.get_class_name(export).as_deref() == Some("StaticMeshComponent"));
+        if is_mesh && !unique.contains(&component) {
+            unique.push(component);
+        }
+    }
+
+    Ok(unique)
+}
+
+/// Loads static meshes once per map, opening the packages of imported ones on demand.
+struct MeshLoader<'a> {
+    provider: &'a DefaultFileProvider,
+    /// The opened packages by lowercase name, `None` for the ones that couldn't be opened.
+    packages: HashMap<String, Option<(UnPackage<OsGameFile>, FPackageReader)>>,
+    meshes: HashMap<String, Rc<UStaticMesh>>
+}
+
+impl MeshLoader<'_> {
+
+    fn load(&mut self, package: &UnPackage<OsGameFile>, reader: &mut FPackageReader, index: i32) -> Result<Rc<UStaticMesh>> {
+        let path = package.get_object_path(index).ok_or_else(|| ParserError::new(&format!("Invalid object reference {}", index)))?;
+        let key = if index > 0 {
+            format!("{}.{}", package.file.get_filename(), path).to_lowercase()
+        } else {
+            path.to_lowercase()
+        };
+
+        if let Some(mesh) = self.meshes.get(&key) {
+            return Ok(mesh.clone());
+        }
+
+        let mesh = if index > 0 {
+            let export = &package.exports[usize::try_from(index - 1)?];
+            UStaticMesh::read(package, reader.read_export(export)?)?
+        } else {
+            self.load_import(&path)?
+        };
+
+        let mesh = Rc::new(mesh);
+        self.meshes.insert(key, mesh.clone());
+        Ok(mesh)
+    }
+
+    /// Loads `Package.Group.Name` from the package it's exported by.
+    fn load_import(&mut self, path: &str) -> Result<UStaticMesh> {
+        let (package_name, object_path) = path.split_once('.').ok_or_else(|| ParserError::new(&format!("{} is not in a package", path)))?;
+        let provider = self.provider;
+        let opened = self.packages.entry(package_name.to_lowercase()).or_insert_with(|| {
+            let file = provider.find_package(package_name)?;
+            provider.open_package_reader(&file.file_name).ok()
+        });
+
+        let (package, reader) = opened.as_mut().ok_or_else(|| ParserError::new(&format!("Package {} not found", package_name)))?;
+        let index = (1..=package.exports.len())
+            .find(|index| i32::try_from(*index).ok().and_then(|index| package.get_object_path(index)).is_some_and(|path| path.eq_ignore_ascii_case(object_path)))
+            .ok_or_else(|| ParserError::new(&format!("{} is not exported by {}", object_path, package_name)))?;
+
+        let export = &package.exports[index - 1];
+        UStaticMesh::read(package, reader.read_export(export)?)
+    }
+
+}
@@ -1,11 +1,28 @@
-use miniz_oxide::inflate::decompress_to_vec_zlib;
+use miniz_oxide::inflate::TINFLStatus;
+use miniz_oxide::inflate::core::{decompress as inflate_core, inflate_flags, DecompressorOxide};
 
-use std::io::{Cursor, SeekFrom, Seek, Write};
+use std::io::{Cursor, SeekFrom};
+use std::sync::Mutex;
 
 use crate::archive::{UESerializable, read_serializable, FArchive};
-use crate::Result;
+use crate::package::{ECompressionFlags, PACKAGE_MAGIC};
+use crate::lzo;
+use crate::{Result, ParserError};
 
-#[derive(Debug, Default)]
+const CHUNK_HEADER_SIZE: usize = 16;
+const CHUNK_BLOCK_SIZE: usize = 8;
+
+const ZLIB_CM_DEFLATE: u8 = 0x08;
+
+const GZIP_ID1: u8 = 0x1F;
+const GZIP_ID2: u8 = 0x8B;
+const GZIP_CM_DEFLATE: u8 = 0x08;
+const GZIP_FHCRC: u8 = 0x02;
+const GZIP_FEXTRA: u8 = 0x04;
+const GZIP_FNAME: u8 = 0x08;
+const GZIP_FCOMMENT: u8 = 0x10;
+
+#[derive(Debug, Default, Clone)]
 pub struct FCompressedChunk {
     pub uncompressed_offset: i32,
     pub uncompressed_size: i32,
@@ -18,16 +35,46 @@ impl UESerializable for FCompressedChunk {
 
     fn serialize<Ar>(item: &mut Self::Item, archive: &mut Ar) -> Result<()>
     where Ar: crate::archive::FArchive {
-        item.uncompressed_offset = i32::try_from(archive.read_i64()?)?;
+        let wide_offsets = archive.profile().has_wide_offsets;
+        item.uncompressed_offset = read_offset(archive, wide_offsets)?;
         item.uncompressed_size = archive.read_i32()?;
 
-        item.compressed_offset = i32::try_from(archive.read_i64()?)?;
+        item.compressed_offset = read_offset(archive, wide_offsets)?;
         item.compressed_size = archive.read_i32()?;
 
         Ok(())
     }
 }
 
+impl FCompressedChunk {
+
+    pub fn write<Ar>(&self, archive: &mut Ar) -> Result<()>
+    where Ar: FArchive {
+        let wide_offsets = archive.profile().has_wide_offsets;
+        write_offset(archive, self.uncompressed_offset, wide_offsets)?;
+        archive.write_i32(self.uncompressed_size)?;
+        write_offset(archive, self.compressed_offset, wide_offsets)?;
+        archive.write_i32(self.compressed_size)
+    }
+
+}
+
+fn read_offset<Ar: FArchive>(archive: &mut Ar, wide: bool) -> Result<i32> {
+    if wide {
+        return Ok(i32::try_from(archive.read_i64()?)?);
+    }
+
+    archive.read_i32()
+}
+
+fn write_offset<Ar: FArchive>(archive: &mut Ar, offset: i32, wide: bool) -> Result<()> {
+    if wide {
+        return archive.write_i64(i64::from(offset));
+    }
+
+    archive.write_i32(offset)
+}
+
 #[derive(Debug, Default)]
 pub struct FCompressedChunkBlock {
     pub compressed_size: i32,
@@ -66,32 +113,295 @@ impl UESerializable for FCompressedChunkHeader {
     }
 }
 
-pub fn decompress<Ar>(archive: &mut Ar, cursor: &mut Cursor<Vec<u8>>, compressed_chunks: &[FCompressedChunk]) -> Result<()>
+/// A single compressed block and where its data goes in the uncompressed output.
+struct BlockJob {
+    compressed_offset: usize,
+    compressed_size: usize,
+    uncompressed_offset: usize,
+    uncompressed_size: usize
+}
+
+pub fn decompress<Ar>(archive: &mut Ar, cursor: &mut Cursor<Vec<u8>>, compressed_chunks: &[FCompressedChunk], flags: ECompressionFlags) -> Result<()>
 where Ar: FArchive {
+    decompress_with_threads(archive, cursor.get_mut(), compressed_chunks, flags, 1)
+}
+
+/// Decompresses the chunks into `output` at their uncompressed offsets, growing it if needed.
+/// The blocks of every chunk are independent streams, so they're spread over `threads` threads
+/// and each one is inflated straight into its slot of the output buffer.
+pub fn decompress_with_threads<Ar>(archive: &mut Ar, output: &mut Vec<u8>, compressed_chunks: &[FCompressedChunk], flags: ECompressionFlags, threads: usize) -> Result<()>
+where Ar: FArchive {
+    let mut jobs: Vec<BlockJob> = vec![];
     for chunk in compressed_chunks {
-        archive.seek(SeekFrom::Start(u64::try_from(chunk.compressed_offset)?))?;
+        jobs.extend(read_block_jobs(archive, chunk, usize::try_from(chunk.uncompressed_offset)?)?);
+    }
+
+    if let Some(end) = jobs.iter().map(|job| job.uncompressed_offset + job.uncompressed_size).max() {
+        if end > output.len() {
+            output.resize(end, 0);
+        }
+    }
+
+    run_block_jobs(archive.get_mut(), output, jobs, flags, threads)
+}
+
+/// Inflates a single chunk, returning its `uncompressed_size` bytes.
+pub fn decompress_chunk<Ar>(archive: &mut Ar, chunk: &FCompressedChunk, flags: ECompressionFlags) -> Result<Vec<u8>>
+where Ar: FArchive {
+    let jobs = read_block_jobs(archive, chunk, 0)?;
+    let size = jobs.iter().map(|job| job.uncompressed_size).sum();
+
+    let mut result = vec![0u8; size];
+    run_block_jobs(archive.get_mut(), &mut result, jobs, flags, 1)?;
+
+    Ok(result)
+}
+
+/// Checks whether `data` is a whole package stored as a single compressed chunk, which is how
+/// UE3 writes fully compressed packages. Returns the byte order of the chunk header if it is.
+pub fn fully_compressed_byte_order(data: &[u8]) -> Option<bool> {
+    [false, true].into_iter().find(|big_endian| is_single_chunk(data, *big_endian))
+}
+
+/// Guesses the method of a fully compressed package from its first block, since the summary
+/// that holds the compression flags is inside the compressed data. ZLIB and GZIP streams start
+/// with a header that LZO1X blocks don't have.
+pub fn detect_fully_compressed_method(data: &[u8], big_endian: bool) -> ECompressionFlags {
+    let read = |offset: usize| read_i32(data, offset, big_endian);
+    let (block_size, uncompressed_size) = match (read(4), read(12)) {
+        (Some(block_size), Some(uncompressed_size)) if block_size > 0 && uncompressed_size > 0 => (block_size, uncompressed_size),
+        _ => return ECompressionFlags::Zlib
+    };
+
+    let blocks = usize::try_from((i64::from(uncompressed_size) + i64::from(block_size) - 1) / i64::from(block_size)).unwrap_or(0);
+    let first_block = data.get(CHUNK_HEADER_SIZE + blocks * CHUNK_BLOCK_SIZE..).unwrap_or_default();
+    if is_gzip(first_block) || is_zlib(first_block) {
+        ECompressionFlags::Zlib
+    } else {
+        ECompressionFlags::Lzo
+    }
+}
+
+fn read_i32(data: &[u8], offset: usize, big_endian: bool) -> Option<i32> {
+    let bytes: [u8; 4] = data.get(offset..offset + 4)?.try_into().ok()?;
+    Some(if big_endian { i32::from_be_bytes(bytes) } else { i32::from_le_bytes(bytes) })
+}
+
+fn is_single_chunk(data: &[u8], big_endian: bool) -> bool {
+    let read = |offset: usize| read_i32(data, offset, big_endian);
+
+    let (tag, block_size, compressed_size, uncompressed_size) = match (read(0), read(4), read(8), read(12)) {
+        (Some(tag), Some(block_size), Some(compressed_size), Some(uncompressed_size)) => (tag as u32, block_size, compressed_size, uncompressed_size),
+        _ => return false
+    };
+
+    if tag != PACKAGE_MAGIC || block_size <= 0 || compressed_size <= 0 || uncompressed_size <= 0 {
+        return false;
+    }
+
+    // The block table and the blocks have to account for the whole file, which a regular
+    // summary starting with the same tag never does.
+    let blocks = usize::try_from((i64::from(uncompressed_size) + i64::from(block_size) - 1) / i64::from(block_size)).unwrap_or(0);
+    let mut total_compressed = 0i64;
+    let mut total_uncompressed = 0i64;
+    for block in 0..blocks {
+        let offset = CHUNK_HEADER_SIZE + block * CHUNK_BLOCK_SIZE;
+        match (read(offset), read(offset + 4)) {
+            (Some(compressed), Some(uncompressed)) => {
+                total_compressed += i64::from(compressed);
+                total_uncompressed += i64::from(uncompressed);
+            },
+            _ => return false
+        }
+    }
+
+    let expected_len = (CHUNK_HEADER_SIZE + blocks * CHUNK_BLOCK_SIZE) as i64 + total_compressed;
+    total_compressed == i64::from(compressed_size) && total_uncompressed == i64::from(uncompressed_size) && expected_len == data.len() as i64
+}
+
+/// Reads the chunk header and block table, placing the chunk's data at `output_offset`.
+fn read_block_jobs<Ar>(archive: &mut Ar, chunk: &FCompressedChunk, output_offset: usize) -> Result<Vec<BlockJob>>
+where Ar: FArchive {
+    archive.seek(SeekFrom::Start(u64::try_from(chunk.compressed_offset)?))?;
 
-        let header: FCompressedChunkHeader = read_serializable(archive)?;
-        let mut blocks: Vec<FCompressedChunkBlock> = vec![];
-        let mut total_block_size = 0;
+    let header: FCompressedChunkHeader = read_serializable(archive)?;
+    let mut blocks: Vec<FCompressedChunkBlock> = vec![];
+    let mut total_block_size = 0;
 
-        while total_block_size < header.summary.uncompressed_size {
-            let block: FCompressedChunkBlock = read_serializable(archive)?;
-            total_block_size += block.uncompressed_size;
-            blocks.push(block);
+    while total_block_size < header.summary.uncompressed_size {
+        let block: FCompressedChunkBlock = read_serializable(archive)?;
+        if block.uncompressed_size <= 0 {
+            return Err(Box::new(ParserError::new("Invalid compressed block size")));
         }
 
-        cursor.seek(SeekFrom::Start(usize::try_from(chunk.uncompressed_offset)? as u64))?;
-        for block in blocks {
-            let mut compressed_data = vec![0u8; usize::try_from(block.compressed_size)?];
-            archive.read_bytes(&mut compressed_data)?; // todo: optimize
+        total_block_size += block.uncompressed_size;
+        blocks.push(block);
+    }
+
+    let mut compressed_offset = usize::try_from(archive.seek(SeekFrom::Current(0))?)?;
+    let mut uncompressed_offset = output_offset;
+    let mut jobs = Vec::with_capacity(blocks.len());
+    for block in blocks {
+        let job = BlockJob {
+            compressed_offset,
+            compressed_size: usize::try_from(block.compressed_size)?,
+            uncompressed_offset,
+            uncompressed_size: usize::try_from(block.uncompressed_size)?
+        };
+
+        compressed_offset += job.compressed_size;
+        uncompressed_offset += job.uncompressed_size;
+        jobs.push(job);
+    }
+
+    Ok(jobs)
+}
+
+fn run_block_jobs(source: &[u8], output: &mut [u8], mut jobs: Vec<BlockJob>, flags: ECompressionFlags, threads: usize) -> Result<()> {
+    jobs.sort_by_key(|job| job.uncompressed_offset);
+
+    // Hand every job its own disjoint slice of the output so the threads never share memory.
+    let mut slots: Vec<(BlockJob, &mut [u8])> = Vec::with_capacity(jobs.len());
+    let mut remaining = output;
+    let mut position = 0;
+    for job in jobs {
+        if job.uncompressed_offset < position {
+            return Err(Box::new(ParserError::new("Compressed blocks overlap")));
+        }
+
+        let (_, rest) = std::mem::take(&mut remaining).split_at_mut(job.uncompressed_offset - position);
+        if rest.len() < job.uncompressed_size {
+            return Err(Box::new(ParserError::new("Compressed block is out of bounds")));
+        }
+
+        let (slot, rest) = rest.split_at_mut(job.uncompressed_size);
+        position = job.uncompressed_offset + job.uncompressed_size;
+        remaining = rest;
+        slots.push((job, slot));
+    }
 
-            let decompressed = decompress_to_vec_zlib(compressed_data.as_slice()).unwrap();
-            cursor.write_all(decompressed.as_slice())?;
+    let threads = threads.clamp(1, slots.len().max(1));
+    if threads == 1 {
+        for (job, slot) in slots {
+            decompress_block(source, &job, slot, flags)?;
         }
 
-        //log::info!("decompressed chunk of {} bytes", 0)
+        return Ok(());
+    }
+
+    let queue = Mutex::new(slots.into_iter());
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads).map(|_| scope.spawn(|| -> std::result::Result<(), String> {
+            loop {
+                let next = queue.lock().unwrap().next();
+                match next {
+                    Some((job, slot)) => decompress_block(source, &job, slot, flags).map_err(|err| err.to_string())?,
+                    None => return Ok(())
+                }
+            }
+        })).collect();
+
+        for worker in workers {
+            worker.join().unwrap().map_err(|err| ParserError::new(&err))?;
+        }
+
+        Ok(())
+    })
+}
+
+fn decompress_block(source: &[u8], job: &BlockJob, output: &mut [u8], flags: ECompressionFlags) -> Result<()> {
+    let input = source.get(job.compressed_offset..job.compressed_offset + job.compressed_size)
+        .ok_or_else(|| ParserError::new("Compressed block is out of bounds"))?;
+
+    let written = match flags {
+        ECompressionFlags::Zlib if is_gzip(input) => inflate_gzip(input, output)?,
+        ECompressionFlags::Zlib => inflate(input, output, inflate_flags::TINFL_FLAG_PARSE_ZLIB_HEADER)?,
+        ECompressionFlags::Gzip => inflate_gzip(input, output)?,
+        ECompressionFlags::Lzo => lzo::decompress(input, output)?,
+        ECompressionFlags::None => return Err(Box::new(ParserError::new("Package has compressed chunks but no compression method"))),
+        other => return Err(Box::new(ParserError::new(&format!("Unsupported compression method: {:?}", other))))
+    };
+
+    if written != output.len() {
+        return Err(Box::new(ParserError::new(&format!("Block decompressed to {} bytes, expected {}", written, output.len()))));
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+fn inflate(input: &[u8], output: &mut [u8], flags: u32) -> Result<usize> {
+    let mut decompressor = Box::new(DecompressorOxide::new());
+    let flags = flags | inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF;
+    let (status, _, written) = inflate_core(&mut decompressor, input, output, 0, flags);
+    if status != TINFLStatus::Done {
+        return Err(Box::new(ParserError::new(&format!("Failed to inflate block: {:?}", status))));
+    }
+
+    Ok(written)
+}
+
+fn is_gzip(input: &[u8]) -> bool {
+    input.len() >= 10 && input[0] == GZIP_ID1 && input[1] == GZIP_ID2 && input[2] == GZIP_CM_DEFLATE
+}
+
+/// A deflate stream with a ZLIB header (RFC 1950), whose two bytes are a multiple of 31.
+fn is_zlib(input: &[u8]) -> bool {
+    input.len() >= 2 && input[0] & 0x0F == ZLIB_CM_DEFLATE && (u16::from(input[0]) << 8 | u16::from(input[1])) % 31 == 0
+}
+
+/// Skips the GZIP member header (RFC 1952) and inflates the raw deflate stream behind it.
+fn inflate_gzip(input: &[u8], output: &mut [u8]) -> Result<usize> {
+    if !is_gzip(input) {
+        return Err(Box::new(ParserError::new("Invalid GZIP header")));
+    }
+
+    let flags = input[3];
+    let mut position = 10;
+    if flags & GZIP_FEXTRA != 0 {
+        let extra = input.get(position..position + 2).ok_or_else(|| ParserError::new("Truncated GZIP header"))?;
+        position += 2 + usize::from(u16::from_le_bytes([extra[0], extra[1]]));
+    }
+
+    for flag in [GZIP_FNAME, GZIP_FCOMMENT] {
+        if flags & flag != 0 {
+            let terminator = input.get(position..).and_then(|rest| rest.iter().position(|b| *b == 0))
+                .ok_or_else(|| ParserError::new("Truncated GZIP header"))?;
+            position += terminator + 1;
+        }
+    }
+
+    if flags & GZIP_FHCRC != 0 {
+        position += 2;
+    }
+
+    let stream = input.get(position..).ok_or_else(|| ParserError::new("Truncated GZIP header"))?;
+    inflate(stream, output, 0)
+}
+
+/// Splits `data` into ZLIB blocks of at most `block_size` bytes behind a chunk header, the way
+/// UE3 writes compressed chunks and bulk data.
+#[cfg(test)]
+pub(crate) fn compress_chunk(data: &[u8], block_size: usize, big_endian: bool) -> Vec<u8> {
+    let blocks: Vec<(usize, Vec<u8>)> = data.chunks(block_size)
+        .map(|block| (block.len(), miniz_oxide::deflate::compress_to_vec_zlib(block, 6)))
+        .collect();
+    let compressed_size: usize = blocks.iter().map(|(_, block)| block.len()).sum();
+
+    let mut archive = crate::archive::FByteArchive::new(vec![]);
+    archive.set_big_endian(big_endian);
+    archive.write_u32(PACKAGE_MAGIC).unwrap();
+    for size in [block_size, compressed_size, data.len()] {
+        archive.write_i32(i32::try_from(size).unwrap()).unwrap();
+    }
+
+    for (uncompressed_size, block) in &blocks {
+        archive.write_i32(i32::try_from(block.len()).unwrap()).unwrap();
+        archive.write_i32(i32::try_from(*uncompressed_size).unwrap()).unwrap();
+    }
+
+    for (_, block) in &blocks {
+        archive.write_all(block).unwrap();
+    }
+
+    std::mem::take(archive.get_mut())
+}
@@ -0,0 +1,132 @@
+//! A DDS writer that stores the mips as they are, without re-encoding.
+
+use std::io::Write;
+
+use crate::dxt::block_count;
+use crate::texture::EPixelFormat;
+use crate::{Result, ParserError};
+
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+const DDS_HEADER_SIZE: u32 = 124;
+const DDS_PIXELFORMAT_SIZE: u32 = 32;
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSD_LINEARSIZE: u32 = 0x80000;
+
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x20000;
+
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xFE00;
+
+/// How a pixel format is described in the DDS pixel format block.
+struct PixelFormat {
+    flags: u32,
+    four_cc: [u8; 4],
+    bit_count: u32,
+    masks: [u32; 4]
+}
+
+fn pixel_format(format: &EPixelFormat) -> Result<PixelFormat> {
+    let four_cc = |four_cc: &[u8; 4]| PixelFormat { flags: DDPF_FOURCC, four_cc: *four_cc, bit_count: 0, masks: [0; 4] };
+    let format = match format {
+        EPixelFormat::DXT1 => four_cc(b"DXT1"),
+        EPixelFormat::DXT3 => four_cc(b"DXT3"),
+        EPixelFormat::DXT5 => four_cc(b"DXT5"),
+        EPixelFormat::BC5 => four_cc(b"ATI2"),
+        EPixelFormat::A8R8G8B8 => PixelFormat {
+            flags: DDPF_RGB | DDPF_ALPHAPIXELS,
+            four_cc: [0; 4],
+            bit_count: 32,
+            masks: [0x00FF0000, 0x0000FF00, 0x000000FF, 0xFF000000]
+        },
+        EPixelFormat::G8 => PixelFormat { flags: DDPF_LUMINANCE, four_cc: [0; 4], bit_count: 8, masks: [0xFF, 0, 0, 0] },
+        EPixelFormat::Unknown(format) => return Err(Box::new(ParserError::new(&format!("Unsupported pixel format: {}", format))))
+    };
+
+    Ok(format)
+}
+
+/// The size of a mip in bytes, without any padding the package may store after it.
+pub(crate) fn mip_size(format: &EPixelFormat, width: usize, height: usize) -> Result<usize> {
+    let size = match format {
+        EPixelFormat::DXT1 => block_count(width) * block_count(height) * 8,
+        EPixelFormat::DXT3 | EPixelFormat::DXT5 | EPixelFormat::BC5 => block_count(width) * block_count(height) * 16,
+        EPixelFormat::A8R8G8B8 => width * height * 4,
+        EPixelFormat::G8 => width * height,
+        EPixelFormat::Unknown(format) => return Err(Box::new(ParserError::new(&format!("Unsupported pixel format: {}", format))))
+    };
+
+    Ok(size)
+}
+
+fn is_block_compressed(format: &EPixelFormat) -> bool {
+    matches!(format, EPixelFormat::DXT1 | EPixelFormat::DXT3 | EPixelFormat::DXT5 | EPixelFormat::BC5)
+}
+
+/// Writes a DDS with `faces` holding the mip chain of every face, largest first.
+/// A single face is a plain texture, six faces are a cubemap in +X, -X, +Y, -Y, +Z, -Z order.
+pub(crate) fn write_dds<W: Write>(writer: &mut W, format: &EPixelFormat, width: usize, height: usize, faces: &[Vec<&[u8]>]) -> Result<()> {
+    let mip_count = faces.first().map_or(0, Vec::len);
+    if mip_count == 0 || !(faces.len() == 1 || faces.len() == 6) || faces.iter().any(|mips| mips.len() != mip_count) {
+        return Err(Box::new(ParserError::new("A DDS needs one or six faces with the same number of mips")));
+    }
+
+    let pixel_format = pixel_format(format)?;
+    let pitch = if is_block_compressed(format) {
+        mip_size(format, width, height)?
+    } else {
+        width * (pixel_format.bit_count as usize / 8)
+    };
+
+    let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT | DDSD_MIPMAPCOUNT;
+    flags |= if is_block_compressed(format) { DDSD_LINEARSIZE } else { DDSD_PITCH };
+
+    let mut caps = DDSCAPS_TEXTURE;
+    if mip_count > 1 {
+        caps |= DDSCAPS_MIPMAP | DDSCAPS_COMPLEX;
+    }
+
+    let caps2 = if faces.len() == 6 {
+        caps |= DDSCAPS_COMPLEX;
+        DDSCAPS2_CUBEMAP_ALL_FACES
+    } else {
+        0
+    };
+
+    let mut header: Vec<u32> = vec![
+        DDS_HEADER_SIZE,
+        flags,
+        u32::try_from(height)?,
+        u32::try_from(width)?,
+        u32::try_from(pitch)?,
+        0,
+        u32::try_from(mip_count)?
+    ];
+    header.extend([0u32; 11]);
+    header.extend([DDS_PIXELFORMAT_SIZE, pixel_format.flags, u32::from_le_bytes(pixel_format.four_cc), pixel_format.bit_count]);
+    header.extend(pixel_format.masks);
+    header.extend([caps, caps2, 0, 0, 0]);
+
+    writer.write_all(DDS_MAGIC)?;
+    for value in header {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    for mips in faces {
+        for mip in mips {
+            writer.write_all(mip)?;
+        }
+    }
+
+    Ok(())
+}
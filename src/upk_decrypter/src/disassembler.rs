@@ -0,0 +1,930 @@
+//! Decodes UnrealScript bytecode into pseudo-code, one statement per line. Object and name
+//! references are resolved through the package, native calls through a table of the native
+//! functions, which turns operators back into their symbols.
+
+#![allow(non_upper_case_globals)]
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+use crate::file::GameFile;
+use crate::package::{FName, UnPackage, resolve_name};
+use crate::reader::FPackageReader;
+use crate::script::*;
+use crate::{Result, ParserError};
+
+pub const EX_LocalVariable: u8 = 0x00;
+pub const EX_InstanceVariable: u8 = 0x01;
+pub const EX_DefaultVariable: u8 = 0x02;
+pub const EX_StateVariable: u8 = 0x03;
+pub const EX_Return: u8 = 0x04;
+pub const EX_Switch: u8 = 0x05;
+pub const EX_Jump: u8 = 0x06;
+pub const EX_JumpIfNot: u8 = 0x07;
+pub const EX_Stop: u8 = 0x08;
+pub const EX_Assert: u8 = 0x09;
+pub const EX_Case: u8 = 0x0A;
+pub const EX_Nothing: u8 = 0x0B;
+pub const EX_LabelTable: u8 = 0x0C;
+pub const EX_GotoLabel: u8 = 0x0D;
+pub const EX_EatReturnValue: u8 = 0x0E;
+pub const EX_Let: u8 = 0x0F;
+pub const EX_DynArrayElement: u8 = 0x10;
+pub const EX_New: u8 = 0x11;
+pub const EX_ClassContext: u8 = 0x12;
+pub const EX_MetaCast: u8 = 0x13;
+pub const EX_LetBool: u8 = 0x14;
+pub const EX_EndParmValue: u8 = 0x15;
+pub const EX_EndFunctionParms: u8 = 0x16;
+pub const EX_Self: u8 = 0x17;
+pub const EX_Skip: u8 = 0x18;
+pub const EX_Context: u8 = 0x19;
+pub const EX_ArrayElement: u8 = 0x1A;
+pub const EX_VirtualFunction: u8 = 0x1B;
+pub const EX_FinalFunction: u8 = 0x1C;
+pub const EX_IntConst: u8 = 0x1D;
+pub const EX_FloatConst: u8 = 0x1E;
+pub const EX_StringConst: u8 = 0x1F;
+pub const EX_ObjectConst: u8 = 0x20;
+pub const EX_NameConst: u8 = 0x21;
+pub const EX_RotationConst: u8 = 0x22;
+pub const EX_VectorConst: u8 = 0x23;
+pub const EX_ByteConst: u8 = 0x24;
+pub const EX_IntZero: u8 = 0x25;
+pub const EX_IntOne: u8 = 0x26;
+pub const EX_True: u8 = 0x27;
+pub const EX_False: u8 = 0x28;
+pub const EX_NativeParm: u8 = 0x29;
+pub const EX_NoObject: u8 = 0x2A;
+pub const EX_IntConstByte: u8 = 0x2C;
+pub const EX_BoolVariable: u8 = 0x2D;
+pub const EX_DynamicCast: u8 = 0x2E;
+pub const EX_Iterator: u8 = 0x2F;
+pub const EX_IteratorPop: u8 = 0x30;
+pub const EX_IteratorNext: u8 = 0x31;
+pub const EX_StructCmpEq: u8 = 0x32;
+pub const EX_StructCmpNe: u8 = 0x33;
+pub const EX_UnicodeStringConst: u8 = 0x34;
+pub const EX_StructMember: u8 = 0x35;
+pub const EX_DynArrayLength: u8 = 0x36;
+pub const EX_GlobalFunction: u8 = 0x37;
+pub const EX_PrimitiveCast: u8 = 0x38;
+pub const EX_DynArrayInsert: u8 = 0x39;
+pub const EX_ReturnNothing: u8 = 0x3A;
+pub const EX_EqualEqual_DelDel: u8 = 0x3B;
+pub const EX_NotEqual_DelDel: u8 = 0x3C;
+pub const EX_EqualEqual_DelFunc: u8 = 0x3D;
+pub const EX_NotEqual_DelFunc: u8 = 0x3E;
+pub const EX_EmptyDelegate: u8 = 0x3F;
+pub const EX_DynArrayRemove: u8 = 0x40;
+pub const EX_DebugInfo: u8 = 0x41;
+pub const EX_DelegateFunction: u8 = 0x42;
+pub const EX_DelegateProperty: u8 = 0x43;
+pub const EX_LetDelegate: u8 = 0x44;
+pub const EX_Conditional: u8 = 0x45;
+pub const EX_DynArrayFind: u8 = 0x46;
+pub const EX_DynArrayFindStruct: u8 = 0x47;
+pub const EX_LocalOutVariable: u8 = 0x48;
+pub const EX_DefaultParmValue: u8 = 0x49;
+pub const EX_EmptyParmValue: u8 = 0x4A;
+pub const EX_InstanceDelegate: u8 = 0x4B;
+pub const EX_InterfaceContext: u8 = 0x51;
+pub const EX_InterfaceCast: u8 = 0x52;
+pub const EX_EndOfScript: u8 = 0x53;
+pub const EX_DynArrayAdd: u8 = 0x54;
+pub const EX_DynArrayAddItem: u8 = 0x55;
+pub const EX_DynArrayRemoveItem: u8 = 0x56;
+pub const EX_DynArrayInsertItem: u8 = 0x57;
+pub const EX_DynArrayIterator: u8 = 0x58;
+pub const EX_DynArraySort: u8 = 0x59;
+pub const EX_FilterEditorOnly: u8 = 0x5A;
+pub const EX_ExtendedNative: u8 = 0x60;
+pub const EX_FirstNative: u8 = 0x70;
+
+/// Jump offsets of the bytecode, `0xFFFF` marking the default case of a switch.
+const NO_OFFSET: u16 = 0xFFFF;
+const MAX_EXPRESSION_DEPTH: usize = 256;
+
+/// The types primitive casts convert to, by cast token.
+const PRIMITIVE_CASTS: [(u8, &str); 31] = [
+    (0x39, "vector"),
+    (0x3A, "int"), (0x3B, "bool"), (0x3C, "float"), (0x3D, "byte"), (0x3E, "bool"), (0x3F, "float"),
+    (0x40, "byte"), (0x41, "int"), (0x42, "float"), (0x43, "byte"), (0x44, "int"), (0x45, "bool"),
+    (0x46, "interface"), (0x47, "bool"), (0x48, "bool"), (0x49, "byte"), (0x4A, "int"), (0x4B, "bool"),
+    (0x4C, "float"), (0x4D, "vector"), (0x4E, "rotator"), (0x4F, "bool"), (0x50, "rotator"), (0x51, "bool"),
+    (0x52, "string"), (0x53, "string"), (0x54, "string"), (0x55, "string"), (0x56, "string"), (0x57, "string")
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FScriptStatement {
+    /// The offset of the statement in the loaded bytecode, the one jumps use.
+    pub offset: u32,
+    pub text: String,
+    /// Whether a jump lands on the statement.
+    pub is_jump_target: bool
+}
+
+#[derive(Debug, Clone)]
+pub struct FNativeFunction {
+    /// The name or operator symbol.
+    pub name: String,
+    pub function_flags: u32
+}
+
+/// The native functions called by index, found in the packages declaring them (mostly `Core`
+/// and `Engine`).
+#[derive(Debug, Clone, Default)]
+pub struct FNativeTable {
+    functions: HashMap<u16, FNativeFunction>
+}
+
+impl FNativeTable {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the indexed native functions exported by `package`, returning how many were added.
+    pub fn add_package<F: GameFile>(&mut self, package: &UnPackage<F>, reader: &mut FPackageReader) -> Result<usize> {
+        let mut count = 0;
+        for export in package.exports.iter().filter(|export| package.get_class_name(export).as_deref() == Some("Function")) {
+            let function = match UFunction::read(package, reader.read_export(export)?) {
+                Ok(function) => function,
+                Err(_) => continue
+            };
+
+            if function.native_index != 0 {
+                self.functions.insert(function.native_index, FNativeFunction { name: function.friendly_name, function_flags: function.function_flags });
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    pub fn get(&self, index: u16) -> Option<&FNativeFunction> {
+        self.functions.get(&index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+}
+
+struct Decoder<'a, F: GameFile> {
+    package: &'a UnPackage<F>,
+    natives: &'a FNativeTable,
+    script: &'a [u8],
+    position: usize,
+    /// The position in the loaded bytecode, ahead of `position` by the widened pointers.
+    memory: u32,
+    pointer_size: u32,
+    jump_targets: BTreeSet<u32>,
+    depth: usize
+}
+
+impl<'a, F: GameFile> Decoder<'a, F> {
+
+    fn bytes<const SIZE: usize>(&mut self) -> Result<[u8; SIZE]> {
+        let bytes = self.script.get(self.position..self.position + SIZE)
+            .ok_or_else(|| ParserError::new(&format!("Script ends in the middle of a token at 0x{:04X}", self.memory)))?;
+        self.position += SIZE;
+        self.memory += u32::try_from(SIZE)?;
+
+        let mut array = [0u8; SIZE];
+        array.copy_from_slice(bytes);
+        if self.package.is_big_endian() {
+            array.reverse();
+        }
+
+        Ok(array)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.bytes::<1>()?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes()?))
+    }
+
+    fn read_i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.bytes()?))
+    }
+
+    fn read_f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.bytes()?))
+    }
+
+    /// An object reference, four bytes serialized and a pointer once loaded.
+    fn read_object(&mut self) -> Result<i32> {
+        let index = self.read_i32()?;
+        self.memory += self.pointer_size - 4;
+
+        Ok(index)
+    }
+
+    fn read_object_name(&mut self) -> Result<String> {
+        let index = self.read_object()?;
+        Ok(self.object_name(index))
+    }
+
+    fn read_name(&mut self) -> Result<String> {
+        let name = FName { index: self.read_i32()?, number: self.read_i32()? };
+        Ok(resolve_name(&self.package.names, &name).unwrap_or_else(|| format!("name_{}", name.index)))
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        let mut bytes = vec![];
+        loop {
+            match self.read_u8()? {
+                0 => break,
+                byte => bytes.push(byte)
+            }
+        }
+
+        Ok(bytes.iter().map(|byte| char::from(*byte)).collect())
+    }
+
+    fn read_unicode_string(&mut self) -> Result<String> {
+        let mut units = vec![];
+        loop {
+            match self.read_u16()? {
+                0 => break,
+                unit => units.push(unit)
+            }
+        }
+
+        Ok(String::from_utf16_lossy(&units))
+    }
+
+    fn read_jump(&mut self) -> Result<String> {
+        let offset = self.read_u16()?;
+        self.jump_targets.insert(u32::from(offset));
+
+        Ok(label(u32::from(offset)))
+    }
+
+    fn object_name(&self, index: i32) -> String {
+        match index {
+            0 => "None".to_string(),
+            index => self.package.get_object_name(index).unwrap_or_else(|| format!("object_{}", index))
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.script.get(self.position).copied()
+    }
+
+    /// The expressions up to the `EX_EndFunctionParms` closing a call.
+    fn arguments(&mut self) -> Result<Vec<String>> {
+        let mut arguments = vec![];
+        loop {
+            match self.peek() {
+                Some(EX_EndFunctionParms) => {
+                    self.read_u8()?;
+                    return Ok(arguments);
+                },
+                Some(_) => arguments.push(self.expression()?),
+                None => return Err(Box::new(ParserError::new("Script ends in the middle of a call")))
+            }
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<String> {
+        let arguments = self.arguments()?;
+        Ok(format!("{}({})", name, trim_arguments(arguments).join(", ")))
+    }
+
+    fn native_call(&mut self, index: u16) -> Result<String> {
+        let arguments = self.arguments()?;
+        let native = match self.natives.get(index) {
+            Some(native) => native,
+            None => return Ok(format!("native_{}({})", index, trim_arguments(arguments).join(", ")))
+        };
+
+        let is_operator = native.function_flags & FUNC_Operator != 0;
+        Ok(match arguments.as_slice() {
+            [left, right] if is_operator => format!("({} {} {})", left, native.name, right),
+            [operand] if native.function_flags & FUNC_PreOperator != 0 => format!("{}{}", native.name, operand),
+            [operand] if is_operator => format!("{}{}", operand, native.name),
+            _ => format!("{}({})", native.name, trim_arguments(arguments).join(", "))
+        })
+    }
+
+    fn dynamic_array_call(&mut self, method: &str, has_skip: bool) -> Result<String> {
+        let array = self.expression()?;
+        if has_skip {
+            self.read_u16()?;
+        }
+
+        let arguments = self.arguments()?;
+        Ok(format!("{}.{}({})", array, method, trim_arguments(arguments).join(", ")))
+    }
+
+    fn expression(&mut self) -> Result<String> {
+        if self.depth >= MAX_EXPRESSION_DEPTH {
+            return Err(Box::new(ParserError::new("Expressions are nested too deep")));
+        }
+
+        self.depth += 1;
+        let result = self.token();
+        self.depth -= 1;
+
+        result
+    }
+
+    fn token(&mut self) -> Result<String> {
+        let offset = self.memory;
+        let token = self.read_u8()?;
+        let text = match token {
+            EX_LocalVariable | EX_InstanceVariable | EX_StateVariable | EX_LocalOutVariable | EX_NativeParm => self.read_object_name()?,
+            EX_DefaultVariable => format!("default.{}", self.read_object_name()?),
+            EX_Return => {
+                let value = self.expression()?;
+                if value.is_empty() { "return".to_string() } else { format!("return {}", value) }
+            },
+            EX_ReturnNothing => {
+                self.read_object()?;
+                "return".to_string()
+            },
+            EX_Switch => {
+                self.read_object()?;
+                self.read_u8()?;
+                format!("switch ({})", self.expression()?)
+            },
+            EX_Jump => format!("goto {}", self.read_jump()?),
+            EX_JumpIfNot => {
+                let target = self.read_jump()?;
+                format!("if (!{}) goto {}", parenthesize(&self.expression()?), target)
+            },
+            EX_Stop => "stop".to_string(),
+            EX_Assert => {
+                self.read_u16()?;
+                self.read_u8()?;
+                format!("assert({})", self.expression()?)
+            },
+            EX_Case => match self.read_u16()? {
+                NO_OFFSET => "default:".to_string(),
+                _ => format!("case {}:", self.expression()?)
+            },
+            EX_Nothing | EX_EmptyParmValue | EX_EndOfScript | EX_EndFunctionParms | EX_EndParmValue => String::new(),
+            EX_LabelTable => {
+                let mut labels = vec![];
+                loop {
+                    let name = self.read_name()?;
+                    let offset = self.read_i32()?;
+                    if name == "None" {
+                        break;
+                    }
+
+                    labels.push(format!("{}={}", name, label(u32::try_from(offset).unwrap_or_default())));
+                }
+                format!("// labels: {}", labels.join(", "))
+            },
+            EX_GotoLabel => format!("goto {}", self.expression()?),
+            EX_EatReturnValue => {
+                self.read_object()?;
+                self.expression()?
+            },
+            EX_Let | EX_LetBool | EX_LetDelegate => {
+                let variable = self.expression()?;
+                format!("{} = {}", variable, self.expression()?)
+            },
+            EX_DynArrayElement | EX_ArrayElement => {
+                let index = self.expression()?;
+                format!("{}[{}]", self.expression()?, index)
+            },
+            EX_New => {
+                let parts = (0..5).map(|_| self.expression()).collect::<Result<Vec<_>>>()?;
+                let outer = trim_arguments(parts[..3].to_vec());
+                let mut text = if outer.is_empty() { "new".to_string() } else { format!("new ({})", outer.join(", ")) };
+                write!(text, " {}", parts[3])?;
+                if !parts[4].is_empty() {
+                    write!(text, "({})", parts[4])?;
+                }
+                text
+            },
+            EX_ClassContext | EX_Context => {
+                let object = self.expression()?;
+                self.read_u16()?;
+                self.read_object()?;
+                self.read_u8()?;
+                let member = self.expression()?;
+                if token == EX_ClassContext { format!("{}.static.{}", object, member) } else { format!("{}.{}", object, member) }
+            },
+            EX_InterfaceContext => self.expression()?,
+            EX_MetaCast => {
+                let class = self.read_object_name()?;
+                format!("class<{}>({})", class, self.expression()?)
+            },
+            EX_DynamicCast | EX_InterfaceCast => {
+                let class = self.read_object_name()?;
+                format!("{}({})", class, self.expression()?)
+            },
+            EX_Self => "self".to_string(),
+            EX_Skip => {
+                self.read_u16()?;
+                self.expression()?
+            },
+            EX_VirtualFunction | EX_GlobalFunction => {
+                let name = self.read_name()?;
+                let call = self.call(&name)?;
+                if token == EX_GlobalFunction { format!("global.{}", call) } else { call }
+            },
+            EX_FinalFunction => {
+                let name = self.read_object_name()?;
+                self.call(&name)?
+            },
+            EX_DelegateFunction => {
+                self.read_u8()?;
+                self.read_object()?;
+                let name = self.read_name()?;
+                self.call(&name)?
+            },
+            EX_DelegateProperty => {
+                let name = self.read_name()?;
+                self.read_object()?;
+                name
+            },
+            EX_InstanceDelegate => self.read_name()?,
+            EX_IntConst => self.read_i32()?.to_string(),
+            EX_FloatConst => format_float(self.read_f32()?),
+            EX_StringConst => quote(&self.read_string()?),
+            EX_UnicodeStringConst => quote(&self.read_unicode_string()?),
+            EX_ObjectConst => {
+                let index = self.read_object()?;
+                let class = self.object_class(index);
+                let path = self.package.get_object_path(index).unwrap_or_else(|| self.object_name(index));
+                format!("{}'{}'", class, path)
+            },
+            EX_NameConst => format!("'{}'", self.read_name()?),
+            EX_RotationConst => format!("rot({}, {}, {})", self.read_i32()?, self.read_i32()?, self.read_i32()?),
+            EX_VectorConst => format!("vect({}, {}, {})", format_float(self.read_f32()?), format_float(self.read_f32()?), format_float(self.read_f32()?)),
+            EX_ByteConst | EX_IntConstByte => self.read_u8()?.to_string(),
+            EX_IntZero => "0".to_string(),
+            EX_IntOne => "1".to_string(),
+            EX_True => "true".to_string(),
+            EX_False => "false".to_string(),
+            EX_NoObject | EX_EmptyDelegate => "None".to_string(),
+            EX_BoolVariable => self.expression()?,
+            EX_Iterator => {
+                let iterator = self.expression()?;
+                self.read_jump()?;
+                format!("foreach {}", iterator)
+            },
+            EX_IteratorPop => "// iterator pop".to_string(),
+            EX_IteratorNext => "// iterator next".to_string(),
+            EX_StructCmpEq | EX_StructCmpNe => {
+                self.read_object()?;
+                let left = self.expression()?;
+                let right = self.expression()?;
+                format!("({} {} {})", left, if token == EX_StructCmpEq { "==" } else { "!=" }, right)
+            },
+            EX_EqualEqual_DelDel | EX_NotEqual_DelDel | EX_EqualEqual_DelFunc | EX_NotEqual_DelFunc => {
+                let operator = if token == EX_EqualEqual_DelDel || token == EX_EqualEqual_DelFunc { "==" } else { "!=" };
+                format!("({})", self.arguments()?.join(&format!(" {} ", operator)))
+            },
+            EX_StructMember => {
+                let member = self.read_object_name()?;
+                self.read_object()?;
+                self.read_u8()?;
+                self.read_u8()?;
+                format!("{}.{}", self.expression()?, member)
+            },
+            EX_DynArrayLength => format!("{}.Length", self.expression()?),
+            EX_PrimitiveCast => {
+                let cast = self.read_u8()?;
+                let target = PRIMITIVE_CASTS.iter().find(|(token, _)| *token == cast).map_or_else(|| format!("cast_0x{:02X}", cast), |(_, name)| name.to_string());
+                format!("{}({})", target, self.expression()?)
+            },
+            EX_DynArrayInsert => self.dynamic_array_call("Insert", false)?,
+            EX_DynArrayRemove => self.dynamic_array_call("Remove", false)?,
+            EX_DynArrayAdd => self.dynamic_array_call("Add", false)?,
+            EX_DynArrayAddItem => self.dynamic_array_call("AddItem", true)?,
+            EX_DynArrayRemoveItem => self.dynamic_array_call("RemoveItem", true)?,
+            EX_DynArrayInsertItem => self.dynamic_array_call("InsertItem", true)?,
+            EX_DynArrayFind | EX_DynArrayFindStruct => self.dynamic_array_call("Find", true)?,
+            EX_DynArraySort => self.dynamic_array_call("Sort", true)?,
+            EX_DynArrayIterator => {
+                let array = self.expression()?;
+                let item = self.expression()?;
+                let has_index = self.read_u8()? != 0;
+                let index = self.expression()?;
+                self.read_jump()?;
+                if has_index { format!("foreach {}({}, {})", array, item, index) } else { format!("foreach {}({})", array, item) }
+            },
+            EX_DebugInfo => {
+                self.read_i32()?;
+                self.read_i32()?;
+                self.read_i32()?;
+                self.read_u8()?;
+                String::new()
+            },
+            EX_Conditional => {
+                let condition = self.expression()?;
+                self.read_u16()?;
+                let when_true = self.expression()?;
+                self.read_u16()?;
+                format!("({} ? {} : {})", condition, when_true, self.expression()?)
+            },
+            EX_DefaultParmValue => {
+                self.read_u16()?;
+                let mut values = vec![];
+                while self.peek().is_some_and(|token| token != EX_EndParmValue) {
+                    values.push(self.expression()?);
+                }
+                self.read_u8()?;
+                format!("// default: {}", trim_arguments(values).join("; "))
+            },
+            EX_FilterEditorOnly => {
+                self.read_u16()?;
+                "// editor only".to_string()
+            },
+            token if token >= EX_FirstNative => self.native_call(u16::from(token))?,
+            token if token >= EX_ExtendedNative => {
+                let index = (u16::from(token - EX_ExtendedNative) << 8) | u16::from(self.read_u8()?);
+                self.native_call(index)?
+            },
+            token => return Err(Box::new(ParserError::new(&format!("Unknown token 0x{:02X} at 0x{:04X}", token, offset))))
+        };
+
+        Ok(text)
+    }
+
+    fn object_class(&self, index: i32) -> String {
+        if index < 0 {
+            return usize::try_from(-index - 1).ok()
+                .and_then(|import| self.package.imports.get(import))
+                .and_then(|import| self.package.get_name(&import.class_name))
+                .unwrap_or_else(|| "Object".to_string());
+        }
+
+        usize::try_from(index - 1).ok()
+            .and_then(|export| self.package.exports.get(export))
+            .and_then(|export| self.package.get_class_name(export))
+            .unwrap_or_else(|| "Object".to_string())
+    }
+
+}
+
+/// Decodes the bytecode of a struct into statements. Decoding stops at the first token it
+/// can't read, with a comment saying where.
+pub fn disassemble<F: GameFile>(package: &UnPackage<F>, natives: &FNativeTable, script: &UStruct) -> Vec<FScriptStatement> {
+    let pointer_size = if usize::try_from(script.bytecode_size).is_ok_and(|size| size != script.script.len()) { 8 } else { 4 };
+    let mut decoder = Decoder { package, natives, script: &script.script, position: 0, memory: 0, pointer_size, jump_targets: BTreeSet::new(), depth: 0 };
+
+    let mut statements = vec![];
+    while decoder.position < script.script.len() {
+        let offset = decoder.memory;
+        let is_end = decoder.peek() == Some(EX_EndOfScript);
+        match decoder.expression() {
+            Ok(text) if text.is_empty() => {},
+            Ok(text) => statements.push(FScriptStatement { offset, text, is_jump_target: false }),
+            Err(err) => {
+                statements.push(FScriptStatement { offset, text: format!("// failed to decode: {}", err), is_jump_target: false });
+                break;
+            }
+        }
+
+        if is_end {
+            break;
+        }
+    }
+
+    for statement in &mut statements {
+        statement.is_jump_target = decoder.jump_targets.contains(&statement.offset);
+    }
+
+    statements
+}
+
+/// The pseudo-code of a function export: its declaration, its locals and its statements.
+pub fn function_to_string<F: GameFile>(package: &UnPackage<F>, reader: &mut FPackageReader, natives: &FNativeTable, index: i32) -> Result<String> {
+    let export = package.exports.get(usize::try_from(index - 1)?).ok_or_else(|| ParserError::new(&format!("Invalid function index {}", index)))?;
+    let function = UFunction::read(package, reader.read_export(export)?)?;
+    let name = package.get_object_name(index).unwrap_or_default();
+
+    let mut parameters = vec![];
+    let mut locals = vec![];
+    let mut return_type = None;
+    for child in read_children(package, reader, function.base.children)? {
+        let child_export = &package.exports[usize::try_from(child - 1)?];
+        let class = package.get_class_name(child_export).unwrap_or_default();
+        if !class.ends_with("Property") {
+            continue;
+        }
+
+        let property = UProperty::read(package, &class, reader.read_export(child_export)?)?;
+        let type_name = property_type(package, reader, child)?;
+        let child_name = package.get_object_name(child).unwrap_or_default();
+        if property.has_flag(CPF_ReturnParm) {
+            return_type = Some(type_name);
+        } else if property.has_flag(CPF_Parm) {
+            let modifiers = [(CPF_OptionalParm, "optional "), (CPF_OutParm, "out "), (CPF_CoerceParm, "coerce "), (CPF_Const, "const ")];
+            let modifiers: String = modifiers.iter().filter(|(flag, _)| property.has_flag(*flag)).map(|(_, modifier)| *modifier).collect();
+            parameters.push(format!("{}{} {}", modifiers, type_name, child_name));
+        } else {
+            locals.push(format!("local {} {};", type_name, child_name));
+        }
+    }
+
+    let mut text = format!("// {}\n", package.get_object_path(index).unwrap_or_default());
+    write!(text, "{}", function_modifiers(&function))?;
+    if let Some(return_type) = return_type {
+        write!(text, "{} ", return_type)?;
+    }
+    let display_name = if function.has_flag(FUNC_Operator) { &function.friendly_name } else { &name };
+    write!(text, "{}({})", display_name, parameters.join(", "))?;
+
+    if !function.has_flag(FUNC_Defined) || function.base.script.is_empty() {
+        text.push_str(";\n");
+        return Ok(text);
+    }
+
+    text.push_str("\n{\n");
+    for local in &locals {
+        writeln!(text, "    {}", local)?;
+    }
+    if !locals.is_empty() {
+        text.push('\n');
+    }
+    write_statements(&mut text, &disassemble(package, natives, &function.base))?;
+    text.push_str("}\n");
+
+    Ok(text)
+}
+
+/// The pseudo-code of a state export, or of the state code of a class: its functions by name
+/// and its labelled code.
+pub fn state_to_string<F: GameFile>(package: &UnPackage<F>, reader: &mut FPackageReader, natives: &FNativeTable, index: i32) -> Result<String> {
+    let export = package.exports.get(usize::try_from(index - 1)?).ok_or_else(|| ParserError::new(&format!("Invalid state index {}", index)))?;
+    let state = UState::read(package, reader.read_export(export)?, export.class_index == 0)?;
+    let name = package.get_object_name(index).unwrap_or_default();
+
+    let mut text = format!("// {}\n", package.get_object_path(index).unwrap_or_default());
+    writeln!(text, "{} {}\n{{", if export.class_index == 0 { "class" } else { "state" }, name)?;
+    if !state.functions.is_empty() {
+        let functions: Vec<&str> = state.functions.iter().map(|(name, _)| name.as_str()).collect();
+        writeln!(text, "    // functions: {}\n", functions.join(", "))?;
+    }
+    write_statements(&mut text, &disassemble(package, natives, &state.base))?;
+    text.push_str("}\n");
+
+    Ok(text)
+}
+
+fn write_statements(text: &mut String, statements: &[FScriptStatement]) -> Result<()> {
+    for statement in statements {
+        if statement.is_jump_target {
+            writeln!(text, "{}:", label(statement.offset))?;
+        }
+
+        let terminator = if statement.text.starts_with("//") || statement.text.ends_with(':') { "" } else { ";" };
+        writeln!(text, "    /* {:04X} */ {}{}", statement.offset, statement.text, terminator)?;
+    }
+
+    Ok(())
+}
+
+fn function_modifiers(function: &UFunction) -> String {
+    let mut modifiers = String::new();
+    let flags = [
+        (FUNC_Private, "private "), (FUNC_Protected, "protected "), (FUNC_Static, "static "), (FUNC_Final, "final "),
+        (FUNC_Simulated, "simulated "), (FUNC_Singular, "singular "), (FUNC_Exec, "exec "), (FUNC_Latent, "latent "),
+        (FUNC_Iterator, "iterator ")
+    ];
+    for (flag, modifier) in flags {
+        if function.has_flag(flag) {
+            modifiers.push_str(modifier);
+        }
+    }
+
+    if function.has_flag(FUNC_Native) {
+        if function.native_index != 0 {
+            write!(modifiers, "native({}) ", function.native_index).ok();
+        } else {
+            modifiers.push_str("native ");
+        }
+    }
+
+    if function.has_flag(FUNC_Net) {
+        modifiers.push_str(if function.has_flag(FUNC_NetReliable) { "reliable " } else { "unreliable " });
+        if function.has_flag(FUNC_NetServer) {
+            modifiers.push_str("server ");
+        } else if function.has_flag(FUNC_NetClient) {
+            modifiers.push_str("client ");
+        }
+    }
+
+    let keyword = if function.has_flag(FUNC_Delegate) {
+        "delegate".to_string()
+    } else if function.has_flag(FUNC_Operator) {
+        if function.has_flag(FUNC_PreOperator) {
+            "preoperator".to_string()
+        } else if function.operator_precedence > 0 {
+            format!("operator({})", function.operator_precedence)
+        } else {
+            "operator".to_string()
+        }
+    } else if function.has_flag(FUNC_Event) {
+        "event".to_string()
+    } else {
+        "function".to_string()
+    };
+
+    format!("{}{} ", modifiers, keyword)
+}
+
+/// Drops the trailing skipped optional arguments of a call.
+fn trim_arguments(mut arguments: Vec<String>) -> Vec<String> {
+    while arguments.last().is_some_and(String::is_empty) {
+        arguments.pop();
+    }
+
+    arguments
+}
+
+/// Wraps an expression in parentheses unless they already enclose all of it. Parentheses and
+/// quotes are ASCII, so the expression is scanned by byte, skipping the string constants.
+fn parenthesize(expression: &str) -> String {
+    let bytes = expression.as_bytes();
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut enclosed = bytes.first() == Some(&b'(');
+    let mut offset = 0;
+    while enclosed && offset < bytes.len() {
+        match bytes[offset] {
+            b'\\' if quoted => offset += 1,
+            b'"' => quoted = !quoted,
+            b'(' if !quoted => depth += 1,
+            b')' if !quoted => depth -= 1,
+            _ => {}
+        }
+
+        enclosed = depth > 0 || offset + 1 >= bytes.len();
+        offset += 1;
+    }
+
+    if enclosed && depth == 0 { expression.to_string() } else { format!("({})", expression) }
+}
+
+fn label(offset: u32) -> String {
+    format!("L{:04X}", offset)
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+fn format_float(value: f32) -> String {
+    let text = value.to_string();
+    if text.contains(['.', 'e', 'N', 'i']) { text } else { format!("{}.0", text) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::file::OsGameFile;
+    use crate::package::{FNameEntry, FObjectExport};
+
+    /// A package exporting `Health`, `Target`, `Location` and `Jump`, the export indices being
+    /// those of their names.
+    fn package() -> UnPackage<OsGameFile> {
+        let mut package = UnPackage::new(OsGameFile::new(PathBuf::from("Test.upk")), Arc::new(Mutex::new(vec![])));
+        package.names = ["None", "Health", "Target", "Location", "Jump"].iter()
+            .map(|name| FNameEntry { name: name.to_string(), flags: 0 })
+            .collect();
+        package.exports = (1..5).map(|index| FObjectExport { object_name: FName { index, number: 0 }, ..Default::default() }).collect();
+
+        package
+    }
+
+    fn object(index: i32) -> [u8; 4] {
+        index.to_le_bytes()
+    }
+
+    fn name(index: i32) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[..4].copy_from_slice(&index.to_le_bytes());
+        bytes
+    }
+
+    /// Decodes `script`, whose object references take eight bytes once loaded if `wide_pointers` is set.
+    fn decode(natives: &FNativeTable, script: &[&[u8]], wide_pointers: bool) -> Vec<(u32, String, bool)> {
+        let script = script.concat();
+        let function = UStruct {
+            properties: vec![], next: 0, super_struct: 0, children: 0, line: 0, text_pos: 0,
+            bytecode_size: i32::try_from(script.len() + if wide_pointers { 4 } else { 0 }).unwrap(),
+            script
+        };
+
+        disassemble(&package(), natives, &function).into_iter()
+            .map(|statement| (statement.offset, statement.text, statement.is_jump_target))
+            .collect()
+    }
+
+    fn texts(natives: &FNativeTable, script: &[&[u8]]) -> Vec<String> {
+        decode(natives, script, false).into_iter().map(|(_, text, _)| text).collect()
+    }
+
+    #[test]
+    fn jumps() {
+        let script: [&[u8]; 6] = [&[EX_JumpIfNot, 0x0B, 0, EX_InstanceVariable], &object(1), &[EX_Jump, 0x0B, 0], &[EX_Return, EX_Nothing], &[EX_EndOfScript], &[EX_IntOne]];
+
+        assert_eq!(decode(&FNativeTable::new(), &script, false), vec![
+            (0x00, "if (!(Health)) goto L000B".to_string(), false),
+            (0x08, "goto L000B".to_string(), false),
+            (0x0B, "return".to_string(), true)
+        ]);
+    }
+
+    #[test]
+    fn jumps_with_wide_pointers() {
+        // The object reference takes eight bytes once loaded, moving the offsets jumps use.
+        let script: [&[u8]; 5] = [&[EX_JumpIfNot, 0x0F, 0, EX_InstanceVariable], &object(1), &[EX_Jump, 0x0F, 0], &[EX_Return, EX_Nothing], &[EX_EndOfScript]];
+
+        assert_eq!(decode(&FNativeTable::new(), &script, true), vec![
+            (0x00, "if (!(Health)) goto L000F".to_string(), false),
+            (0x0C, "goto L000F".to_string(), false),
+            (0x0F, "return".to_string(), true)
+        ]);
+    }
+
+    #[test]
+    fn function_calls() {
+        let script: [&[u8]; 14] = [
+            &[EX_VirtualFunction], &name(4), &[EX_IntConst], &5i32.to_le_bytes(), &[EX_EmptyParmValue, EX_EndFunctionParms],
+            &[EX_GlobalFunction], &name(4), &[EX_EndFunctionParms],
+            &[EX_FinalFunction], &object(4), &[EX_InstanceVariable], &object(1), &[EX_EndFunctionParms],
+            &[EX_Self]
+        ];
+
+        assert_eq!(texts(&FNativeTable::new(), &script), vec!["Jump(5)", "global.Jump()", "Jump(Health)", "self"]);
+    }
+
+    #[test]
+    fn native_calls() {
+        let mut natives = FNativeTable::new();
+        natives.functions.insert(0x81, FNativeFunction { name: "!".to_string(), function_flags: FUNC_Operator | FUNC_PreOperator });
+        natives.functions.insert(0x90, FNativeFunction { name: "+".to_string(), function_flags: FUNC_Operator });
+        natives.functions.insert(0x102, FNativeFunction { name: "Abs".to_string(), function_flags: 0 });
+
+        let script: [&[u8]; 5] = [
+            &[0x90, EX_IntOne, EX_IntZero, EX_EndFunctionParms],
+            &[0x81, EX_IntOne, EX_EndFunctionParms],
+            &[EX_ExtendedNative + 1, 0x02, EX_IntZero, EX_EndFunctionParms],
+            &[0x91, EX_IntOne, EX_EndFunctionParms],
+            &[EX_EndOfScript]
+        ];
+
+        assert_eq!(texts(&natives, &script), vec!["(1 + 0)", "!1", "Abs(0)", "native_145(1)"]);
+    }
+
+    #[test]
+    fn context_expressions() {
+        let context = |token: u8, member: &[u8]| [&[token, EX_InstanceVariable][..], &object(2), &[5, 0], &object(0), &[0], member].concat();
+        let location = [&[EX_InstanceVariable][..], &object(3)].concat();
+        let call = [&[EX_VirtualFunction][..], &name(4), &[EX_EndFunctionParms]].concat();
+
+        let script: [&[u8]; 5] = [&context(EX_Context, &location), &context(EX_ClassContext, &location), &context(EX_Context, &call), &[EX_JumpIfNot, 0, 0], &context(EX_Context, &location)];
+
+        assert_eq!(texts(&FNativeTable::new(), &script), vec![
+            "Target.Location",
+            "Target.static.Location",
+            "Target.Jump()",
+            "if (!(Target.Location)) goto L0000"
+        ]);
+    }
+
+    #[test]
+    fn truncated_script() {
+        let statements = texts(&FNativeTable::new(), &[&[EX_IntOne, EX_IntConst, 1, 2]]);
+
+        assert_eq!(statements[0], "1");
+        assert!(statements[1].starts_with("// failed to decode"));
+    }
+
+    #[test]
+    fn parentheses() {
+        assert_eq!(parenthesize("Health"), "(Health)");
+        assert_eq!(parenthesize("(a + b)"), "(a + b)");
+        assert_eq!(parenthesize("(a) + (b)"), "((a) + (b))");
+        assert_eq!(parenthesize("(a"), "((a)");
+        // Parentheses in string constants don't count, nor do escaped quotes end them.
+        assert_eq!(parenthesize("(\")\" $ a)"), "(\")\" $ a)");
+        assert_eq!(parenthesize("(\"(\" $ a) $ b"), "((\"(\" $ a) $ b)");
+        assert_eq!(parenthesize("(\"\\\")\")"), "(\"\\\")\")");
+        // Multi-byte characters don't shift the end.
+        assert_eq!(parenthesize("(\"é\" $ ü)"), "(\"é\" $ ü)");
+        assert_eq!(parenthesize("(a) $ é"), "((a) $ é)");
+    }
+}
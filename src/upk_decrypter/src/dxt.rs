@@ -0,0 +1,262 @@
+//! Decoders for the block compressed pixel formats, all producing RGBA8.
+
+use crate::{Result, ParserError};
+
+const BLOCK_DIM: usize = 4;
+
+/// The number of 4x4 blocks along a dimension, at least one even for the smallest mips.
+pub(crate) fn block_count(size: usize) -> usize {
+    size.div_ceil(BLOCK_DIM).max(1)
+}
+
+pub(crate) fn decode_dxt1(data: &[u8], width: usize, height: usize) -> Result<Vec<u8>> {
+    decode_blocks(data, width, height, 8, |block, pixels| decode_color_block(block, pixels, true))
+}
+
+pub(crate) fn decode_dxt3(data: &[u8], width: usize, height: usize) -> Result<Vec<u8>> {
+    decode_blocks(data, width, height, 16, |block, pixels| {
+        decode_color_block(&block[8..], pixels, false);
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let nibble = (block[i / 2] >> ((i % 2) * 4)) & 0x0F;
+            pixel[3] = nibble * 17;
+        }
+    })
+}
+
+pub(crate) fn decode_dxt5(data: &[u8], width: usize, height: usize) -> Result<Vec<u8>> {
+    decode_blocks(data, width, height, 16, |block, pixels| {
+        decode_color_block(&block[8..], pixels, false);
+        let alpha = decode_alpha_block(&block[..8]);
+        for (pixel, alpha) in pixels.iter_mut().zip(alpha) {
+            pixel[3] = alpha;
+        }
+    })
+}
+
+/// Two-channel normal maps. Red and green come from the two alpha-style blocks and blue is
+/// rebuilt from them, as the normal is unit length.
+pub(crate) fn decode_bc5(data: &[u8], width: usize, height: usize) -> Result<Vec<u8>> {
+    decode_blocks(data, width, height, 16, |block, pixels| {
+        let red = decode_alpha_block(&block[..8]);
+        let green = decode_alpha_block(&block[8..]);
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let x = f32::from(red[i]) / 127.5 - 1.0;
+            let y = f32::from(green[i]) / 127.5 - 1.0;
+            let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+            *pixel = [red[i], green[i], ((z + 1.0) * 127.5) as u8, 255];
+        }
+    })
+}
+
+fn decode_blocks<F>(data: &[u8], width: usize, height: usize, block_size: usize, decode: F) -> Result<Vec<u8>>
+where F: Fn(&[u8], &mut [[u8; 4]; 16]) {
+    let blocks_x = block_count(width);
+    let blocks_y = block_count(height);
+    if data.len() < blocks_x * blocks_y * block_size {
+        return Err(Box::new(ParserError::new(&format!("Not enough data for a {}x{} mip: {} bytes", width, height, data.len()))));
+    }
+
+    let mut result = vec![0u8; width * height * 4];
+    let mut pixels = [[0u8; 4]; 16];
+    for by in 0..blocks_y {
+        for bx in 0..blocks_x {
+            let offset = (by * blocks_x + bx) * block_size;
+            decode(&data[offset..offset + block_size], &mut pixels);
+
+            // Blocks hang over the edge of mips smaller than 4x4, those pixels are dropped.
+            for py in 0..BLOCK_DIM {
+                let y = by * BLOCK_DIM + py;
+                if y >= height {
+                    break;
+                }
+
+                for px in 0..BLOCK_DIM {
+                    let x = bx * BLOCK_DIM + px;
+                    if x >= width {
+                        break;
+                    }
+
+                    let target = (y * width + x) * 4;
+                    result[target..target + 4].copy_from_slice(&pixels[py * BLOCK_DIM + px]);
+                }
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+fn rgb565(color: u16) -> [u8; 4] {
+    let r = ((color >> 11) & 0x1F) as u8;
+    let g = ((color >> 5) & 0x3F) as u8;
+    let b = (color & 0x1F) as u8;
+
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2), 255]
+}
+
+fn mix(a: [u8; 4], b: [u8; 4], weight_a: u16, weight_b: u16) -> [u8; 4] {
+    let total = weight_a + weight_b;
+    let channel = |i: usize| ((u16::from(a[i]) * weight_a + u16::from(b[i]) * weight_b) / total) as u8;
+
+    [channel(0), channel(1), channel(2), 255]
+}
+
+/// The DXT1 color block, also the second half of DXT3/DXT5 blocks where it's always in 4-color mode.
+fn decode_color_block(block: &[u8], pixels: &mut [[u8; 4]; 16], allow_transparent: bool) {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let color0 = rgb565(c0);
+    let color1 = rgb565(c1);
+
+    let palette = if c0 > c1 || !allow_transparent {
+        [color0, color1, mix(color0, color1, 2, 1), mix(color0, color1, 1, 2)]
+    } else {
+        [color0, color1, mix(color0, color1, 1, 1), [0, 0, 0, 0]]
+    };
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = palette[((indices >> (i * 2)) & 0x03) as usize];
+    }
+}
+
+/// The interpolated 8-bit block of DXT5 alpha and BC4/BC5 channels.
+fn decode_alpha_block(block: &[u8]) -> [u8; 16] {
+    let a0 = u16::from(block[0]);
+    let a1 = u16::from(block[1]);
+
+    let mut palette = [0u8; 8];
+    palette[0] = block[0];
+    palette[1] = block[1];
+    if a0 > a1 {
+        for i in 1..7u16 {
+            palette[usize::from(i) + 1] = (((7 - i) * a0 + i * a1) / 7) as u8;
+        }
+    } else {
+        for i in 1..5u16 {
+            palette[usize::from(i) + 1] = (((5 - i) * a0 + i * a1) / 5) as u8;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+
+    let mut bits = 0u64;
+    for (i, byte) in block[2..8].iter().enumerate() {
+        bits |= u64::from(*byte) << (i * 8);
+    }
+
+    let mut result = [0u8; 16];
+    for (i, value) in result.iter_mut().enumerate() {
+        *value = palette[((bits >> (i * 3)) & 0x07) as usize];
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: u16 = 0xF800;
+    const BLUE: u16 = 0x001F;
+
+    fn color_block(c0: u16, c1: u16, indices: u32) -> Vec<u8> {
+        [c0.to_le_bytes().as_slice(), &c1.to_le_bytes(), &indices.to_le_bytes()].concat()
+    }
+
+    /// An alpha block with a 3-bit index for each pixel, the rest of the pixels on index 0.
+    fn alpha_block(a0: u8, a1: u8, indices: &[u64]) -> Vec<u8> {
+        let bits = indices.iter().enumerate().fold(0u64, |bits, (i, index)| bits | (index << (i * 3)));
+        [&[a0, a1], &bits.to_le_bytes()[..6]].concat()
+    }
+
+    fn pixel(rgba: &[u8], width: usize, x: usize, y: usize) -> [u8; 4] {
+        let offset = (y * width + x) * 4;
+        rgba[offset..offset + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn bc1_four_color_block() {
+        // Pixels 0 to 3 use the four palette entries, the rest the first one.
+        let rgba = decode_dxt1(&color_block(RED, BLUE, 0xE4), 4, 4).unwrap();
+        assert_eq!(pixel(&rgba, 4, 0, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(&rgba, 4, 1, 0), [0, 0, 255, 255]);
+        assert_eq!(pixel(&rgba, 4, 2, 0), [170, 0, 85, 255]);
+        assert_eq!(pixel(&rgba, 4, 3, 0), [85, 0, 170, 255]);
+        assert_eq!(pixel(&rgba, 4, 3, 3), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn bc1_three_color_block_with_transparency() {
+        // color0 <= color1 switches to the midpoint and a transparent black entry.
+        let rgba = decode_dxt1(&color_block(BLUE, RED, 0xE4), 4, 4).unwrap();
+        assert_eq!(pixel(&rgba, 4, 0, 0), [0, 0, 255, 255]);
+        assert_eq!(pixel(&rgba, 4, 1, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(&rgba, 4, 2, 0), [127, 0, 127, 255]);
+        assert_eq!(pixel(&rgba, 4, 3, 0), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn bc3_color_block_is_always_four_color() {
+        let block = [alpha_block(255, 255, &[]), color_block(BLUE, RED, 0xE4)].concat();
+        let rgba = decode_dxt5(&block, 4, 4).unwrap();
+        assert_eq!(pixel(&rgba, 4, 2, 0), [85, 0, 170, 255]);
+        assert_eq!(pixel(&rgba, 4, 3, 0), [170, 0, 85, 255]);
+    }
+
+    #[test]
+    fn bc3_eight_alpha_block() {
+        // alpha0 > alpha1 interpolates six values between them.
+        let block = [alpha_block(255, 0, &[0, 1, 2, 7]), color_block(RED, RED, 0)].concat();
+        let rgba = decode_dxt5(&block, 4, 4).unwrap();
+        assert_eq!(pixel(&rgba, 4, 0, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(&rgba, 4, 1, 0), [255, 0, 0, 0]);
+        assert_eq!(pixel(&rgba, 4, 2, 0), [255, 0, 0, 218]);
+        assert_eq!(pixel(&rgba, 4, 3, 0), [255, 0, 0, 36]);
+    }
+
+    #[test]
+    fn bc3_six_alpha_block() {
+        // alpha0 <= alpha1 interpolates four values and adds fully transparent and opaque.
+        let block = [alpha_block(0, 255, &[2, 6, 7]), color_block(RED, RED, 0)].concat();
+        let rgba = decode_dxt5(&block, 4, 4).unwrap();
+        assert_eq!(pixel(&rgba, 4, 0, 0)[3], 51);
+        assert_eq!(pixel(&rgba, 4, 1, 0)[3], 0);
+        assert_eq!(pixel(&rgba, 4, 2, 0)[3], 255);
+    }
+
+    #[test]
+    fn bc5_rebuilds_blue() {
+        // Red at +1 leaves nothing for blue, a flat normal points straight out.
+        let block = [alpha_block(255, 128, &[0, 1]), alpha_block(128, 128, &[])].concat();
+        let rgba = decode_bc5(&block, 4, 4).unwrap();
+        assert_eq!(pixel(&rgba, 4, 0, 0), [255, 128, 127, 255]);
+        assert_eq!(pixel(&rgba, 4, 1, 0), [128, 128, 254, 255]);
+    }
+
+    #[test]
+    fn dimensions_not_multiple_of_four() {
+        // A 5x3 mip takes two blocks, the second one contributing its first column only.
+        let data = [color_block(RED, RED, 0), color_block(BLUE, BLUE, 0)].concat();
+        let rgba = decode_dxt1(&data, 5, 3).unwrap();
+        assert_eq!(rgba.len(), 5 * 3 * 4);
+        assert_eq!(pixel(&rgba, 5, 3, 2), [255, 0, 0, 255]);
+        assert_eq!(pixel(&rgba, 5, 4, 0), [0, 0, 255, 255]);
+        assert_eq!(pixel(&rgba, 5, 4, 2), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn mip_smaller_than_a_block() {
+        // Index 1 at pixel 5 of the block, which is (1, 1) of a 2x2 mip.
+        let rgba = decode_dxt1(&color_block(RED, BLUE, 1 << (5 * 2)), 2, 2).unwrap();
+        assert_eq!(rgba.len(), 2 * 2 * 4);
+        assert_eq!(pixel(&rgba, 2, 0, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(&rgba, 2, 1, 1), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn too_little_data() {
+        assert!(decode_dxt1(&color_block(RED, BLUE, 0), 8, 4).is_err());
+        assert!(decode_bc5(&[0u8; 8], 4, 4).is_err());
+    }
+}
@@ -1,11 +1,12 @@
 use aes::Aes256;
-use block_modes::{BlockMode, Ecb, block_padding::ZeroPadding};
+use block_modes::{BlockMode, Ecb, block_padding::NoPadding};
 
-use std::fmt::Display;
-use std::io::SeekFrom;
-use std::ops::Add;
+use std::fmt::{Debug, Display};
+use std::ops::{Add, Range};
+use std::sync::{Arc, Mutex};
 
-use crate::archive::FArchive;
+use crate::package::FPackageFileSummary;
+use crate::profile::GameProfile;
 use crate::{Result, ParserError};
 
 const KEY_SIZE: usize = 32;
@@ -32,7 +33,7 @@ impl FAesKey {
     } 
 
     pub fn to_hex(&self) -> String {
-        "0x".to_owned().add(&hex::encode(&self.key))
+        "0x".to_owned().add(&hex::encode(self.key))
     }
 
     pub fn as_bytes(&self) -> &[u8] {
@@ -46,24 +47,73 @@ impl FAesKey {
         result
     }
 
-    pub(crate) fn decrypt<Ar>(&self, archive: &mut Ar, offset: u64, len: usize) -> Result<()> 
-    where Ar: FArchive {
-        let cipher = Ecb::<Aes256, ZeroPadding>::new_from_slices(&self.key, Default::default())?;
+    /// Decrypts `data` in place. The length has to be a multiple of the AES block size,
+    /// the encrypted region is never padded so there's nothing to strip afterwards.
+    pub(crate) fn decrypt(&self, data: &mut [u8]) -> Result<()> {
+        let cipher = Ecb::<Aes256, NoPadding>::new_from_slices(&self.key, Default::default())?;
+        cipher.decrypt(data)?;
 
-        archive.seek(SeekFrom::Start(offset))?;
-        let mut encrypted = vec![0; len];
-        archive.read_bytes_vec(&mut encrypted)?;
+        Ok(())
+    }
+
+}
+
+/// Decrypts the parts of a package that a game encrypts. `UnPackage` asks for the encrypted
+/// range once the summary is read and hands that slice of the file to `decrypt`.
+pub trait PackageCipher: Send + Sync + Debug {
+    /// The byte range of the file that is encrypted, `None` if the package isn't encrypted.
+    fn encrypted_range(&self, summary: &FPackageFileSummary, profile: &GameProfile) -> Result<Option<Range<usize>>>;
+
+    /// Decrypts `data` in place. `is_valid` tells whether a candidate plaintext parses,
+    /// which is how the right key is picked when there are several.
+    fn decrypt(&self, summary: &FPackageFileSummary, data: &mut [u8], is_valid: &dyn Fn(&[u8]) -> bool) -> Result<()>;
+}
+
+/// Rocket League's AES-256-ECB encryption of everything from the name table up to the end
+/// of the header, tried against every known key.
+#[derive(Debug)]
+pub struct RocketLeagueCipher {
+    keys: Arc<Mutex<Vec<FAesKey>>>
+}
+
+impl RocketLeagueCipher {
+
+    pub fn new(keys: Arc<Mutex<Vec<FAesKey>>>) -> Self {
+        Self { keys }
+    }
 
-        let start = usize::try_from(offset)?;
-        let end = start + len;
-        let block = &mut archive.get_mut()[start..end];
-        let decrypted = cipher.decrypt_vec(encrypted.as_mut_slice())?;
-        if decrypted.len() != block.len() {
-            return Err(Box::new(ParserError::new("decrypted block size != encrypted block size")));
+}
+
+impl PackageCipher for RocketLeagueCipher {
+
+    fn encrypted_range(&self, summary: &FPackageFileSummary, profile: &GameProfile) -> Result<Option<Range<usize>>> {
+        if !profile.has_encrypted_chunk_info {
+            return Ok(None);
         }
 
-        block.copy_from_slice(decrypted.as_slice());
-        Ok(())
+        let start = usize::try_from(summary.name_offset)?;
+        let len = usize::try_from((summary.header_size - summary.garbage_size - summary.name_offset + 15) & !15)?;
+
+        Ok(Some(start..start + len))
+    }
+
+    fn decrypt(&self, _summary: &FPackageFileSummary, data: &mut [u8], is_valid: &dyn Fn(&[u8]) -> bool) -> Result<()> {
+        let keys = self.keys.lock().unwrap();
+        if keys.is_empty() {
+            return Err(Box::new(ParserError::new("Package is encrypted but no keys are loaded")));
+        }
+
+        // ECB never fails with a wrong key, it just produces garbage, so every attempt is checked.
+        let mut decrypted = data.to_vec();
+        for key in keys.iter() {
+            decrypted.copy_from_slice(data);
+            if key.decrypt(&mut decrypted).is_ok() && is_valid(&decrypted) {
+                data.copy_from_slice(&decrypted);
+                return Ok(());
+            }
+        }
+
+        Err(Box::new(ParserError::new("None of the keys could decrypt the package")))
     }
 
-}
\ No newline at end of file
+}
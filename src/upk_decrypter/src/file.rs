@@ -5,6 +5,12 @@ pub trait GameFile {
     //fn create_reader(&self) -> FArchive
 
     fn get_filename(&self) -> &String;
+
+    /// Reads a file shipped next to this one, named after it with `suffix` appended
+    /// (like `Foo.upk.uncompressed_size`). `None` if there isn't one.
+    fn read_companion(&self, _suffix: &str) -> Option<Vec<u8>> {
+        None
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -24,6 +30,13 @@ impl GameFile for OsGameFile {
         &self.file_name
     }
 
+    fn read_companion(&self, suffix: &str) -> Option<Vec<u8>> {
+        let mut path = self.path.clone().into_os_string();
+        path.push(suffix);
+
+        std::fs::read(path).ok()
+    }
+
 }
 
 impl OsGameFile {
@@ -0,0 +1,418 @@
+//! A minimal glTF 2.0 binary writer for the mesh exporters.
+//!
+//! Unreal is left-handed with Z up and centimeters, glTF is right-handed with Y up and meters,
+//! so vectors get their Y and Z swapped and positions are scaled down. The swap mirrors the mesh,
+//! which also turns Unreal's clockwise front faces into the counter-clockwise ones glTF expects.
+
+use std::io::Write;
+
+use serde_json::{Value, json};
+
+use crate::{Result, ParserError};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: &[u8; 4] = b"JSON";
+const CHUNK_BIN: &[u8; 4] = b"BIN\0";
+
+const UNITS_PER_METER: f32 = 100.0;
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const UNSIGNED_BYTE: u32 = 5121;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+
+/// A mesh with its vertex attributes in Unreal's coordinates, one primitive per section.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct GltfMesh {
+    pub name: String,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    /// Every UV channel, each with one coordinate per vertex.
+    pub uvs: Vec<Vec<[f32; 2]>>,
+    /// RGBA colors, empty when the mesh has none.
+    pub colors: Vec<[u8; 4]>,
+    pub primitives: Vec<GltfPrimitive>,
+    /// The material slot names, referenced by the primitives.
+    pub materials: Vec<String>,
+    /// The bones influencing each vertex and their weights, empty for meshes without a skeleton.
+    pub joints: Vec<[u16; 4]>,
+    pub weights: Vec<[u8; 4]>,
+    /// The skeleton in its bind pose, parents before their children.
+    pub bones: Vec<GltfBone>,
+    pub animations: Vec<GltfAnimation>
+}
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct GltfBone {
+    pub name: String,
+    pub parent: Option<usize>,
+    /// The transform relative to the parent, in Unreal's coordinates.
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4]
+}
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct GltfAnimation {
+    pub name: String,
+    pub tracks: Vec<GltfTrack>
+}
+
+/// The keys of a bone, in seconds and Unreal's coordinates. Either half can be empty.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct GltfTrack {
+    pub bone: usize,
+    pub translation_times: Vec<f32>,
+    pub translations: Vec<[f32; 3]>,
+    pub rotation_times: Vec<f32>,
+    pub rotations: Vec<[f32; 4]>
+}
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct GltfPrimitive {
+    pub indices: Vec<u32>,
+    pub material: usize
+}
+
+/// Collects the binary buffer along with the views and accessors describing it.
+#[derive(Default)]
+struct GltfBuilder {
+    buffer: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>
+}
+
+impl GltfBuilder {
+
+    fn push_view(&mut self, data: &[u8], target: u32) -> usize {
+        while !self.buffer.len().is_multiple_of(4) {
+            self.buffer.push(0);
+        }
+
+        self.views.push(json!({
+            "buffer": 0,
+            "byteOffset": self.buffer.len(),
+            "byteLength": data.len(),
+            "target": target
+        }));
+        self.buffer.extend_from_slice(data);
+
+        self.views.len() - 1
+    }
+
+    fn push_accessor(&mut self, accessor: Value) -> usize {
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn push_floats<const N: usize>(&mut self, values: &[[f32; N]], kind: &str, bounds: bool) -> usize {
+        let data: Vec<u8> = values.iter().flatten().flat_map(|value| value.to_le_bytes()).collect();
+        let view = self.push_view(&data, ARRAY_BUFFER);
+
+        let mut accessor = json!({ "bufferView": view, "componentType": FLOAT, "count": values.len(), "type": kind });
+        if bounds {
+            let mut min = [f32::MAX; N];
+            let mut max = [f32::MIN; N];
+            for value in values {
+                for i in 0..N {
+                    min[i] = min[i].min(value[i]);
+                    max[i] = max[i].max(value[i]);
+                }
+            }
+            accessor["min"] = json!(min.to_vec());
+            accessor["max"] = json!(max.to_vec());
+        }
+
+        self.push_accessor(accessor)
+    }
+
+    fn push_colors(&mut self, colors: &[[u8; 4]]) -> usize {
+        let view = self.push_view(&colors.concat(), ARRAY_BUFFER);
+        self.push_accessor(json!({ "bufferView": view, "componentType": UNSIGNED_BYTE, "normalized": true, "count": colors.len(), "type": "VEC4" }))
+    }
+
+    fn push_joints(&mut self, joints: &[[u16; 4]]) -> usize {
+        let data: Vec<u8> = joints.iter().flatten().flat_map(|joint| joint.to_le_bytes()).collect();
+        let view = self.push_view(&data, ARRAY_BUFFER);
+        self.push_accessor(json!({ "bufferView": view, "componentType": UNSIGNED_SHORT, "count": joints.len(), "type": "VEC4" }))
+    }
+
+    /// Data that isn't a vertex attribute, like inverse bind matrices and animation keys.
+    fn push_data<const N: usize>(&mut self, values: &[[f32; N]], kind: &str, bounds: bool) -> usize {
+        let index = self.push_floats(values, kind, bounds);
+        let view = self.accessors[index]["bufferView"].as_u64().unwrap_or_default() as usize;
+        if let Some(view) = self.views[view].as_object_mut() {
+            view.remove("target");
+        }
+
+        index
+    }
+
+    fn push_indices(&mut self, indices: &[u32]) -> usize {
+        let data: Vec<u8> = indices.iter().flat_map(|index| index.to_le_bytes()).collect();
+        let view = self.push_view(&data, ELEMENT_ARRAY_BUFFER);
+        self.push_accessor(json!({ "bufferView": view, "componentType": UNSIGNED_INT, "count": indices.len(), "type": "SCALAR" }))
+    }
+
+}
+
+/// Converts a direction from Unreal's axes to glTF's.
+pub(crate) fn convert_direction(vector: [f32; 3]) -> [f32; 3] {
+    [vector[0], vector[2], vector[1]]
+}
+
+/// Converts a position from Unreal's axes and units to glTF's.
+pub(crate) fn convert_position(vector: [f32; 3]) -> [f32; 3] {
+    convert_direction(vector).map(|component| component / UNITS_PER_METER)
+}
+
+/// Converts a rotation from Unreal's axes to glTF's. Mirroring the axes also reverses the
+/// direction of the rotation, hence the negated axis.
+pub(crate) fn convert_rotation(quat: [f32; 4]) -> [f32; 4] {
+    let length = quat.iter().map(|component| component * component).sum::<f32>().sqrt();
+    let [x, y, z, w] = if length > 0.0 { quat.map(|component| component / length) } else { [0.0, 0.0, 0.0, 1.0] };
+
+    [-x, -z, -y, w]
+}
+
+/// A rotation matrix and a translation, enough for bone transforms which are never scaled.
+#[derive(Clone, Copy)]
+struct RigidTransform {
+    rotation: [[f32; 3]; 3],
+    translation: [f32; 3]
+}
+
+impl RigidTransform {
+
+    fn new([x, y, z, w]: [f32; 4], translation: [f32; 3]) -> Self {
+        let rotation = [
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - z * w), 2.0 * (x * z + y * w)],
+            [2.0 * (x * y + z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - x * w)],
+            [2.0 * (x * z - y * w), 2.0 * (y * z + x * w), 1.0 - 2.0 * (x * x + y * y)]
+        ];
+
+        Self { rotation, translation }
+    }
+
+    fn transform(&self, vector: [f32; 3]) -> [f32; 3] {
+        let row = |i: usize| self.rotation[i].iter().zip(vector).map(|(a, b)| a * b).sum::<f32>();
+        [row(0), row(1), row(2)]
+    }
+
+    /// `self` applied after `child`.
+    fn then(&self, child: &Self) -> Self {
+        let mut rotation = [[0.0; 3]; 3];
+        for (i, row) in rotation.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| self.rotation[i][k] * child.rotation[k][j]).sum();
+            }
+        }
+
+        let offset = self.transform(child.translation);
+        Self { rotation, translation: [0, 1, 2].map(|i| offset[i] + self.translation[i]) }
+    }
+
+    fn inverse(&self) -> Self {
+        let mut rotation = [[0.0; 3]; 3];
+        for (i, row) in rotation.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.rotation[j][i];
+            }
+        }
+
+        let inverse = Self { rotation, translation: [0.0; 3] };
+        let offset = inverse.transform(self.translation);
+        Self { rotation, translation: offset.map(|component| -component) }
+    }
+
+    /// The 4x4 matrix in glTF's column-major order.
+    fn to_columns(self) -> [f32; 16] {
+        let r = self.rotation;
+        let t = self.translation;
+        [
+            r[0][0], r[1][0], r[2][0], 0.0,
+            r[0][1], r[1][1], r[2][1], 0.0,
+            r[0][2], r[1][2], r[2][2], 0.0,
+            t[0], t[1], t[2], 1.0
+        ]
+    }
+
+}
+
+/// Writes `mesh` as a `.glb` with a single node.
+pub(crate) fn write_glb<W: Write>(writer: &mut W, mesh: &GltfMesh) -> Result<()> {
+    let vertex_count = mesh.positions.len();
+    if (!mesh.normals.is_empty() && mesh.normals.len() != vertex_count)
+        || (!mesh.colors.is_empty() && mesh.colors.len() != vertex_count)
+        || mesh.uvs.iter().any(|uvs| uvs.len() != vertex_count) {
+        return Err(Box::new(ParserError::new("The vertex attributes don't have the same length")));
+    }
+
+    if let Some(index) = mesh.primitives.iter().flat_map(|primitive| &primitive.indices).find(|index| **index as usize >= vertex_count) {
+        return Err(Box::new(ParserError::new(&format!("Index {} is out of the {} vertices", index, vertex_count))));
+    }
+
+    let skinned = !mesh.bones.is_empty() && !mesh.joints.is_empty();
+    if skinned && (mesh.joints.len() != vertex_count || mesh.weights.len() != vertex_count) {
+        return Err(Box::new(ParserError::new("The vertex influences don't have the same length")));
+    }
+
+    if mesh.bones.iter().enumerate().any(|(index, bone)| bone.parent.is_some_and(|parent| parent >= index)) {
+        return Err(Box::new(ParserError::new("A bone comes before its parent")));
+    }
+
+    if let Some(joint) = mesh.joints.iter().flatten().find(|joint| usize::from(**joint) >= mesh.bones.len()) {
+        return Err(Box::new(ParserError::new(&format!("Joint {} is out of the {} bones", joint, mesh.bones.len()))));
+    }
+
+    let mut builder = GltfBuilder::default();
+    let positions: Vec<[f32; 3]> = mesh.positions.iter().copied().map(convert_position).collect();
+
+    let mut attributes = serde_json::Map::new();
+    attributes.insert(String::from("POSITION"), json!(builder.push_floats(&positions, "VEC3", true)));
+    if !mesh.normals.is_empty() {
+        let normals: Vec<[f32; 3]> = mesh.normals.iter().copied().map(convert_direction).collect();
+        attributes.insert(String::from("NORMAL"), json!(builder.push_floats(&normals, "VEC3", false)));
+    }
+
+    for (channel, uvs) in mesh.uvs.iter().enumerate() {
+        attributes.insert(format!("TEXCOORD_{}", channel), json!(builder.push_floats(uvs, "VEC2", false)));
+    }
+
+    if !mesh.colors.is_empty() {
+        attributes.insert(String::from("COLOR_0"), json!(builder.push_colors(&mesh.colors)));
+    }
+
+    if skinned {
+        attributes.insert(String::from("JOINTS_0"), json!(builder.push_joints(&mesh.joints)));
+        attributes.insert(String::from("WEIGHTS_0"), json!(builder.push_colors(&mesh.weights)));
+    }
+
+    let mut primitives = vec![];
+    for primitive in mesh.primitives.iter().filter(|primitive| !primitive.indices.is_empty()) {
+        let indices = builder.push_indices(&primitive.indices);
+        primitives.push(json!({ "attributes": attributes, "indices": indices, "material": primitive.material }));
+    }
+
+    // The mesh is node 0 and the bones follow it, so bone i is node i + 1.
+    let mut nodes = vec![json!({ "name": mesh.name, "mesh": 0 })];
+    let mut scene_nodes = vec![0];
+    for (index, bone) in mesh.bones.iter().enumerate() {
+        let children: Vec<usize> = (index + 1..mesh.bones.len()).filter(|child| mesh.bones[*child].parent == Some(index)).map(|child| child + 1).collect();
+        let mut node = json!({
+            "name": bone.name,
+            "translation": convert_position(bone.translation).to_vec(),
+            "rotation": convert_rotation(bone.rotation).to_vec()
+        });
+        if !children.is_empty() {
+            node["children"] = json!(children);
+        }
+
+        if bone.parent.is_none() {
+            scene_nodes.push(index + 1);
+        }
+        nodes.push(node);
+    }
+
+    let materials: Vec<Value> = mesh.materials.iter().map(|name| json!({ "name": name })).collect();
+    let mut document = json!({
+        "asset": { "version": "2.0", "generator": "upk_decrypter" },
+        "scene": 0,
+        "scenes": [{ "nodes": scene_nodes }],
+        "meshes": [{ "name": mesh.name, "primitives": primitives }],
+        "materials": materials
+    });
+
+    if skinned {
+        nodes[0]["skin"] = json!(0);
+        let matrices = inverse_bind_matrices(&mesh.bones);
+        let joints: Vec<usize> = (1..=mesh.bones.len()).collect();
+        document["skins"] = json!([{ "inverseBindMatrices": builder.push_data(&matrices, "MAT4", false), "joints": joints }]);
+    }
+
+    if !mesh.bones.is_empty() && !mesh.animations.is_empty() {
+        let animations: Vec<Value> = mesh.animations.iter().filter_map(|animation| push_animation(&mut builder, animation)).collect();
+        if !animations.is_empty() {
+            document["animations"] = json!(animations);
+        }
+    }
+
+    document["nodes"] = json!(nodes);
+    document["buffers"] = json!([{ "byteLength": builder.buffer.len() }]);
+    document["bufferViews"] = json!(builder.views);
+    document["accessors"] = json!(builder.accessors);
+
+    write_container(writer, &serde_json::to_vec(&document)?, &builder.buffer)
+}
+
+/// The inverse of each bone's bind pose in mesh space.
+fn inverse_bind_matrices(bones: &[GltfBone]) -> Vec<[f32; 16]> {
+    let mut globals: Vec<RigidTransform> = Vec::with_capacity(bones.len());
+    for bone in bones {
+        let local = RigidTransform::new(convert_rotation(bone.rotation), convert_position(bone.translation));
+        let global = match bone.parent {
+            Some(parent) => globals[parent].then(&local),
+            None => local
+        };
+        globals.push(global);
+    }
+
+    globals.iter().map(|global| global.inverse().to_columns()).collect()
+}
+
+/// Adds the keys of an animation, unless none of its tracks have any.
+fn push_animation(builder: &mut GltfBuilder, animation: &GltfAnimation) -> Option<Value> {
+    let mut samplers = vec![];
+    let mut channels = vec![];
+    for track in &animation.tracks {
+        let node = track.bone + 1;
+        if !track.translations.is_empty() && track.translations.len() == track.translation_times.len() {
+            let times: Vec<[f32; 1]> = track.translation_times.iter().map(|time| [*time]).collect();
+            let values: Vec<[f32; 3]> = track.translations.iter().copied().map(convert_position).collect();
+            samplers.push(json!({ "input": builder.push_data(&times, "SCALAR", true), "output": builder.push_data(&values, "VEC3", false) }));
+            channels.push(json!({ "sampler": samplers.len() - 1, "target": { "node": node, "path": "translation" } }));
+        }
+
+        if !track.rotations.is_empty() && track.rotations.len() == track.rotation_times.len() {
+            let times: Vec<[f32; 1]> = track.rotation_times.iter().map(|time| [*time]).collect();
+            let values: Vec<[f32; 4]> = track.rotations.iter().copied().map(convert_rotation).collect();
+            samplers.push(json!({ "input": builder.push_data(&times, "SCALAR", true), "output": builder.push_data(&values, "VEC4", false) }));
+            channels.push(json!({ "sampler": samplers.len() - 1, "target": { "node": node, "path": "rotation" } }));
+        }
+    }
+
+    if channels.is_empty() {
+        return None;
+    }
+
+    Some(json!({ "name": animation.name, "samplers": samplers, "channels": channels }))
+}
+
+/// The GLB container, a header followed by the JSON and binary chunks padded to 4 bytes.
+fn write_container<W: Write>(writer: &mut W, document: &[u8], buffer: &[u8]) -> Result<()> {
+    let json_padding = (4 - document.len() % 4) % 4;
+    let bin_padding = (4 - buffer.len() % 4) % 4;
+    let json_length = document.len() + json_padding;
+    let bin_length = buffer.len() + bin_padding;
+    let total = 12 + 8 + json_length + if buffer.is_empty() { 0 } else { 8 + bin_length };
+
+    writer.write_all(GLB_MAGIC)?;
+    writer.write_all(&GLB_VERSION.to_le_bytes())?;
+    writer.write_all(&u32::try_from(total)?.to_le_bytes())?;
+
+    writer.write_all(&u32::try_from(json_length)?.to_le_bytes())?;
+    writer.write_all(CHUNK_JSON)?;
+    writer.write_all(document)?;
+    writer.write_all(&b"   "[..json_padding])?;
+
+    if !buffer.is_empty() {
+        writer.write_all(&u32::try_from(bin_length)?.to_le_bytes())?;
+        writer.write_all(CHUNK_BIN)?;
+        writer.write_all(buffer)?;
+        writer.write_all(&[0u8; 3][..bin_padding])?;
+    }
+
+    Ok(())
+}
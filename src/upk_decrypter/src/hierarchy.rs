@@ -0,0 +1,212 @@
+//! The class hierarchy of the packages: every class under its super class, which is resolved
+//! across packages through the imports, with the properties of its default object.
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use serde_json::{Map, Value, json};
+
+use crate::file::GameFile;
+use crate::json::PropertySerializer;
+use crate::package::{UnPackage, RF_ClassDefaultObject};
+use crate::reader::FPackageReader;
+use crate::script::object_key;
+use crate::Result;
+
+const DEFAULT_PREFIX: &str = "Default__";
+const MAX_TREE_DEPTH: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct FClassInfo {
+    /// The lowercase `package.path` the class is referred to by, from any package.
+    pub key: String,
+    pub package: String,
+    pub name: String,
+    pub super_key: Option<String>,
+    /// The path of the super class as the package refers to it, like `Engine.Actor`.
+    pub super_path: Option<String>,
+    /// The properties of the class default object, with its subobjects expanded.
+    pub defaults: Map<String, Value>
+}
+
+/// Reads the classes exported by `package`, whose file name without extension is `name`.
+pub fn read_classes<F: GameFile>(package: &UnPackage<F>, reader: &mut FPackageReader, name: &str) -> Result<Vec<FClassInfo>> {
+    // The default object of every class, the class being exported by the same package.
+    let mut default_objects = HashMap::new();
+    for (index, export) in package.exports.iter().enumerate().filter(|(_, export)| export.class_index > 0) {
+        let is_default = export.object_flags & RF_ClassDefaultObject != 0
+            || package.get_name(&export.object_name).is_some_and(|name| name.starts_with(DEFAULT_PREFIX));
+        if is_default {
+            default_objects.entry(export.class_index).or_insert(i32::try_from(index)? + 1);
+        }
+    }
+
+    let mut serializer = PropertySerializer::new(package, reader);
+    let mut classes = vec![];
+    for (index, export) in package.exports.iter().enumerate().filter(|(_, export)| export.class_index == 0) {
+        let index = i32::try_from(index)? + 1;
+        let class_name = package.get_object_name(index).unwrap_or_default();
+        let super_path = match export.super_index {
+            0 => None,
+            super_index if super_index < 0 => package.get_object_path(super_index),
+            super_index => package.get_object_path(super_index).map(|path| format!("{}.{}", name, path))
+        };
+
+        let defaults = match default_objects.get(&index).map(|default_object| serializer.object(*default_object)) {
+            Some(Ok(defaults)) => defaults,
+            Some(Err(err)) => {
+                log::warn!("skipped the defaults of {}.{}: {}", name, class_name, err);
+                Map::new()
+            },
+            None => Map::new()
+        };
+
+        classes.push(FClassInfo {
+            key: object_key(package, name, index).unwrap_or_default(),
+            package: name.to_string(),
+            name: class_name,
+            super_key: object_key(package, name, export.super_index),
+            super_path,
+            defaults
+        });
+    }
+
+    Ok(classes)
+}
+
+/// The classes of all the packages under their super classes. Classes whose super class
+/// wasn't loaded are roots next to `Object`.
+#[derive(Debug, Clone, Default)]
+pub struct FClassTree {
+    classes: Vec<FClassInfo>,
+    index: HashMap<String, usize>,
+    /// The subclasses of every class, by name.
+    children: Vec<Vec<usize>>,
+    roots: Vec<usize>
+}
+
+impl FClassTree {
+
+    /// Builds the tree, skipping the classes another package already exported.
+    pub fn new(classes: Vec<FClassInfo>) -> Self {
+        let mut tree = Self::default();
+        for class in classes {
+            if !tree.index.contains_key(&class.key) {
+                tree.index.insert(class.key.clone(), tree.classes.len());
+                tree.classes.push(class);
+            }
+        }
+
+        let mut order: Vec<usize> = (0..tree.classes.len()).collect();
+        order.sort_by(|a, b| tree.classes[*a].name.to_lowercase().cmp(&tree.classes[*b].name.to_lowercase()));
+
+        tree.children = vec![vec![]; tree.classes.len()];
+        for index in order {
+            match tree.classes[index].super_key.as_ref().and_then(|key| tree.index.get(key)) {
+                Some(parent) if *parent != index => tree.children[*parent].push(index),
+                _ => tree.roots.push(index)
+            }
+        }
+
+        tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    /// Finds a class by name, or by `package.name` when the name is ambiguous.
+    pub fn find(&self, name: &str) -> Option<usize> {
+        let name = name.to_lowercase();
+        self.index.get(&name).copied()
+            .or_else(|| self.classes.iter().position(|class| class.name.to_lowercase() == name))
+    }
+
+    pub fn get(&self, index: usize) -> Option<&FClassInfo> {
+        self.classes.get(index)
+    }
+
+    /// The super classes of a class, from its direct super class to the root.
+    pub fn ancestors(&self, index: usize) -> Vec<&FClassInfo> {
+        let mut ancestors = vec![];
+        let mut current = self.classes[index].super_key.as_ref().and_then(|key| self.index.get(key));
+        while let Some(parent) = current.filter(|_| ancestors.len() < MAX_TREE_DEPTH) {
+            ancestors.push(&self.classes[*parent]);
+            current = self.classes[*parent].super_key.as_ref().and_then(|key| self.index.get(key));
+        }
+
+        ancestors
+    }
+
+    /// The classes as nested objects with their defaults and subclasses, from `root` or from
+    /// every root class.
+    pub fn to_json(&self, root: Option<usize>) -> Value {
+        match root {
+            Some(root) => self.class_json(root, 0),
+            None => Value::Array(self.roots.iter().map(|root| self.class_json(*root, 0)).collect())
+        }
+    }
+
+    fn class_json(&self, index: usize, depth: usize) -> Value {
+        let class = &self.classes[index];
+        let children: Vec<Value> = if depth < MAX_TREE_DEPTH {
+            self.children[index].iter().map(|child| self.class_json(*child, depth + 1)).collect()
+        } else {
+            vec![]
+        };
+
+        json!({
+            "name": class.name,
+            "package": class.package,
+            "super": class.super_path,
+            "defaults": class.defaults,
+            "children": children
+        })
+    }
+
+    /// Writes the classes as an indented tree, from `root` or from every root class, with the
+    /// default properties under each class if `defaults` is set.
+    pub fn write_tree<W: Write>(&self, writer: &mut W, root: Option<usize>, defaults: bool) -> Result<()> {
+        let roots = match root {
+            Some(root) => vec![root],
+            None => self.roots.clone()
+        };
+
+        for root in roots {
+            self.write_class(writer, root, "", "", defaults, 0)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a class line after `prefix`, then its defaults and subclasses after `indent`.
+    fn write_class<W: Write>(&self, writer: &mut W, index: usize, prefix: &str, indent: &str, defaults: bool, depth: usize) -> Result<()> {
+        let class = &self.classes[index];
+        let is_root = class.super_key.as_ref().and_then(|key| self.index.get(key)).is_none();
+        match &class.super_path {
+            Some(super_path) if is_root => writeln!(writer, "{}{} ({}, extends {} which wasn't loaded)", prefix, class.name, class.package, super_path)?,
+            _ => writeln!(writer, "{}{} ({})", prefix, class.name, class.package)?
+        }
+
+        let children = if depth < MAX_TREE_DEPTH { self.children[index].as_slice() } else { &[] };
+        if defaults {
+            let bar = if children.is_empty() { "    " } else { "│   " };
+            for (name, value) in &class.defaults {
+                writeln!(writer, "{}{}{} = {}", indent, bar, name, value)?;
+            }
+        }
+
+        for (position, child) in children.iter().enumerate() {
+            let is_last = position + 1 == children.len();
+            let (branch, continuation) = if is_last { ("└── ", "    ") } else { ("├── ", "│   ") };
+            self.write_class(writer, *child, &format!("{}{}", indent, branch), &format!("{}{}", indent, continuation), defaults, depth + 1)?;
+        }
+
+        Ok(())
+    }
+
+}
@@ -0,0 +1,207 @@
+//! Converts the tagged properties of exports to JSON, expanding the subobjects they reference
+//! in place. Array and struct layouts aren't described by their tags, so the common structs
+//! are decoded by name and the rest is guessed from the data, falling back to hex.
+
+use std::io::SeekFrom;
+
+use serde_json::{Map, Value, json};
+
+use crate::archive::{FArchive, read_serializable};
+use crate::file::GameFile;
+use crate::package::{FName, UnPackage};
+use crate::properties::{FPropertyTag, UPropertyValue, read_object_properties, read_properties};
+use crate::reader::FPackageReader;
+use crate::{Result, ParserError};
+
+const MAX_OUTER_DEPTH: usize = 64;
+const MAX_SUBOBJECT_DEPTH: usize = 16;
+
+pub(crate) struct PropertySerializer<'a, F: GameFile> {
+    package: &'a UnPackage<F>,
+    reader: &'a mut FPackageReader,
+    /// The exports being expanded, the first one owns the subobjects.
+    stack: Vec<i32>
+}
+
+impl<'a, F: GameFile> PropertySerializer<'a, F> {
+
+    pub fn new(package: &'a UnPackage<F>, reader: &'a mut FPackageReader) -> Self {
+        Self { package, reader, stack: vec![] }
+    }
+
+    /// The properties of an export, with the exports nested in it expanded.
+    pub fn object(&mut self, index: i32) -> Result<Map<String, Value>> {
+        let properties = self.read_export_properties(index)?;
+        self.owned_properties(index, &properties)
+    }
+
+    /// Converts properties read from the export `owner`, expanding its subobjects.
+    pub fn owned_properties(&mut self, owner: i32, properties: &[FPropertyTag]) -> Result<Map<String, Value>> {
+        self.stack.push(owner);
+        let object = self.properties(properties);
+        self.stack.pop();
+
+        object
+    }
+
+    /// Reads the tagged properties of an export.
+    pub fn read_export_properties(&mut self, index: i32) -> Result<Vec<FPropertyTag>> {
+        let export = usize::try_from(index - 1).ok()
+            .and_then(|index| self.package.exports.get(index))
+            .ok_or_else(|| ParserError::new(&format!("Invalid export index {}", index)))?;
+
+        let data = self.reader.read_export(export)?;
+        read_object_properties(&mut self.package.object_archive(data), &self.package.names)
+    }
+
+    /// Converts properties to an object, the elements of static arrays are grouped in a JSON array.
+    pub fn properties(&mut self, properties: &[FPropertyTag]) -> Result<Map<String, Value>> {
+        let mut object = Map::new();
+        for property in properties {
+            let value = self.value(property)?;
+            match object.get_mut(&property.name) {
+                Some(Value::Array(elements)) if property.array_index > 0 => elements.push(value),
+                Some(existing) if property.array_index > 0 => *existing = Value::Array(vec![existing.take(), value]),
+                _ => { object.insert(property.name.clone(), value); }
+            }
+        }
+
+        Ok(object)
+    }
+
+    fn value(&mut self, property: &FPropertyTag) -> Result<Value> {
+        let value = match &property.value {
+            UPropertyValue::Int(value) => json!(value),
+            UPropertyValue::Float(value) => float(*value),
+            UPropertyValue::Bool(value) => json!(value),
+            UPropertyValue::Byte(value) => json!(value),
+            UPropertyValue::Enum { value, .. } | UPropertyValue::Name(value) | UPropertyValue::Str(value) => json!(value),
+            UPropertyValue::Object(index) => self.reference(*index)?,
+            UPropertyValue::Struct { struct_name, data } => self.structure(struct_name, data)?,
+            UPropertyValue::Array { count, data } => self.array(*count, data)?,
+            UPropertyValue::Unknown(data) => json!(hex::encode(data))
+        };
+
+        Ok(value)
+    }
+
+    /// Subobjects are expanded, other references are written as their path.
+    fn reference(&mut self, index: i32) -> Result<Value> {
+        if index == 0 {
+            return Ok(Value::Null);
+        }
+
+        if self.is_subobject(index) {
+            return Ok(Value::Object(self.object(index)?));
+        }
+
+        Ok(self.package.get_object_path(index).map_or(Value::Null, Value::String))
+    }
+
+    fn is_subobject(&self, index: i32) -> bool {
+        let owner = match self.stack.first() {
+            Some(owner) => *owner,
+            None => return false
+        };
+
+        if index <= 0 || self.stack.contains(&index) || self.stack.len() >= MAX_SUBOBJECT_DEPTH {
+            return false;
+        }
+
+        let mut current = index;
+        for _ in 0..MAX_OUTER_DEPTH {
+            current = match usize::try_from(current - 1).ok().and_then(|index| self.package.exports.get(index)) {
+                Some(export) => export.outer_index,
+                None => return false
+            };
+
+            if current == owner {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn structure(&mut self, struct_name: &str, data: &[u8]) -> Result<Value> {
+        let mut archive = self.package.object_archive(data.to_vec());
+        let value = match struct_name {
+            "Vector" => json!({ "X": float(archive.read_f32()?), "Y": float(archive.read_f32()?), "Z": float(archive.read_f32()?) }),
+            "Vector2D" => json!({ "X": float(archive.read_f32()?), "Y": float(archive.read_f32()?) }),
+            "Vector4" | "Plane" | "Quat" => json!({
+                "X": float(archive.read_f32()?), "Y": float(archive.read_f32()?), "Z": float(archive.read_f32()?), "W": float(archive.read_f32()?)
+            }),
+            "Rotator" => json!({ "Pitch": archive.read_i32()?, "Yaw": archive.read_i32()?, "Roll": archive.read_i32()? }),
+            "Color" => json!({ "B": archive.read_u8()?, "G": archive.read_u8()?, "R": archive.read_u8()?, "A": archive.read_u8()? }),
+            "LinearColor" => json!({
+                "R": float(archive.read_f32()?), "G": float(archive.read_f32()?), "B": float(archive.read_f32()?), "A": float(archive.read_f32()?)
+            }),
+            _ => match read_properties(&mut archive, &self.package.names) {
+                Ok(properties) if archive.seek(SeekFrom::Current(0))? == data.len() as u64 => Value::Object(self.properties(&properties)?),
+                _ => json!(hex::encode(data))
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Arrays of subobjects, of tagged structs, of ints or of names, in that order of preference.
+    fn array(&mut self, count: i32, data: &[u8]) -> Result<Value> {
+        let count = match usize::try_from(count) {
+            Ok(count) if count <= data.len() => count,
+            _ => return Ok(json!(hex::encode(data)))
+        };
+
+        if count * 4 == data.len() {
+            let mut archive = self.package.object_archive(data.to_vec());
+            let values = (0..count).map(|_| archive.read_i32()).collect::<Result<Vec<_>>>()?;
+            if values.iter().any(|value| *value != 0) && values.iter().all(|value| *value == 0 || self.is_subobject(*value)) {
+                return Ok(Value::Array(values.into_iter().map(|value| self.reference(value)).collect::<Result<_>>()?));
+            }
+
+            return Ok(json!(values));
+        }
+
+        if let Some(elements) = self.struct_elements(count, data)? {
+            return Ok(Value::Array(elements));
+        }
+
+        if count * 8 == data.len() {
+            let mut archive = self.package.object_archive(data.to_vec());
+            let names: Option<Vec<String>> = (0..count)
+                .map(|_| read_serializable::<FName, _>(&mut archive).ok().and_then(|name| self.package.get_name(&name)))
+                .collect();
+
+            if let Some(names) = names {
+                return Ok(json!(names));
+            }
+        }
+
+        Ok(json!(hex::encode(data)))
+    }
+
+    fn struct_elements(&mut self, count: usize, data: &[u8]) -> Result<Option<Vec<Value>>> {
+        let mut archive = self.package.object_archive(data.to_vec());
+        let mut elements = Vec::with_capacity(count);
+        for _ in 0..count {
+            match read_properties(&mut archive, &self.package.names) {
+                Ok(properties) => elements.push(properties),
+                Err(_) => return Ok(None)
+            }
+        }
+
+        if archive.seek(SeekFrom::Current(0))? != data.len() as u64 {
+            return Ok(None);
+        }
+
+        elements.iter().map(|properties| Ok(Value::Object(self.properties(properties)?))).collect::<Result<_>>().map(Some)
+    }
+
+}
+
+/// Writes floats with their shortest representation rather than their exact double value.
+fn float(value: f32) -> Value {
+    value.to_string().parse::<f64>().ok()
+        .and_then(serde_json::Number::from_f64)
+        .map_or(Value::Null, Value::Number)
+}
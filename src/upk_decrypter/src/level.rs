@@ -0,0 +1,112 @@
+//! The actors of a map's `Level` and the transforms placing them and their components in the world.
+
+use crate::archive::FArchive;
+use crate::file::GameFile;
+use crate::package::UnPackage;
+use crate::properties::{FPropertyTag, find_property, read_object_properties, read_rotator_property, read_vector_property};
+use crate::{Result, ParserError};
+
+const MAX_ACTORS: i32 = 1 << 20;
+/// `FRotator` units in a full turn.
+const ROTATOR_UNITS: f32 = 65536.0;
+
+#[derive(Debug, Clone)]
+pub struct ULevel {
+    pub properties: Vec<FPropertyTag>,
+    /// The actor object references, 0 for the slots of destroyed actors.
+    pub actors: Vec<i32>
+}
+
+impl ULevel {
+
+    /// Parses the serialized data of a `Level` export of `package`.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, data: Vec<u8>) -> Result<Self> {
+        let mut archive = package.object_archive(data);
+        let properties = read_object_properties(&mut archive, &package.names)?;
+
+        let count = archive.read_i32()?;
+        if !(0..=MAX_ACTORS).contains(&count) {
+            return Err(Box::new(ParserError::new(&format!("Invalid actor count: {}", count))));
+        }
+        let actors = (0..count).map(|_| archive.read_i32()).collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { properties, actors })
+    }
+
+}
+
+/// A translation, rotation and scale as stored on actors and primitive components, applied
+/// in Unreal's order: scale, then rotate, then translate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FTransform {
+    pub translation: [f32; 3],
+    /// Pitch, yaw and roll in `FRotator` units.
+    pub rotation: [i32; 3],
+    pub scale: [f32; 3]
+}
+
+impl Default for FTransform {
+    fn default() -> Self {
+        Self { translation: [0.0; 3], rotation: [0; 3], scale: [1.0; 3] }
+    }
+}
+
+impl FTransform {
+
+    /// The placement of an actor, from its `Location`, `Rotation`, `DrawScale` and `DrawScale3D`.
+    pub fn from_actor<F: GameFile>(package: &UnPackage<F>, properties: &[FPropertyTag]) -> Result<Self> {
+        Self::from_properties(package, properties, ["Location", "Rotation", "DrawScale", "DrawScale3D"])
+    }
+
+    /// The placement of a component relative to its actor, from its `Translation`, `Rotation`, `Scale` and `Scale3D`.
+    pub fn from_component<F: GameFile>(package: &UnPackage<F>, properties: &[FPropertyTag]) -> Result<Self> {
+        Self::from_properties(package, properties, ["Translation", "Rotation", "Scale", "Scale3D"])
+    }
+
+    fn from_properties<F: GameFile>(package: &UnPackage<F>, properties: &[FPropertyTag], [translation, rotation, scale, scale_3d]: [&str; 4]) -> Result<Self> {
+        let mut transform = Self::default();
+        if let Some(property) = find_property(properties, translation) {
+            transform.translation = read_vector_property(package, property)?;
+        }
+
+        if let Some(property) = find_property(properties, rotation) {
+            transform.rotation = read_rotator_property(package, property)?;
+        }
+
+        if let Some(property) = find_property(properties, scale_3d) {
+            transform.scale = read_vector_property(package, property)?;
+        }
+
+        if let Some(scale) = find_property(properties, scale).and_then(FPropertyTag::as_float) {
+            transform.scale = transform.scale.map(|component| component * scale);
+        }
+
+        Ok(transform)
+    }
+
+    /// The rows of `FRotationMatrix`, the rotated X, Y and Z axes.
+    fn rotation_matrix(&self) -> [[f32; 3]; 3] {
+        let [pitch, yaw, roll] = self.rotation.map(|angle| angle as f32 * std::f32::consts::TAU / ROTATOR_UNITS);
+        let (sp, cp) = pitch.sin_cos();
+        let (sy, cy) = yaw.sin_cos();
+        let (sr, cr) = roll.sin_cos();
+
+        [
+            [cp * cy, cp * sy, sp],
+            [sr * sp * cy - cr * sy, sr * sp * sy + cr * cy, -sr * cp],
+            [-(cr * sp * cy + sr * sy), cy * sr - cr * sp * sy, cr * cp]
+        ]
+    }
+
+    pub fn apply(&self, point: [f32; 3]) -> [f32; 3] {
+        let matrix = self.rotation_matrix();
+        let scaled = [0, 1, 2].map(|i| point[i] * self.scale[i]);
+        [0, 1, 2].map(|axis| (0..3).map(|row| scaled[row] * matrix[row][axis]).sum::<f32>() + self.translation[axis])
+    }
+
+    /// Whether the transform mirrors what it's applied to, which flips the winding of triangles.
+    pub fn is_mirrored(&self) -> bool {
+        self.scale.iter().filter(|component| **component < 0.0).count() % 2 == 1
+    }
+
+}
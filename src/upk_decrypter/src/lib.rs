@@ -8,16 +8,48 @@
 use std::sync::{Arc, Mutex};
 use std::path::PathBuf;
 use std::fmt;
+use std::io::BufReader;
 
 pub mod package;
 pub mod file;
 pub mod encryption;
 pub mod compression;
+pub mod reader;
+pub mod profile;
+pub mod properties;
+pub mod bulkdata;
+pub mod staticmesh;
+pub mod skeletalmesh;
+pub mod animation;
+pub mod level;
+pub mod collision;
+pub mod car;
+pub mod product;
+pub mod swf;
+pub mod wwise;
+pub mod wem;
+pub mod script;
+pub mod disassembler;
+pub mod sdk;
+pub mod hierarchy;
+pub mod texture;
 mod archive;
+mod dds;
+mod dxt;
+mod gltf;
+mod json;
+mod lzo;
+mod mesh;
+mod vorbis;
 
 use file::{OsGameFile, GameFile};
-use encryption::FAesKey;
-use package::UnPackage;
+use encryption::{FAesKey, PackageCipher};
+use package::{UnPackage, OutputMode};
+use reader::FPackageReader;
+use profile::GameProfile;
+use texture::UTexture2D;
+
+const TEXTURE_FILE_CACHE_EXTENSION: &str = "tfc";
 
 pub type Result<Type> = std::result::Result<Type, Box<dyn std::error::Error>>;
 
@@ -59,6 +91,9 @@ pub struct DefaultFileProvider {
     pub files: Vec<OsGameFile>,
     output: PathBuf,
     input: PathBuf,
+    decompression_threads: usize,
+    profile: Option<GameProfile>,
+    cipher: Option<Arc<dyn PackageCipher>>,
 }
 
 impl FileProvider for DefaultFileProvider {
@@ -78,10 +113,28 @@ impl DefaultFileProvider {
             keys: Arc::new(Mutex::new(Vec::new())),
             files: Vec::new(),
             output: PathBuf::from(output_dir),
-            input: PathBuf::from(input_dir)
+            input: PathBuf::from(input_dir),
+            decompression_threads: 1,
+            profile: None,
+            cipher: None
         }
     }
 
+    /// Forces the layout used to read every package, instead of detecting it per package.
+    pub fn set_profile(&mut self, profile: GameProfile) {
+        self.profile = Some(profile);
+    }
+
+    /// Uses `cipher` instead of the Rocket League AES scheme for every package.
+    pub fn set_cipher(&mut self, cipher: Arc<dyn PackageCipher>) {
+        self.cipher = Some(cipher);
+    }
+
+    /// Sets how many threads decompress the blocks of a single package.
+    pub fn set_decompression_threads(&mut self, threads: usize) {
+        self.decompression_threads = threads.max(1);
+    }
+
 
     pub fn scan_files(&mut self) -> Result<usize> {
         self.scan_files_with_pattern("*.upk")
@@ -105,13 +158,31 @@ impl DefaultFileProvider {
         Ok(package)
     }
 
+    pub fn load_package_header(&self, name: &str) -> Result<UnPackage<OsGameFile>> {
+        let mut package = self.get_package(name)?;
+        package.load_header()?;
+
+        Ok(package)
+    }
+
+    pub fn open_package_reader(&self, name: &str) -> Result<(UnPackage<OsGameFile>, FPackageReader)> {
+        let mut package = self.get_package(name)?;
+        let reader = package.open_reader()?;
+
+        Ok((package, reader))
+    }
+
     pub fn save_package(&self, name: &str) -> Result<UnPackage<OsGameFile>> {
+        self.save_package_with_mode(name, OutputMode::Raw)
+    }
+
+    pub fn save_package_with_mode(&self, name: &str, mode: OutputMode) -> Result<UnPackage<OsGameFile>> {
         let mut package = self.get_package(name)?;
         let mut path = PathBuf::new();
         path.push(self.output.as_os_str().to_str().unwrap());
         path.push(package.file.get_filename().to_string().as_str());
 
-        package.save(path)?;
+        package.save_with_mode(path, mode)?;
 
         Ok(package)
     }
@@ -122,10 +193,59 @@ impl DefaultFileProvider {
             None => return Err(Box::new(ParserError::new("Package not found.")))
         };
 
-        let package = UnPackage::<OsGameFile>::new(file.clone(), self.keys.clone());
+        let mut package = UnPackage::<OsGameFile>::new(file.clone(), self.keys.clone());
+        package.set_decompression_threads(self.decompression_threads);
+        if let Some(cipher) = &self.cipher {
+            package.set_cipher(cipher.clone());
+        }
+
+        if let Some(profile) = self.profile {
+            package.set_profile(profile);
+        }
+
         Ok(package)
     }
 
+    /// Finds `<name>.tfc` in the input directory, ignoring case.
+    #[must_use]
+    pub fn find_texture_file_cache(&self, name: &str) -> Option<PathBuf> {
+        let file_name = format!("{}.{}", name, TEXTURE_FILE_CACHE_EXTENSION);
+        let path = self.input.join(&file_name);
+        if path.is_file() {
+            return Some(path);
+        }
+
+        std::fs::read_dir(&self.input).ok()?
+            .flatten()
+            .map(|entry| entry.path())
+            .find(|path| path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.eq_ignore_ascii_case(&file_name)))
+    }
+
+    /// Loads the mips of `texture` that are stored in its texture file cache, returning how many were loaded.
+    pub fn load_texture_mips<File: GameFile>(&self, package: &UnPackage<File>, texture: &mut UTexture2D) -> Result<usize> {
+        if !texture.has_external_mips() {
+            return Ok(0);
+        }
+
+        let name = match &texture.texture_file_cache_name {
+            Some(name) => name,
+            None => return Err(Box::new(ParserError::new("Texture has external mips but no TextureFileCacheName")))
+        };
+
+        let path = match self.find_texture_file_cache(name) {
+            Some(path) => path,
+            None => return Err(Box::new(ParserError::new(&format!("Texture file cache {}.{} not found", name, TEXTURE_FILE_CACHE_EXTENSION))))
+        };
+
+        let mut cache = BufReader::new(std::fs::File::open(path)?);
+        texture.load_external_mips(&mut cache, package.is_big_endian())
+    }
+
+    /// Finds a package by its name without the extension, ignoring case.
+    pub fn find_package(&self, name: &str) -> Option<&OsGameFile> {
+        self.files.iter().find(|f| f.file_name.strip_suffix(&format!(".{}", f.extension)).is_some_and(|stem| stem.eq_ignore_ascii_case(name)))
+    }
+
     #[must_use]
     pub fn find_game_file(&self, name: &str) -> Option<&OsGameFile> {
         self.files.iter().find(|f| f.file_name.to_lowercase() == name.to_lowercase())
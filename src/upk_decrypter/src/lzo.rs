@@ -0,0 +1,250 @@
+//! A safe LZO1X decompressor, equivalent to `lzo1x_decompress_safe`.
+
+use crate::{Result, ParserError};
+
+const M2_MAX_OFFSET: usize = 0x0800;
+const MAX_255_COUNT: usize = usize::MAX / 255 - 2;
+
+struct Decoder<'a> {
+    input: &'a [u8],
+    output: &'a mut [u8],
+    ip: usize,
+    op: usize
+}
+
+impl<'a> Decoder<'a> {
+
+    fn byte(&mut self) -> Result<usize> {
+        let val = *self.input.get(self.ip).ok_or_else(overrun)?;
+        self.ip += 1;
+
+        Ok(usize::from(val))
+    }
+
+    fn le16(&mut self) -> Result<usize> {
+        let bytes = self.input.get(self.ip..self.ip + 2).ok_or_else(overrun)?;
+        self.ip += 2;
+
+        Ok(usize::from(u16::from_le_bytes([bytes[0], bytes[1]])))
+    }
+
+    /// Reads the run of zero bytes that extends a length, each zero adds 255.
+    fn extended_length(&mut self, base: usize) -> Result<usize> {
+        let start = self.ip;
+        while *self.input.get(self.ip).ok_or_else(overrun)? == 0 {
+            self.ip += 1;
+        }
+
+        let zeros = self.ip - start;
+        if zeros > MAX_255_COUNT {
+            return Err(Box::new(ParserError::new("LZO length overflow")));
+        }
+
+        Ok(zeros * 255 + base + self.byte()?)
+    }
+
+    fn copy_literals(&mut self, len: usize) -> Result<()> {
+        let literals = self.input.get(self.ip..self.ip + len).ok_or_else(overrun)?;
+        self.output.get_mut(self.op..self.op + len).ok_or_else(output_overrun)?.copy_from_slice(literals);
+        self.ip += len;
+        self.op += len;
+
+        Ok(())
+    }
+
+    fn copy_match(&mut self, distance: usize, len: usize) -> Result<()> {
+        if distance > self.op {
+            return Err(Box::new(ParserError::new("LZO lookbehind overrun")));
+        }
+
+        if self.op + len > self.output.len() {
+            return Err(Box::new(output_overrun()));
+        }
+
+        // Matches can overlap the bytes they produce, so this has to go byte by byte.
+        let start = self.op - distance;
+        for i in 0..len {
+            self.output[self.op + i] = self.output[start + i];
+        }
+
+        self.op += len;
+        Ok(())
+    }
+
+}
+
+fn overrun() -> ParserError {
+    ParserError::new("LZO input overrun")
+}
+
+fn output_overrun() -> ParserError {
+    ParserError::new("LZO output overrun")
+}
+
+/// Decompresses an LZO1X stream into `output`, returning the number of bytes written.
+pub(crate) fn decompress(input: &[u8], output: &mut [u8]) -> Result<usize> {
+    let mut decoder = Decoder { input, output, ip: 0, op: 0 };
+
+    // `state` is the number of literals copied after the last instruction (4 for a literal run),
+    // it changes how the next short instruction is interpreted.
+    let mut state;
+    let first = decoder.byte()?;
+    if first > 17 {
+        let len = first - 17;
+        decoder.copy_literals(len)?;
+        state = if len < 4 { len } else { 4 };
+    } else {
+        decoder.ip = 0;
+        state = 0;
+    }
+
+    loop {
+        let t = decoder.byte()?;
+        let (distance, len, next) = if t < 16 {
+            if state == 0 {
+                let len = if t == 0 { decoder.extended_length(15)? } else { t };
+                decoder.copy_literals(len + 3)?;
+                state = 4;
+                continue;
+            }
+
+            let distance = (t >> 2) + (decoder.byte()? << 2);
+            if state != 4 {
+                (distance + 1, 2, t & 3)
+            } else {
+                (distance + 1 + M2_MAX_OFFSET, 3, t & 3)
+            }
+        } else if t >= 64 {
+            let distance = ((t >> 2) & 7) + (decoder.byte()? << 3) + 1;
+            (distance, (t >> 5) + 1, t & 3)
+        } else if t >= 32 {
+            let len = if t & 31 == 0 { decoder.extended_length(31)? } else { t & 31 };
+            let trailer = decoder.le16()?;
+            ((trailer >> 2) + 1, len + 2, trailer & 3)
+        } else {
+            let high = (t & 8) << 11;
+            let len = if t & 7 == 0 { decoder.extended_length(7)? } else { t & 7 };
+            let trailer = decoder.le16()?;
+            let distance = high + (trailer >> 2);
+            if distance == 0 {
+                break;
+            }
+
+            (distance + 0x4000, len + 2, trailer & 3)
+        };
+
+        decoder.copy_match(distance, len)?;
+        decoder.copy_literals(next)?;
+        state = next;
+    }
+
+    if decoder.ip != decoder.input.len() {
+        return Err(Box::new(ParserError::new("LZO input not fully consumed")));
+    }
+
+    Ok(decoder.op)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const END_MARKER: [u8; 3] = [0x11, 0x00, 0x00];
+
+    fn decompress_vec(input: &[u8], size: usize) -> Result<Vec<u8>> {
+        let mut output = vec![0u8; size];
+        let len = decompress(input, &mut output)?;
+        output.truncate(len);
+
+        Ok(output)
+    }
+
+    fn stream(parts: &[&[u8]]) -> Vec<u8> {
+        parts.concat()
+    }
+
+    #[test]
+    fn first_literal_run() {
+        let input = stream(&[&[17 + 5], b"hello", &END_MARKER]);
+        assert_eq!(decompress_vec(&input, 16).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn long_literal_run() {
+        // A first byte of 0 is a literal instruction whose length continues in the zero bytes:
+        // 300 = 3 + 15 + 255 + 27.
+        let literals: Vec<u8> = (0..300u32).map(|i| (i % 251) as u8).collect();
+        let input = stream(&[&[0x00, 0x00, 27], &literals, &END_MARKER]);
+        assert_eq!(decompress_vec(&input, 300).unwrap(), literals);
+    }
+
+    #[test]
+    fn m1_match_after_short_literals() {
+        // After fewer than 4 literals, an instruction below 16 copies 2 bytes from close by.
+        let input = stream(&[&[17 + 2], b"ab", &[0x04, 0x00], &END_MARKER]);
+        assert_eq!(decompress_vec(&input, 16).unwrap(), b"abab");
+    }
+
+    #[test]
+    fn m2_match() {
+        // Length 4 and distance 4: ((4 - 1) << 5) | (((4 - 1) & 7) << 2), then (4 - 1) >> 3.
+        let input = stream(&[&[17 + 4], b"abcd", &[0x6C, 0x00], &END_MARKER]);
+        assert_eq!(decompress_vec(&input, 16).unwrap(), b"abcdabcd");
+    }
+
+    #[test]
+    fn m3_overlapping_match_with_trailing_literals() {
+        // Length 6 at distance 2 overlaps what it writes, then 2 literals follow the match.
+        let input = stream(&[&[17 + 2], b"ab", &[0x20 | 4, (1 << 2) | 2, 0x00], b"xy", &END_MARKER]);
+        assert_eq!(&decompress_vec(&input, 16).unwrap()[..], b"ababababxy".as_slice());
+    }
+
+    #[test]
+    fn m3_extended_length() {
+        // 255 + 31 + 5 + 2 copies of the previous byte.
+        let input = stream(&[&[17 + 1], b"a", &[0x20, 0x00, 0x05, 0x00, 0x00], &END_MARKER]);
+        assert_eq!(decompress_vec(&input, 512).unwrap(), vec![b'a'; 1 + 293]);
+    }
+
+    #[test]
+    fn m4_match() {
+        // Distances past 0x4000 need an M4 instruction: length 3 from the very first byte.
+        let literals: Vec<u8> = (0..0x4001u32).map(|i| (i % 251) as u8).collect();
+        let zeros = vec![0u8; 64];
+        let input = stream(&[&[0x00], &zeros, &[47], &literals, &[0x11, 1 << 2, 0x00], &END_MARKER]);
+
+        let output = decompress_vec(&input, 0x4010).unwrap();
+        assert_eq!(output.len(), 0x4001 + 3);
+        assert_eq!(&output[..0x4001], literals.as_slice());
+        assert_eq!(&output[0x4001..], &literals[..3]);
+    }
+
+    #[test]
+    fn truncated_input_fails() {
+        assert!(decompress_vec(&[17 + 5, b'h', b'e'], 16).is_err());
+        assert!(decompress_vec(&stream(&[&[17 + 5], b"hello"]), 16).is_err());
+        assert!(decompress_vec(&stream(&[&[17 + 5], b"hello", &[0x11, 0x00]]), 16).is_err());
+        assert!(decompress_vec(&[], 16).is_err());
+    }
+
+    #[test]
+    fn output_overrun_fails() {
+        let input = stream(&[&[17 + 5], b"hello", &END_MARKER]);
+        assert!(decompress_vec(&input, 3).is_err());
+
+        let input = stream(&[&[17 + 1], b"a", &[0x20, 0x00, 0x05, 0x00, 0x00], &END_MARKER]);
+        assert!(decompress_vec(&input, 100).is_err());
+    }
+
+    #[test]
+    fn lookbehind_overrun_fails() {
+        let input = stream(&[&[17 + 1], b"a", &[0x6C, 0x00], &END_MARKER]);
+        assert!(decompress_vec(&input, 16).is_err());
+    }
+
+    #[test]
+    fn trailing_data_fails() {
+        let input = stream(&[&[17 + 5], b"hello", &END_MARKER, &[0x00]]);
+        assert!(decompress_vec(&input, 16).is_err());
+    }
+}
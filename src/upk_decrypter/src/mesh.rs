@@ -0,0 +1,131 @@
+//! The vertex data shared by the mesh types, read into plain arrays with Unreal's axes and units.
+
+use crate::archive::FArchive;
+use crate::{Result, ParserError};
+
+/// A bulk serialized `TArray`, its element size followed by the count and the raw elements.
+pub(crate) struct FBulkArray {
+    pub element_size: usize,
+    pub count: usize,
+    pub data: Vec<u8>
+}
+
+impl FBulkArray {
+
+    pub fn read<Ar: FArchive>(archive: &mut Ar) -> Result<Self> {
+        let element_size = archive.read_i32()?;
+        let count = archive.read_i32()?;
+        if element_size < 0 || count < 0 {
+            return Err(Box::new(ParserError::new(&format!("Invalid bulk array of {} elements of {} bytes", count, element_size))));
+        }
+
+        let element_size = usize::try_from(element_size)?;
+        let count = usize::try_from(count)?;
+        let mut data = vec![0u8; element_size.checked_mul(count).ok_or_else(|| ParserError::new("Bulk array is too large"))?];
+        archive.read_bytes_vec(&mut data)?;
+
+        Ok(Self { element_size, count, data })
+    }
+
+    /// Reads an array whose elements have to be `expected` bytes long.
+    pub fn read_sized<Ar: FArchive>(archive: &mut Ar, expected: usize, what: &str) -> Result<Self> {
+        let array = Self::read(archive)?;
+        if array.count > 0 && array.element_size != expected {
+            return Err(Box::new(ParserError::new(&format!("Unexpected {} element size: {}, expected {}", what, array.element_size, expected))));
+        }
+
+        Ok(array)
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = &[u8]> {
+        self.data.chunks_exact(self.element_size.max(1)).take(self.count)
+    }
+
+}
+
+/// Reads a value of the element from its bytes, in the archive's byte order.
+pub(crate) struct ElementReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    big_endian: bool
+}
+
+impl<'a> ElementReader<'a> {
+
+    pub fn new(data: &'a [u8], big_endian: bool) -> Self {
+        Self { data, position: 0, big_endian }
+    }
+
+    fn take<const SIZE: usize>(&mut self) -> Result<[u8; SIZE]> {
+        let bytes = self.data.get(self.position..self.position + SIZE).ok_or_else(|| ParserError::new("Element is too short"))?;
+        self.position += SIZE;
+
+        let mut result = [0u8; SIZE];
+        result.copy_from_slice(bytes);
+        if self.big_endian {
+            result.reverse();
+        }
+
+        Ok(result)
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    pub fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+
+    pub fn f16(&mut self) -> Result<f32> {
+        Ok(half_to_f32(self.u16()?))
+    }
+
+    pub fn vector(&mut self) -> Result<[f32; 3]> {
+        Ok([self.f32()?, self.f32()?, self.f32()?])
+    }
+
+    /// An `FPackedNormal`, each component mapped from 0..255 to -1..1. The fourth one is the
+    /// sign of the binormal on `TangentZ`.
+    pub fn packed_normal(&mut self) -> Result<[f32; 4]> {
+        let packed = self.u32()?.to_le_bytes();
+        Ok(packed.map(|component| f32::from(component) / 127.5 - 1.0))
+    }
+
+}
+
+pub(crate) fn read_vector<Ar: FArchive>(archive: &mut Ar) -> Result<[f32; 3]> {
+    Ok([archive.read_f32()?, archive.read_f32()?, archive.read_f32()?])
+}
+
+/// Reads the vectors of a bulk array of `FVector`.
+pub(crate) fn read_vector_array<Ar: FArchive>(archive: &mut Ar, what: &str) -> Result<Vec<[f32; 3]>> {
+    let big_endian = archive.is_big_endian();
+    let array = FBulkArray::read_sized(archive, 12, what)?;
+    array.elements().map(|element| ElementReader::new(element, big_endian).vector()).collect()
+}
+
+/// Reads a bulk array of 16-bit indices.
+pub(crate) fn read_index_buffer<Ar: FArchive>(archive: &mut Ar) -> Result<Vec<u32>> {
+    let big_endian = archive.is_big_endian();
+    let array = FBulkArray::read_sized(archive, 2, "index")?;
+    array.elements().map(|element| ElementReader::new(element, big_endian).u16().map(u32::from)).collect()
+}
+
+/// An IEEE half float, as used by the packed UVs.
+fn half_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((half >> 10) & 0x1F);
+    let mantissa = f32::from(half & 0x3FF);
+
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1F if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1F => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15)
+    }
+}
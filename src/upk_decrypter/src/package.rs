@@ -1,52 +1,99 @@
 #![allow(non_upper_case_globals)]
 
-use std::io::{SeekFrom, Cursor};
+use std::io::{SeekFrom, Cursor, Read, Seek};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
-use crate::archive::{FArchive, FByteArchive, UESerializable, read_array, read_serializable_array, read_sized_serializable_array};
-use crate::compression::{FCompressedChunk, decompress};
-use crate::encryption::FAesKey;
+use crate::archive::{FArchive, FByteArchive, UESerializable, read_array, read_serializable, read_serializable_array, read_sized_serializable_array};
+use crate::compression::{FCompressedChunk, decompress_with_threads, detect_fully_compressed_method, fully_compressed_byte_order};
+use crate::encryption::{FAesKey, PackageCipher, RocketLeagueCipher};
 use crate::file::GameFile;
+use crate::profile::GameProfile;
+use crate::reader::FPackageReader;
 use crate::{Result, ParserError};
 
-const PACKAGE_MAGIC: u32 = 0x9E2A83C1;
+pub(crate) const PACKAGE_MAGIC: u32 = 0x9E2A83C1;
 
 pub const PKG_Cooked: u32 = 0x00000008;
 pub const PKG_StoreCompressed: u32 = 0x02000000;
 
+pub const RF_ClassDefaultObject: u64 = 0x00000200;
+pub const RF_ArchetypeObject: u64 = 0x00000400;
+
 pub const COMPRESS_None: u32 = 0x00;
 pub const COMPRESS_ZLIB: u32 = 0x01;
-pub const COMPRESS_GZIP: u32 = 0x02;
+pub const COMPRESS_LZO: u32 = 0x02;
+pub const COMPRESS_LZX: u32 = 0x04;
 
-#[derive(Debug)]
+/// The bits of the compression flags that select the method, the rest are platform hints
+/// (`COMPRESS_BiasMemory`, `COMPRESS_BiasSpeed`, ...).
+const COMPRESS_MethodMask: u32 = 0x0F;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum ECompressionFlags {
+    #[default]
     None,
     Zlib,
-    Gzip
+    /// UE3 has no flag for GZIP, titles that use it store the streams under `COMPRESS_ZLIB`.
+    /// Zlib blocks are checked for the GZIP magic and decoded accordingly.
+    Gzip,
+    Lzo,
+    Lzx,
+    Unknown(u32)
 }
 
-impl Default for ECompressionFlags {
-    fn default() -> Self {
-        Self::None
+impl From<u32> for ECompressionFlags{
+    fn from(val: u32) -> Self {
+        match val & COMPRESS_MethodMask {
+            COMPRESS_None => Self::None,
+            COMPRESS_ZLIB => Self::Zlib,
+            COMPRESS_LZO => Self::Lzo,
+            COMPRESS_LZX => Self::Lzx,
+            _ => Self::Unknown(val)
+        }
     }
 }
 
-impl From<u32> for ECompressionFlags{
-    fn from(val: u32) -> Self {
+impl From<ECompressionFlags> for u32 {
+    fn from(val: ECompressionFlags) -> Self {
         match val {
-            COMPRESS_ZLIB => Self::Zlib,
-            COMPRESS_GZIP => Self::Gzip,
-            _ => Self::None
+            ECompressionFlags::None => COMPRESS_None,
+            ECompressionFlags::Zlib | ECompressionFlags::Gzip => COMPRESS_ZLIB,
+            ECompressionFlags::Lzo => COMPRESS_LZO,
+            ECompressionFlags::Lzx => COMPRESS_LZX,
+            ECompressionFlags::Unknown(val) => val
         }
     }
 }
 
+/// Controls what `UnPackage::save_with_mode` writes to disk.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// The decrypted and decompressed data with the original summary, as the game shipped it.
+    #[default]
+    Raw,
+    /// Like `Raw`, but the summary is rewritten to describe a plain uncompressed package
+    /// so third-party tools (UE Explorer, UModel) can open the output.
+    Uncompressed
+}
+
 #[derive(Debug)]
 pub struct UnPackage<File: GameFile> {
     pub file: File,
     pub keys: Arc<Mutex<Vec<FAesKey>>>,
-    pub summary: FPackageFileSummary
+    pub cipher: Arc<dyn PackageCipher>,
+    pub summary: FPackageFileSummary,
+    pub names: Vec<FNameEntry>,
+    pub imports: Vec<FObjectImport>,
+    pub exports: Vec<FObjectExport>,
+    summary_size: usize,
+    decompression_threads: usize,
+    profile: Option<GameProfile>,
+    /// Whether the header had to be decrypted. False for unencrypted or already decrypted packages.
+    pub encrypted: bool,
+    /// Set when the data at the table offsets is already inflated, so there are no chunks to read.
+    decompressed: bool,
+    big_endian: bool
 }
 
 impl<File> UnPackage<File>
@@ -55,70 +102,440 @@ where File : GameFile {
     pub fn new(file: File, keys: Arc<Mutex<Vec<FAesKey>>>) -> Self {
         Self {
             file,
+            cipher: Arc::new(RocketLeagueCipher::new(keys.clone())),
             keys,
-            summary: FPackageFileSummary::default()
+            summary: FPackageFileSummary::default(),
+            names: Vec::new(),
+            imports: Vec::new(),
+            exports: Vec::new(),
+            summary_size: 0,
+            decompression_threads: 1,
+            profile: None,
+            encrypted: false,
+            decompressed: false,
+            big_endian: false
         }
     }
 
+    /// Replaces the default Rocket League encryption scheme.
+    pub fn set_cipher(&mut self, cipher: Arc<dyn PackageCipher>) {
+        self.cipher = cipher;
+    }
+
+    /// Forces the layout used to read this package instead of detecting it from the summary's versions.
+    pub fn set_profile(&mut self, profile: GameProfile) {
+        self.profile = Some(profile);
+    }
+
+    /// Sets how many threads inflate the compressed blocks of this package. Defaults to 1.
+    pub fn set_decompression_threads(&mut self, threads: usize) {
+        self.decompression_threads = threads.max(1);
+    }
+
+    /// Decrypts and decompresses the whole package, then reads its name, import and export tables.
     pub fn load(&mut self) -> Result<FByteArchive> {
-        let data = self.file.read();
+        let mut archive = self.load_data()?;
+        self.serialize_tables(&mut archive)?;
+
+        Ok(archive)
+    }
+
+    /// Decrypts and decompresses the whole package without parsing its tables, all saving needs.
+    fn load_data(&mut self) -> Result<FByteArchive> {
+        let mut archive = self.load_summary()?;
+
+        self.decrypt(&mut archive)?;
+        self.decompress(&mut archive, None)?;
+
+        Ok(archive)
+    }
+
+    /// Loads the summary and the name, import and export tables without inflating the export data.
+    /// Only the chunks that overlap the header tables are decompressed, everything past
+    /// `depends_offset` is left zeroed in the returned archive.
+    pub fn load_header(&mut self) -> Result<FByteArchive> {
+        let mut archive = self.load_summary()?;
+
+        self.decrypt(&mut archive)?;
+        self.decompress(&mut archive, Some(self.summary.depends_offset))?;
+        self.serialize_tables(&mut archive)?;
+
+        Ok(archive)
+    }
+
+    /// Opens a `Read + Seek` view over the uncompressed package without decompressing it up front.
+    /// The header tables are loaded through the reader, so only the chunks that cover them are inflated.
+    pub fn open_reader(&mut self) -> Result<FPackageReader> {
+        let mut archive = self.load_summary()?;
+
+        self.decrypt(&mut archive)?;
+        let compressed_chunks = self.read_compressed_chunks(&mut archive)?;
+        let big_endian = archive.is_big_endian();
+        let profile = archive.profile();
+
+        let mut reader = FPackageReader::new(archive, compressed_chunks, self.summary.compression_flags);
+        let mut header = vec![0u8; usize::try_from(self.summary.depends_offset)?];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut header)?;
+
+        let mut header_archive = FByteArchive::new(header);
+        header_archive.set_big_endian(big_endian);
+        header_archive.set_profile(profile);
+        self.serialize_tables(&mut header_archive)?;
+
+        Ok(reader)
+    }
+
+    /// Resolves a name reference against the name table, including its instance number.
+    pub fn get_name(&self, name: &FName) -> Option<String> {
+        resolve_name(&self.names, name)
+    }
+
+    /// The name of the class of an export, `Class` for the classes themselves.
+    pub fn get_class_name(&self, export: &FObjectExport) -> Option<String> {
+        match export.class_index {
+            0 => Some(String::from("Class")),
+            index => self.get_object_name(index)
+        }
+    }
+
+    /// The name of an object reference, negative indices are imports and positive ones exports.
+    pub fn get_object_name(&self, index: i32) -> Option<String> {
+        if index < 0 {
+            let import = self.imports.get(usize::try_from(-index - 1).ok()?)?;
+            return self.get_name(&import.object_name);
+        }
+
+        let export = self.exports.get(usize::try_from(index - 1).ok()?)?;
+        self.get_name(&export.object_name)
+    }
+
+    /// The dotted path of an object reference through its outers, without the package name.
+    pub fn get_object_path(&self, index: i32) -> Option<String> {
+        let mut parts = vec![];
+        let mut current = index;
+        while current != 0 && parts.len() < MAX_OUTER_DEPTH {
+            parts.push(self.get_object_name(current)?);
+            current = if current < 0 {
+                self.imports.get(usize::try_from(-current - 1).ok()?)?.outer_index
+            } else {
+                self.exports.get(usize::try_from(current - 1).ok()?)?.outer_index
+            };
+        }
+
+        parts.reverse();
+        Some(parts.join("."))
+    }
+
+    pub fn is_big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    /// The layout the package is read with, either the forced one or the detected one.
+    pub fn profile(&self) -> GameProfile {
+        self.profile.unwrap_or_else(|| GameProfile::detect(self.summary.file_version, self.summary.licensee_version))
+    }
+
+    /// Wraps the serialized data of an export so it's read with the package's byte order and layout.
+    pub(crate) fn object_archive(&self, data: Vec<u8>) -> FByteArchive {
         let mut archive = FByteArchive::new(data);
-        FPackageFileSummary::serialize(&mut self.summary, &mut archive)?;
+        archive.set_big_endian(self.big_endian);
+        archive.set_profile(self.profile());
+
+        archive
+    }
 
-        let encrypted_size = usize::try_from((self.summary.header_size - self.summary.garbage_size - self.summary.name_offset + 15) & !15)?;
+    fn load_summary(&mut self) -> Result<FByteArchive> {
+        let mut data = self.file.read();
+        if let Some(big_endian) = fully_compressed_byte_order(&data) {
+            data = self.inflate_file(data, big_endian)?;
+        }
 
-        self.decrypt(&mut archive, encrypted_size)?;
-        self.decompress(&mut archive, encrypted_size)?;
+        let mut archive = FByteArchive::new(data);
+        FPackageFileSummary::serialize_with_profile(&mut self.summary, &mut archive, self.profile)?;
+        self.big_endian = archive.is_big_endian();
+        self.summary_size = usize::try_from(archive.seek(SeekFrom::Current(0))?)?;
 
         Ok(archive)
     }
 
+    /// Inflates a fully compressed package, where the whole file is one compressed chunk, with the
+    /// method of the forced profile or the one its data looks like.
+    /// The size UE3 writes to the `.uncompressed_size` sidecar is checked when it's there.
+    fn inflate_file(&self, data: Vec<u8>, big_endian: bool) -> Result<Vec<u8>> {
+        let chunk = FCompressedChunk {
+            compressed_size: i32::try_from(data.len())?,
+            ..FCompressedChunk::default()
+        };
+
+        let method = self.profile.and_then(|profile| profile.fully_compressed_method)
+            .unwrap_or_else(|| detect_fully_compressed_method(&data, big_endian));
+
+        let mut archive = FByteArchive::new(data);
+        archive.set_big_endian(big_endian);
+
+        let mut result = vec![];
+        decompress_with_threads(&mut archive, &mut result, &[chunk], method, self.decompression_threads)?;
+
+        if let Some(sidecar) = self.file.read_companion(UNCOMPRESSED_SIZE_SUFFIX) {
+            let expected: usize = String::from_utf8_lossy(&sidecar).trim().parse()?;
+            if expected != result.len() {
+                return Err(Box::new(ParserError::new(&format!("Package inflated to {} bytes, {} expects {}", result.len(), UNCOMPRESSED_SIZE_SUFFIX, expected))));
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn serialize_tables(&mut self, archive: &mut FByteArchive) -> Result<()> {
+        archive.seek(SeekFrom::Start(u64::try_from(self.summary.name_offset)?))?;
+        self.names = read_sized_serializable_array(archive, self.summary.name_count)?;
+
+        archive.seek(SeekFrom::Start(u64::try_from(self.summary.import_offset)?))?;
+        self.imports = read_sized_serializable_array(archive, self.summary.import_count)?;
+
+        archive.seek(SeekFrom::Start(u64::try_from(self.summary.export_offset)?))?;
+        self.exports = read_sized_serializable_array(archive, self.summary.export_count)?;
+
+        Ok(())
+    }
+
     pub fn save(&mut self, path: PathBuf) -> Result<()> {
-        let mut archive = self.load()?;
-        std::fs::write(path, &mut archive.get_mut())?;
+        self.save_with_mode(path, OutputMode::Raw)
+    }
+
+    pub fn save_with_mode(&mut self, path: PathBuf, mode: OutputMode) -> Result<()> {
+        let mut archive = self.load_data()?;
+        if mode == OutputMode::Uncompressed {
+            self.strip_compression(&mut archive)?;
+        }
+
+        std::fs::write(path, archive.get_mut())?;
+
+        Ok(())
+    }
+
+    /// Rewrites the summary at the start of the decompressed data so it no longer
+    /// references the compressed layout. The new summary is smaller (the chunk table is gone),
+    /// so it's padded back to its original size, which keeps every table and export offset valid.
+    fn strip_compression(&self, archive: &mut FByteArchive) -> Result<()> {
+        let mut summary = self.summary.clone();
+        summary.package_flags &= !PKG_StoreCompressed;
+        summary.compression_flags = ECompressionFlags::None;
+        summary.compressed_chunks.clear();
+        summary.compression_chunkinfo_offset = 0;
+        summary.last_block_size = 0;
+
+        let mut writer = FByteArchive::new(Vec::with_capacity(self.summary_size));
+        writer.set_big_endian(archive.is_big_endian());
+        writer.set_profile(archive.profile());
+        summary.write(&mut writer)?;
+
+        let mut header = std::mem::take(writer.get_mut());
+        if header.len() > self.summary_size {
+            return Err(Box::new(ParserError::new("Rewritten summary is bigger than the original")));
+        }
+
+        header.resize(self.summary_size, 0);
+        archive.get_mut()[0..self.summary_size].copy_from_slice(&header);
 
         Ok(())
     }
     
-    fn decrypt(&mut self, archive: &mut FByteArchive, encrypted_size: usize) -> Result<()> {
-        let summary = &self.summary;
-        archive.seek(SeekFrom::Start(u64::try_from(self.summary.name_offset)?))?;
+    /// Decrypts the header region in place, unless it already parses as plain data
+    /// (an unencrypted package, or one that was decrypted before).
+    fn decrypt(&mut self, archive: &mut FByteArchive) -> Result<()> {
+        self.encrypted = false;
+        self.decompressed = false;
+        let range = match self.cipher.encrypted_range(&self.summary, &archive.profile())? {
+            Some(range) => range,
+            None => return Ok(())
+        };
 
-        let keys = self.keys.lock().unwrap();
-        for key in keys.iter() {
-            if key.decrypt(archive, u64::try_from(summary.name_offset)?, encrypted_size).is_ok() {
-                break;
-            }
+        let big_endian = archive.is_big_endian();
+        let profile = archive.profile();
+        let file = archive.get_mut();
+        let mut region = match file.get(range.clone()) {
+            Some(region) => region.to_vec(),
+            None => return Err(Box::new(ParserError::new("Encrypted region is out of bounds")))
+        };
+
+        let compressed = self.summary.compression_flags != ECompressionFlags::None && profile.has_encrypted_chunk_info;
+        let chunk_table = |data: &[u8]| compressed && self.has_plain_chunk_table(data, range.start, file, big_endian, profile);
+        let names = |data: &[u8]| self.has_plain_names(data, range.start, big_endian, profile);
+
+        if chunk_table(&region) {
+            return Ok(());
         }
 
+        if names(&region) {
+            // The tables sit where the chunk table would be, so the data is already inflated,
+            // either because the package was never compressed or an earlier run decompressed it.
+            self.decompressed = true;
+            return Ok(());
+        }
+
+        let is_valid = |data: &[u8]| if compressed { chunk_table(data) } else { names(data) };
+        self.cipher.decrypt(&self.summary, &mut region, &is_valid)?;
+        file[range].copy_from_slice(&region);
+        self.encrypted = true;
+
         Ok(())
     }
 
-    fn decompress(&mut self, archive: &mut FByteArchive, encrypted_size: usize) -> Result<()> {
-        let header_end = usize::try_from(self.summary.name_offset)? + usize::try_from(self.summary.compression_chunkinfo_offset)?;
-        archive.seek(SeekFrom::Start(header_end as u64))?;
-        let compressed_chunks_len = archive.read_i32()?;
-        if !(0..100).contains(&compressed_chunks_len) {
-            return Err(Box::new(ParserError::new(&format!("Compressed chunks too big: {}", compressed_chunks_len))));
+    /// Whether the chunk table in `region`, which sits at `region_start` in the file, is sane
+    /// and every chunk it lists starts with the package tag.
+    fn has_plain_chunk_table(&self, region: &[u8], region_start: usize, file: &[u8], big_endian: bool, profile: GameProfile) -> bool {
+        let offset = self.chunk_info_offset().ok().and_then(|offset| offset.checked_sub(region_start));
+        match offset.and_then(|offset| region_archive(region, offset, big_endian, profile)) {
+            Some(mut archive) => is_plain_chunk_table(&mut archive, file),
+            None => false
+        }
+    }
+
+    /// Whether the first entries of the name table in `region` are well formed names. A wrong
+    /// key still decrypts to something, so this is what tells the right key apart.
+    fn has_plain_names(&self, region: &[u8], region_start: usize, big_endian: bool, profile: GameProfile) -> bool {
+        let offset = usize::try_from(self.summary.name_offset).ok().and_then(|offset| offset.checked_sub(region_start));
+        let count = usize::try_from(self.summary.name_count).unwrap_or(0).clamp(1, CHECKED_NAMES);
+        match offset.and_then(|offset| region_archive(region, offset, big_endian, profile)) {
+            Some(mut archive) => (0..count).all(|_| is_plain_name(&mut archive) && archive.read_u64().is_ok()),
+            None => false
+        }
+    }
+
+    /// Decompresses the chunks into a new buffer that replaces the archive's data.
+    /// With `limit` set, chunks starting at or after that uncompressed offset are skipped.
+    /// Packages without any chunks are left as they are.
+    fn decompress(&mut self, archive: &mut FByteArchive, limit: Option<i32>) -> Result<()> {
+        if self.decompressed {
+            return Ok(());
         }
 
-        let compressed_chunks: Vec<FCompressedChunk> = read_sized_serializable_array(archive, compressed_chunks_len)?;
+        let header_end = self.chunk_info_offset()?;
+        let mut compressed_chunks = self.read_compressed_chunks(archive)?;
+        if let Some(limit) = limit {
+            compressed_chunks.retain(|chunk| chunk.uncompressed_offset < limit);
+        }
 
-        let result: Vec<u8> = vec![0u8; usize::try_from(self.summary.name_offset)? + encrypted_size]; // lol make this better
+        if compressed_chunks.is_empty() {
+            return Ok(());
+        }
+
+        let result: Vec<u8> = vec![0u8; header_end];
         let mut result_cursor = Cursor::new(result);
 
         let header = &archive.get_mut()[0..header_end];
         result_cursor.get_mut()[0..header_end].copy_from_slice(header);
 
-        decompress(archive, &mut result_cursor, &compressed_chunks)?;
+        decompress_with_threads(archive, result_cursor.get_mut(), &compressed_chunks, self.summary.compression_flags, self.decompression_threads)?;
 
         archive.replace_cursor(result_cursor);
         Ok(())
     }
 
+    fn chunk_info_offset(&self) -> Result<usize> {
+        Ok(usize::try_from(self.summary.name_offset)? + usize::try_from(self.summary.compression_chunkinfo_offset)?)
+    }
+
+    fn read_compressed_chunks(&self, archive: &mut FByteArchive) -> Result<Vec<FCompressedChunk>> {
+        // Uncompressed packages don't have a chunk table at all.
+        if self.decompressed || self.summary.compression_flags == ECompressionFlags::None {
+            return Ok(vec![]);
+        }
+
+        if !archive.profile().has_encrypted_chunk_info {
+            return Ok(self.summary.compressed_chunks.clone());
+        }
+
+        archive.seek(SeekFrom::Start(u64::try_from(self.chunk_info_offset()?)?))?;
+        let compressed_chunks_len = archive.read_i32()?;
+        if !(0..MAX_COMPRESSED_CHUNKS).contains(&compressed_chunks_len) {
+            return Err(Box::new(ParserError::new(&format!("Compressed chunks too big: {}", compressed_chunks_len))));
+        }
+
+        read_sized_serializable_array(archive, compressed_chunks_len)
+    }
+
+}
+
+const MAX_COMPRESSED_CHUNKS: i32 = 100;
+const MAX_OUTER_DEPTH: usize = 64;
+const UNCOMPRESSED_SIZE_SUFFIX: &str = ".uncompressed_size";
+const MAX_NAME_LENGTH: usize = 1024;
+/// How many names of a decrypted name table have to parse for the key to be accepted.
+const CHECKED_NAMES: usize = 4;
+
+/// Resolves a name reference against a name table, including its instance number.
+pub fn resolve_name(names: &[FNameEntry], name: &FName) -> Option<String> {
+    let entry = names.get(usize::try_from(name.index).ok()?)?;
+    if name.number > 0 {
+        return Some(format!("{}_{}", entry.name, name.number - 1));
+    }
+
+    Some(entry.name.clone())
+}
+
+fn region_archive(region: &[u8], offset: usize, big_endian: bool, profile: GameProfile) -> Option<FByteArchive> {
+    let mut archive = FByteArchive::new(region.to_vec());
+    archive.set_big_endian(big_endian);
+    archive.set_profile(profile);
+    archive.seek(SeekFrom::Start(u64::try_from(offset).ok()?)).ok()?;
+
+    Some(archive)
 }
 
-#[derive(Debug, Default)]
+fn is_plain_chunk_table(archive: &mut FByteArchive, file: &[u8]) -> bool {
+    let count = match archive.read_i32() {
+        Ok(count) if (1..MAX_COMPRESSED_CHUNKS).contains(&count) => count,
+        _ => return false
+    };
+
+    let chunks: Vec<FCompressedChunk> = match read_sized_serializable_array(archive, count) {
+        Ok(chunks) => chunks,
+        Err(_) => return false
+    };
+
+    let magic = if archive.is_big_endian() { PACKAGE_MAGIC.to_be_bytes() } else { PACKAGE_MAGIC.to_le_bytes() };
+    chunks.iter().all(|chunk| {
+        let start = match usize::try_from(chunk.compressed_offset) {
+            Ok(start) => start,
+            Err(_) => return false
+        };
+
+        let end = usize::try_from(chunk.compressed_size).ok().and_then(|size| start.checked_add(size));
+        chunk.uncompressed_size > 0 && chunk.uncompressed_offset >= 0
+            && end.is_some_and(|end| end <= file.len())
+            && file.get(start..start + 4) == Some(&magic[..])
+    })
+}
+
+fn is_plain_name(archive: &mut FByteArchive) -> bool {
+    let len = match archive.read_i32() {
+        Ok(len) => len,
+        Err(_) => return false
+    };
+
+    // Negative lengths are UTF-16 strings.
+    let width: usize = if len < 0 { 2 } else { 1 };
+    let len = len.unsigned_abs() as usize;
+    if len == 0 || len > MAX_NAME_LENGTH {
+        return false;
+    }
+
+    let mut bytes = vec![0u8; len * width];
+    if archive.read_bytes_vec(&mut bytes).is_err() {
+        return false;
+    }
+
+    let (text, terminator) = bytes.split_at(bytes.len() - width);
+    terminator.iter().all(|b| *b == 0) && text.chunks(width).all(|c| {
+        let c = if archive.is_big_endian() { c[c.len() - 1] } else { c[0] };
+        c.is_ascii_graphic() || c == b' '
+    })
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct FGuid {
     pub a: u32,
     pub b: u32,
@@ -126,7 +543,119 @@ pub struct FGuid {
     pub d: u32,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FName {
+    pub index: i32,
+    pub number: i32
+}
+
+impl UESerializable for FName {
+    type Item = FName;
+
+    fn serialize<Ar: FArchive>(item: &mut Self::Item, archive: &mut Ar) -> Result<()> {
+        item.index = archive.read_i32()?;
+        item.number = archive.read_i32()?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FNameEntry {
+    pub name: String,
+    pub flags: u64
+}
+
+impl UESerializable for FNameEntry {
+    type Item = FNameEntry;
+
+    fn serialize<Ar: FArchive>(item: &mut Self::Item, archive: &mut Ar) -> Result<()> {
+        let mut name = archive.read_fstring()?;
+        name.truncate(name.trim_end_matches('\0').len());
+
+        item.name = name;
+        item.flags = archive.read_u64()?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FObjectImport {
+    pub class_package: FName,
+    pub class_name: FName,
+    pub outer_index: i32,
+    pub object_name: FName
+}
+
+impl UESerializable for FObjectImport {
+    type Item = FObjectImport;
+
+    fn serialize<Ar: FArchive>(item: &mut Self::Item, archive: &mut Ar) -> Result<()> {
+        item.class_package = read_serializable(archive)?;
+        item.class_name = read_serializable(archive)?;
+        item.outer_index = archive.read_i32()?;
+        item.object_name = read_serializable(archive)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FObjectExport {
+    pub class_index: i32,
+    pub super_index: i32,
+    pub outer_index: i32,
+    pub object_name: FName,
+    pub archetype_index: i32,
+    pub object_flags: u64,
+    pub serial_size: i32,
+    pub serial_offset: i64,
+    pub export_flags: u32,
+    pub generation_net_object_count: Vec<i32>,
+    pub package_guid: FGuid,
+    pub package_flags: u32
+}
+
+impl UESerializable for FObjectExport {
+    type Item = FObjectExport;
+
+    fn serialize<Ar: FArchive>(item: &mut Self::Item, archive: &mut Ar) -> Result<()> {
+        item.class_index = archive.read_i32()?;
+        item.super_index = archive.read_i32()?;
+        item.outer_index = archive.read_i32()?;
+        item.object_name = read_serializable(archive)?;
+        item.archetype_index = archive.read_i32()?;
+        item.object_flags = archive.read_u64()?;
+        item.serial_size = archive.read_i32()?;
+
+        let profile = archive.profile();
+        item.serial_offset = if profile.has_wide_offsets {
+            archive.read_i64()?
+        } else {
+            i64::from(archive.read_i32()?)
+        };
+
+        if profile.has_export_component_map {
+            let components = archive.read_i32()?;
+            archive.seek(SeekFrom::Current(i64::from(components) * 12))?;
+        }
+
+        item.export_flags = archive.read_u32()?;
+        if profile.has_net_object_counts {
+            item.generation_net_object_count = read_array(archive, |ar| ar.read_i32())?.into_iter().collect::<Result<Vec<i32>>>()?;
+            archive.read_existing_guid(&mut item.package_guid)?;
+        }
+
+        if profile.has_export_package_flags {
+            item.package_flags = archive.read_u32()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct FGenerationInfo {
     pub export_count: i32,
     pub name_count: i32,
@@ -139,13 +668,72 @@ impl UESerializable for FGenerationInfo {
     fn serialize<Ar: FArchive>(item: &mut Self::Item, archive: &mut Ar) -> Result<()> {
         item.export_count = archive.read_i32()?;
         item.name_count = archive.read_i32()?;
-        item.net_object_count = archive.read_i32()?;
+        if archive.profile().has_net_object_counts {
+            item.net_object_count = archive.read_i32()?;
+        }
 
         Ok(())
     }
 }
 
-#[derive(Debug, Default)]
+impl FGenerationInfo {
+
+    pub fn write<Ar: FArchive>(&self, archive: &mut Ar) -> Result<()> {
+        archive.write_i32(self.export_count)?;
+        archive.write_i32(self.name_count)?;
+        if archive.profile().has_net_object_counts {
+            archive.write_i32(self.net_object_count)?;
+        }
+
+        Ok(())
+    }
+
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FTextureAllocation {
+    pub size_x: i32,
+    pub size_y: i32,
+    pub num_mips: i32,
+    pub format: u32,
+    pub tex_create_flags: u32,
+    pub export_indices: Vec<i32>
+}
+
+impl UESerializable for FTextureAllocation {
+    type Item = FTextureAllocation;
+
+    fn serialize<Ar: FArchive>(item: &mut Self::Item, archive: &mut Ar) -> Result<()> {
+        item.size_x = archive.read_i32()?;
+        item.size_y = archive.read_i32()?;
+        item.num_mips = archive.read_i32()?;
+        item.format = archive.read_u32()?;
+        item.tex_create_flags = archive.read_u32()?;
+        item.export_indices = read_array(archive, |ar| ar.read_i32())?.into_iter().collect::<Result<Vec<i32>>>()?;
+
+        Ok(())
+    }
+}
+
+impl FTextureAllocation {
+
+    pub fn write<Ar: FArchive>(&self, archive: &mut Ar) -> Result<()> {
+        archive.write_i32(self.size_x)?;
+        archive.write_i32(self.size_y)?;
+        archive.write_i32(self.num_mips)?;
+        archive.write_u32(self.format)?;
+        archive.write_u32(self.tex_create_flags)?;
+        archive.write_i32(i32::try_from(self.export_indices.len())?)?;
+        for index in &self.export_indices {
+            archive.write_i32(*index)?;
+        }
+
+        Ok(())
+    }
+
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct FPackageFileSummary {
     pub magic: u32,
     pub file_version: u16,
@@ -160,14 +748,20 @@ pub struct FPackageFileSummary {
     pub import_count: i32,
     pub import_offset: i32,
     pub depends_offset: i32,
+    pub import_export_guids_offset: i32,
+    pub import_guids_count: i32,
+    pub export_guids_count: i32,
+    pub thumbnail_table_offset: i32,
     pub guid: FGuid,
     pub generations: Vec<FGenerationInfo>,
     pub engine_version: i32,
     pub cooker_version: i32,
     pub compression_flags: ECompressionFlags,
     pub compressed_chunks: Vec<FCompressedChunk>,
+    pub package_source: u32,
     pub additional_packages_to_cook: Vec<String>,
     pub unknown_structs: i32,
+    pub texture_allocations: Vec<FTextureAllocation>,
     pub garbage_size: i32,
     pub compression_chunkinfo_offset: i32,
     pub last_block_size: i32
@@ -178,11 +772,29 @@ impl UESerializable for FPackageFileSummary {
 
     // todo: minimal serialization for saving only
     fn serialize<Ar: FArchive>(val: &mut Self::Item, archive: &mut Ar) -> Result<()> {
+        Self::serialize_with_profile(val, archive, None)
+    }
+}
+
+impl FPackageFileSummary {
+
+    /// Reads the summary with the given layout, or the one detected from its versions.
+    /// The profile is set on the archive so everything read after the summary uses it too.
+    pub fn serialize_with_profile<Ar: FArchive>(val: &mut Self, archive: &mut Ar, profile: Option<GameProfile>) -> Result<()> {
         val.magic = archive.read_u32()?;
+        if val.magic == PACKAGE_MAGIC.swap_bytes() {
+            archive.set_big_endian(true);
+            val.magic = PACKAGE_MAGIC;
+        }
+
         assert!(val.magic == PACKAGE_MAGIC, "Invalid file magic. Magic = {} PACKAGE_MAGIC = {}", val.magic, PACKAGE_MAGIC);
 
         val.file_version = archive.read_u16()?;
         val.licensee_version = archive.read_u16()?;
+
+        let profile = profile.unwrap_or_else(|| GameProfile::detect(val.file_version, val.licensee_version));
+        archive.set_profile(profile);
+
         val.header_size = archive.read_i32()?;
         val.package_group = archive.read_fstring()?;
         val.package_flags = archive.read_u32()?;
@@ -193,7 +805,15 @@ impl UESerializable for FPackageFileSummary {
         val.import_count = archive.read_i32()?;
         val.import_offset = archive.read_i32()?;
         val.depends_offset = archive.read_i32()?;
-        archive.seek(SeekFrom::Current(4 * 4))?;
+        if profile.has_import_export_guids {
+            val.import_export_guids_offset = archive.read_i32()?;
+            val.import_guids_count = archive.read_i32()?;
+            val.export_guids_count = archive.read_i32()?;
+        }
+
+        if profile.has_thumbnail_table {
+            val.thumbnail_table_offset = archive.read_i32()?;
+        }
 
         archive.read_existing_guid(&mut val.guid)?;
         val.generations = read_serializable_array(archive)?;
@@ -201,19 +821,543 @@ impl UESerializable for FPackageFileSummary {
         val.cooker_version = archive.read_i32()?;
         val.compression_flags = ECompressionFlags::from(archive.read_u32()?);
         val.compressed_chunks = read_serializable_array(archive)?;
-        archive.seek(SeekFrom::Current(4))?;
+        if profile.has_package_source {
+            val.package_source = archive.read_u32()?;
+        }
+
+        if profile.has_additional_packages_to_cook {
+            val.additional_packages_to_cook = read_array(archive, |ar| ar.read_fstring().unwrap())?;
+        }
+
+        if profile.has_texture_allocations {
+            val.unknown_structs = archive.read_i32()?;
+            val.texture_allocations = read_sized_serializable_array(archive, val.unknown_structs)?;
+        }
+
+        if profile.has_encrypted_chunk_info {
+            val.garbage_size = archive.read_i32()?;
+            val.compression_chunkinfo_offset = archive.read_i32()?;
+            val.last_block_size = archive.read_i32()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write<Ar: FArchive>(&self, archive: &mut Ar) -> Result<()> {
+        archive.write_u32(self.magic)?;
+        archive.write_u16(self.file_version)?;
+        archive.write_u16(self.licensee_version)?;
+        archive.write_i32(self.header_size)?;
+        archive.write_fstring(&self.package_group)?;
+        archive.write_u32(self.package_flags)?;
+        archive.write_i32(self.name_count)?;
+        archive.write_i32(self.name_offset)?;
+        archive.write_i32(self.export_count)?;
+        archive.write_i32(self.export_offset)?;
+        archive.write_i32(self.import_count)?;
+        archive.write_i32(self.import_offset)?;
+        archive.write_i32(self.depends_offset)?;
+
+        let profile = archive.profile();
+        if profile.has_import_export_guids {
+            archive.write_i32(self.import_export_guids_offset)?;
+            archive.write_i32(self.import_guids_count)?;
+            archive.write_i32(self.export_guids_count)?;
+        }
+
+        if profile.has_thumbnail_table {
+            archive.write_i32(self.thumbnail_table_offset)?;
+        }
+
+        archive.write_guid(&self.guid)?;
+        archive.write_i32(i32::try_from(self.generations.len())?)?;
+        for generation in &self.generations {
+            generation.write(archive)?;
+        }
+
+        archive.write_i32(self.engine_version)?;
+        archive.write_i32(self.cooker_version)?;
+        archive.write_u32(u32::from(self.compression_flags))?;
+        archive.write_i32(i32::try_from(self.compressed_chunks.len())?)?;
+        for chunk in &self.compressed_chunks {
+            chunk.write(archive)?;
+        }
+
+        if profile.has_package_source {
+            archive.write_u32(self.package_source)?;
+        }
 
-        val.additional_packages_to_cook = read_array(archive, |ar| ar.read_fstring().unwrap())?;
-        val.unknown_structs = archive.read_i32()?;
-        for _ in 0..val.unknown_structs {
-            archive.seek(SeekFrom::Current(4 * 5))?;
-            read_array(archive, |ar| ar.read_i32())?;
+        if profile.has_additional_packages_to_cook {
+            archive.write_i32(i32::try_from(self.additional_packages_to_cook.len())?)?;
+            for package in &self.additional_packages_to_cook {
+                archive.write_fstring(package)?;
+            }
         }
 
-        val.garbage_size = archive.read_i32()?;
-        val.compression_chunkinfo_offset = archive.read_i32()?;
-        val.last_block_size = archive.read_i32()?;
+        if profile.has_texture_allocations {
+            archive.write_i32(i32::try_from(self.texture_allocations.len())?)?;
+            for allocation in &self.texture_allocations {
+                allocation.write(archive)?;
+            }
+        }
+
+        if profile.has_encrypted_chunk_info {
+            archive.write_i32(self.garbage_size)?;
+            archive.write_i32(self.compression_chunkinfo_offset)?;
+            archive.write_i32(self.last_block_size)?;
+        }
 
         Ok(())
     }
-}
\ No newline at end of file
+
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use aes::Aes256;
+    use block_modes::{BlockMode, Ecb, block_padding::NoPadding};
+
+    use super::*;
+    use crate::compression::compress_chunk;
+    use crate::profile::{RL_FILE_VERSION, RL_LICENSEE_ENCRYPTED};
+
+    const NAMES: [&str; 4] = ["None", "Core", "Package", "TestObject"];
+    /// Where Rocket League puts the chunk table in the encrypted region, after some garbage.
+    const CHUNK_INFO_OFFSET: usize = 16;
+    /// Small enough that the tables and the export data span several chunks of several blocks.
+    const CHUNK_SIZE: usize = 96;
+    const BLOCK_SIZE: usize = 40;
+
+    struct MemoryFile {
+        file_name: String,
+        data: Vec<u8>,
+        companions: HashMap<String, Vec<u8>>
+    }
+
+    impl GameFile for MemoryFile {
+
+        fn read(&self) -> Vec<u8> {
+            self.data.clone()
+        }
+
+        fn get_filename(&self) -> &String {
+            &self.file_name
+        }
+
+        fn read_companion(&self, suffix: &str) -> Option<Vec<u8>> {
+            self.companions.get(suffix).cloned()
+        }
+
+    }
+
+    fn key() -> FAesKey {
+        FAesKey { key: std::array::from_fn(|i| i as u8) }
+    }
+
+    fn open(data: Vec<u8>) -> UnPackage<MemoryFile> {
+        let file = MemoryFile { file_name: String::from("Test_T_SF.upk"), data, companions: HashMap::new() };
+        UnPackage::new(file, Arc::new(Mutex::new(vec![key()])))
+    }
+
+    fn export_data() -> Vec<u8> {
+        (0..48).collect()
+    }
+
+    fn writer(profile: GameProfile, big_endian: bool) -> FByteArchive {
+        let mut archive = FByteArchive::new(vec![]);
+        archive.set_big_endian(big_endian);
+        archive.set_profile(profile);
+
+        archive
+    }
+
+    fn new_summary(licensee_version: u16, compression_flags: ECompressionFlags) -> FPackageFileSummary {
+        FPackageFileSummary {
+            magic: PACKAGE_MAGIC,
+            file_version: RL_FILE_VERSION,
+            licensee_version,
+            package_group: String::from("None"),
+            package_flags: PKG_Cooked,
+            compression_flags,
+            ..FPackageFileSummary::default()
+        }
+    }
+
+    fn write_summary(summary: &FPackageFileSummary, big_endian: bool) -> Vec<u8> {
+        let mut archive = writer(GameProfile::detect(summary.file_version, summary.licensee_version), big_endian);
+        summary.write(&mut archive).unwrap();
+
+        std::mem::take(archive.get_mut())
+    }
+
+    fn read_summary(data: &[u8]) -> (FPackageFileSummary, usize) {
+        let mut archive = FByteArchive::new(data.to_vec());
+        let mut summary = FPackageFileSummary::default();
+        FPackageFileSummary::serialize_with_profile(&mut summary, &mut archive, None).unwrap();
+
+        (summary, archive.seek(SeekFrom::Current(0)).unwrap() as usize)
+    }
+
+    /// The name, import and export tables followed by the data of the single export, laid out
+    /// from `base`. The offsets and counts are set on `summary`.
+    fn tables(summary: &mut FPackageFileSummary, base: usize, big_endian: bool) -> Vec<u8> {
+        let profile = GameProfile::detect(summary.file_version, summary.licensee_version);
+        let mut archive = writer(profile, big_endian);
+        let offset = |archive: &mut FByteArchive| (base as u64 + archive.seek(SeekFrom::Current(0)).unwrap()) as i32;
+
+        summary.name_offset = offset(&mut archive);
+        summary.name_count = NAMES.len() as i32;
+        for name in NAMES {
+            archive.write_fstring(name).unwrap();
+            archive.write_i64(0).unwrap();
+        }
+
+        summary.import_offset = offset(&mut archive);
+        summary.import_count = 1;
+        for value in [1, 0, 2, 0, 0, 1, 0] {
+            archive.write_i32(value).unwrap();
+        }
+
+        summary.export_offset = offset(&mut archive);
+        summary.export_count = 1;
+        let offset_size = if profile.has_wide_offsets { 8 } else { 4 };
+        let entry_size = 64 + offset_size;
+        for value in [-1, 0, 0, 3, 0, 0, 0, 0, export_data().len() as i32] {
+            archive.write_i32(value).unwrap();
+        }
+
+        // The export data comes right after the export table and the depends table.
+        let serial_offset = summary.export_offset + entry_size + 4;
+        if profile.has_wide_offsets {
+            archive.write_i64(i64::from(serial_offset)).unwrap();
+        } else {
+            archive.write_i32(serial_offset).unwrap();
+        }
+
+        for value in [0; 7] {
+            archive.write_i32(value).unwrap();
+        }
+
+        summary.depends_offset = offset(&mut archive);
+        assert_eq!(summary.depends_offset, summary.export_offset + entry_size);
+        archive.write_i32(0).unwrap();
+        archive.write_all(&export_data()).unwrap();
+
+        std::mem::take(archive.get_mut())
+    }
+
+    fn encrypt(region: &mut [u8]) {
+        let len = region.len();
+        let cipher = Ecb::<Aes256, NoPadding>::new_from_slices(&key().key, Default::default()).unwrap();
+        cipher.encrypt(region, len).unwrap();
+    }
+
+    /// A cooked package with its tables and export data in ZLIB chunks. With the Rocket League
+    /// licensee version the chunk table is in the header region, encrypted when `encrypted` is
+    /// set, otherwise it's in the summary like stock UE3 has it.
+    /// Returns the file and what it decompresses to.
+    fn compressed_package(licensee_version: u16, big_endian: bool, encrypted: bool) -> (Vec<u8>, Vec<u8>) {
+        let profile = GameProfile::detect(RL_FILE_VERSION, licensee_version);
+        let mut summary = new_summary(licensee_version, ECompressionFlags::Zlib);
+        summary.package_flags |= PKG_StoreCompressed;
+
+        let chunk_count = tables(&mut summary.clone(), 0, big_endian).len().div_ceil(CHUNK_SIZE);
+        if !profile.has_encrypted_chunk_info {
+            summary.compressed_chunks = vec![FCompressedChunk::default(); chunk_count];
+        }
+
+        let base = write_summary(&summary, big_endian).len();
+        let body = tables(&mut summary, base, big_endian);
+
+        let mut table_size = 0;
+        if profile.has_encrypted_chunk_info {
+            let chunk_size = if profile.has_wide_offsets { 24 } else { 16 };
+            table_size = (CHUNK_INFO_OFFSET + 4 + chunk_count * chunk_size + 15) & !15;
+            summary.compression_chunkinfo_offset = CHUNK_INFO_OFFSET as i32;
+        }
+
+        summary.header_size = (base + table_size) as i32;
+        let mut chunks = vec![];
+        let mut blobs = vec![];
+        for (index, part) in body.chunks(CHUNK_SIZE).enumerate() {
+            let blob = compress_chunk(part, BLOCK_SIZE, big_endian);
+            chunks.push(FCompressedChunk {
+                uncompressed_offset: (base + index * CHUNK_SIZE) as i32,
+                uncompressed_size: part.len() as i32,
+                compressed_offset: (base + table_size + blobs.len()) as i32,
+                compressed_size: blob.len() as i32
+            });
+            blobs.extend(blob);
+        }
+
+        let mut region = writer(profile, big_endian);
+        if profile.has_encrypted_chunk_info {
+            region.write_all(&[0xAB; CHUNK_INFO_OFFSET]).unwrap();
+            region.write_i32(chunks.len() as i32).unwrap();
+            for chunk in &chunks {
+                chunk.write(&mut region).unwrap();
+            }
+        } else {
+            summary.compressed_chunks = chunks;
+        }
+
+        let mut region = std::mem::take(region.get_mut());
+        region.resize(table_size, 0);
+        if encrypted {
+            encrypt(&mut region);
+        }
+
+        let header = write_summary(&summary, big_endian);
+        assert_eq!(header.len(), base);
+        ([header.as_slice(), &region, &blobs].concat(), [header, body].concat())
+    }
+
+    /// A cooked package that was never compressed, its tables right after the summary.
+    fn uncompressed_package(licensee_version: u16) -> Vec<u8> {
+        let mut summary = new_summary(licensee_version, ECompressionFlags::None);
+        let base = write_summary(&summary, false).len();
+        let body = tables(&mut summary, base, false);
+        summary.header_size = summary.depends_offset;
+
+        [write_summary(&summary, false), body].concat()
+    }
+
+    /// Reads the summary and runs the decryption step, returning the resulting file data.
+    fn decrypt(package: &mut UnPackage<MemoryFile>) -> Vec<u8> {
+        let mut archive = package.load_summary().unwrap();
+        package.decrypt(&mut archive).unwrap();
+
+        archive.get_mut().clone()
+    }
+
+    fn assert_export_data(package: &UnPackage<MemoryFile>, data: &[u8]) {
+        let export = &package.exports[0];
+        let start = export.serial_offset as usize;
+        assert_eq!(data[start..start + export.serial_size as usize], export_data());
+    }
+
+    #[test]
+    fn compressed_package_is_decrypted_and_inflated() {
+        let (file, uncompressed) = compressed_package(RL_LICENSEE_ENCRYPTED, false, true);
+        let mut package = open(file);
+        let mut archive = package.load().unwrap();
+
+        assert!(package.encrypted);
+        assert_eq!(archive.get_mut(), &uncompressed);
+        assert_eq!(package.names.iter().map(|name| name.name.as_str()).collect::<Vec<_>>(), NAMES);
+        assert_eq!(package.get_object_path(1).as_deref(), Some("TestObject"));
+        assert_eq!(package.get_object_name(-1).as_deref(), Some("Core"));
+        assert_export_data(&package, &uncompressed);
+    }
+
+    #[test]
+    fn uncompressed_output_rewrites_the_summary() {
+        let (file, uncompressed) = compressed_package(RL_LICENSEE_ENCRYPTED, false, true);
+        let mut package = open(file);
+        let mut archive = package.load_data().unwrap();
+        package.strip_compression(&mut archive).unwrap();
+
+        let output = archive.get_mut().clone();
+        let (summary, summary_size) = read_summary(&output);
+        assert_eq!(summary.compression_flags, ECompressionFlags::None);
+        assert_eq!(summary.package_flags & PKG_StoreCompressed, 0);
+        assert_eq!(summary.compression_chunkinfo_offset, 0);
+        assert_eq!(summary.name_offset, package.summary.name_offset);
+        assert_eq!(output[summary_size..], uncompressed[summary_size..]);
+
+        // Tools that don't know about the encryption or the chunks read it as a plain package.
+        let mut reopened = open(output.clone());
+        let mut archive = reopened.load().unwrap();
+        assert!(!reopened.encrypted);
+        assert_eq!(archive.get_mut(), &output);
+        assert_export_data(&reopened, &output);
+    }
+
+    #[test]
+    fn uncompressed_output_pads_the_smaller_summary() {
+        // Stock UE3 lists the chunks in the summary, so dropping them shrinks it.
+        let (file, uncompressed) = compressed_package(0, false, false);
+        let mut package = open(file);
+        let mut archive = package.load_data().unwrap();
+        package.strip_compression(&mut archive).unwrap();
+
+        let output = archive.get_mut().clone();
+        let (summary, summary_size) = read_summary(&output);
+        assert!(summary.compressed_chunks.is_empty());
+        assert_eq!(summary.compression_flags, ECompressionFlags::None);
+        assert!(summary_size < package.summary_size);
+        assert!(output[summary_size..package.summary_size].iter().all(|b| *b == 0));
+        assert_eq!(output[package.summary_size..], uncompressed[package.summary_size..]);
+
+        let mut reopened = open(output.clone());
+        reopened.load().unwrap();
+        assert_eq!(reopened.names.len(), NAMES.len());
+        assert_export_data(&reopened, &output);
+    }
+
+    #[test]
+    fn big_endian_package_is_detected_from_its_magic() {
+        let (file, uncompressed) = compressed_package(RL_LICENSEE_ENCRYPTED, true, true);
+        assert_eq!(file[..4], PACKAGE_MAGIC.to_be_bytes());
+
+        let mut package = open(file.clone());
+        let mut archive = package.load().unwrap();
+        assert!(package.is_big_endian());
+        assert!(package.encrypted);
+        assert_eq!(package.summary.magic, PACKAGE_MAGIC);
+        assert_eq!(package.summary.file_version, RL_FILE_VERSION);
+        assert_eq!(archive.get_mut(), &uncompressed);
+        assert_eq!(package.names.iter().map(|name| name.name.as_str()).collect::<Vec<_>>(), NAMES);
+        assert_export_data(&package, &uncompressed);
+
+        // Export data is read back in the package's byte order.
+        let mut package = open(file);
+        let mut reader = package.open_reader().unwrap();
+        let mut object = package.object_archive(reader.read_export(&package.exports[0]).unwrap());
+        assert_eq!(object.read_u32().unwrap(), u32::from_be_bytes([0, 1, 2, 3]));
+    }
+
+    #[test]
+    fn plain_chunk_table_is_left_untouched() {
+        let (file, uncompressed) = compressed_package(RL_LICENSEE_ENCRYPTED, false, false);
+        let mut package = open(file.clone());
+        assert_eq!(decrypt(&mut package), file);
+        assert!(!package.encrypted);
+
+        let mut archive = package.load().unwrap();
+        assert_eq!(archive.get_mut(), &uncompressed);
+    }
+
+    #[test]
+    fn plain_names_are_left_untouched() {
+        let file = uncompressed_package(RL_LICENSEE_ENCRYPTED);
+        let mut package = open(file.clone());
+        assert_eq!(decrypt(&mut package), file);
+        assert!(!package.encrypted);
+        assert!(package.decompressed);
+
+        let mut archive = package.load().unwrap();
+        assert_eq!(archive.get_mut(), &file);
+        assert_eq!(package.names.len(), NAMES.len());
+        assert_export_data(&package, &file);
+    }
+
+    #[test]
+    fn second_pass_over_a_decrypted_package_changes_nothing() {
+        let (file, uncompressed) = compressed_package(RL_LICENSEE_ENCRYPTED, false, true);
+        let decrypted = decrypt(&mut open(file));
+
+        // Decrypted but still compressed: the chunk table parses as it is.
+        let mut package = open(decrypted.clone());
+        assert_eq!(decrypt(&mut package), decrypted);
+        assert!(!package.encrypted);
+        assert_eq!(package.load().unwrap().get_mut(), &uncompressed);
+
+        // Decrypted and decompressed, like the raw output: the names are where the chunk table was.
+        let mut package = open(uncompressed.clone());
+        assert_eq!(decrypt(&mut package), uncompressed);
+        assert!(!package.encrypted);
+        assert!(package.decompressed);
+        assert_eq!(package.load().unwrap().get_mut(), &uncompressed);
+    }
+
+    #[test]
+    fn key_that_parses_is_picked() {
+        let (file, uncompressed) = compressed_package(RL_LICENSEE_ENCRYPTED, false, true);
+        let mut package = open(file);
+        package.keys.lock().unwrap().insert(0, FAesKey { key: [0xCD; 32] });
+
+        assert_eq!(package.load().unwrap().get_mut(), &uncompressed);
+        assert!(package.encrypted);
+    }
+
+    #[test]
+    fn plain_names_are_recognized() {
+        let name = |len: i32, text: &[u8], big_endian: bool| {
+            let length = if big_endian { len.to_be_bytes() } else { len.to_le_bytes() };
+            let mut archive = FByteArchive::new([&length[..], text].concat());
+            archive.set_big_endian(big_endian);
+            is_plain_name(&mut archive)
+        };
+
+        assert!(name(5, b"Core\0", false));
+        assert!(name(5, b"Core\0", true));
+        assert!(name(-3, &[b'O', 0, b'K', 0, 0, 0], false));
+        assert!(name(-3, &[0, b'O', 0, b'K', 0, 0], true));
+        assert!(!name(0, b"", false));
+        assert!(!name(5, b"Core!", false));
+        assert!(!name(5, b"Co\x01e\0", false));
+        assert!(!name(5, b"Core", false));
+        assert!(!name(2000, &[b'a'; 2000], false));
+    }
+
+    #[test]
+    fn plain_chunk_table_needs_every_chunk_to_start_with_the_tag() {
+        let mut file = vec![0u8; 64];
+        file[32..36].copy_from_slice(&PACKAGE_MAGIC.to_le_bytes());
+        let table = |chunks: &[FCompressedChunk]| {
+            let mut archive = writer(GameProfile::rocket_league(), false);
+            archive.write_i32(chunks.len() as i32).unwrap();
+            for chunk in chunks {
+                chunk.write(&mut archive).unwrap();
+            }
+
+            archive.seek(SeekFrom::Start(0)).unwrap();
+            archive
+        };
+
+        let chunk = FCompressedChunk { uncompressed_offset: 100, uncompressed_size: 200, compressed_offset: 32, compressed_size: 32 };
+        assert!(is_plain_chunk_table(&mut table(std::slice::from_ref(&chunk)), &file));
+        assert!(!is_plain_chunk_table(&mut table(&[]), &file));
+        assert!(!is_plain_chunk_table(&mut table(&[FCompressedChunk { compressed_offset: 0, ..chunk.clone() }]), &file));
+        assert!(!is_plain_chunk_table(&mut table(&[FCompressedChunk { compressed_size: 64, ..chunk.clone() }]), &file));
+        assert!(!is_plain_chunk_table(&mut table(&[chunk.clone(), FCompressedChunk { uncompressed_size: 0, ..chunk }]), &file));
+    }
+
+    #[test]
+    fn package_without_chunks_is_read_as_it_is() {
+        let file = uncompressed_package(0);
+        let mut package = open(file.clone());
+        assert_eq!(package.load().unwrap().get_mut(), &file);
+        assert_eq!(package.names.len(), NAMES.len());
+
+        let mut package = open(file.clone());
+        let mut reader = package.open_reader().unwrap();
+        assert_eq!(reader.len(), file.len() as u64);
+        assert_eq!(reader.read_export(&package.exports[0]).unwrap(), export_data());
+    }
+
+    #[test]
+    fn fully_compressed_package_is_inflated() {
+        let file = uncompressed_package(RL_LICENSEE_ENCRYPTED);
+        let mut package = open(compress_chunk(&file, BLOCK_SIZE, false));
+        package.file.companions.insert(String::from(UNCOMPRESSED_SIZE_SUFFIX), format!("{}\n", file.len()).into_bytes());
+
+        assert_eq!(package.load().unwrap().get_mut(), &file);
+        assert!(!package.encrypted);
+        assert_export_data(&package, &file);
+    }
+
+    #[test]
+    fn fully_compressed_big_endian_package_is_inflated() {
+        // A compressed package inflated as a whole still has the chunk flags in its summary.
+        let (_, file) = compressed_package(RL_LICENSEE_ENCRYPTED, true, false);
+        let mut package = open(compress_chunk(&file, BLOCK_SIZE, true));
+
+        assert_eq!(package.load().unwrap().get_mut(), &file);
+        assert!(package.is_big_endian());
+        assert_eq!(package.names.len(), NAMES.len());
+    }
+
+    #[test]
+    fn fully_compressed_package_must_match_its_uncompressed_size() {
+        let file = uncompressed_package(RL_LICENSEE_ENCRYPTED);
+        let mut package = open(compress_chunk(&file, BLOCK_SIZE, false));
+        package.file.companions.insert(String::from(UNCOMPRESSED_SIZE_SUFFIX), format!("{}", file.len() + 1).into_bytes());
+
+        assert!(package.load().is_err());
+    }
+
+}
@@ -0,0 +1,170 @@
+//! The item catalogue: the product asset exports of the cooked packages, normalized to one row
+//! per item whatever the product class.
+
+use std::io::Write;
+
+use serde_json::{Value, json};
+
+use crate::file::GameFile;
+use crate::package::{UnPackage, RF_ClassDefaultObject};
+use crate::properties::{FPropertyTag, find_property, read_object_properties};
+use crate::reader::FPackageReader;
+use crate::Result;
+
+/// Products are the exports of the classes starting with this, like `ProductAsset_Body_TA`.
+const PRODUCT_CLASS_PREFIX: &str = "ProductAsset";
+const SLOT_PREFIX: &str = "ProductSlot_";
+const CLASS_SUFFIX: &str = "_TA";
+
+const ID_PROPERTIES: [&str; 2] = ["ID", "ProductID"];
+const LABEL_PROPERTIES: [&str; 2] = ["Label", "LongLabel"];
+const ASSET_PACKAGE_PROPERTIES: [&str; 2] = ["AssetPackageName", "PackageName"];
+
+const CSV_HEADER: [&str; 10] = ["id", "name", "label", "slot", "quality", "paintable", "class", "package", "asset_package", "path"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FProduct {
+    pub id: Option<i32>,
+    /// The name of the product asset.
+    pub name: String,
+    pub label: Option<String>,
+    /// The slot the item goes in, like `Body` or `Wheels`.
+    pub slot: Option<String>,
+    pub quality: Option<String>,
+    pub paintable: Option<bool>,
+    pub class: String,
+    /// The package the product asset was read from, without its extension.
+    pub package: String,
+    /// The package holding the item's meshes and textures.
+    pub asset_package: String,
+    /// The path of the product asset in its package.
+    pub path: String
+}
+
+impl FProduct {
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "name": self.name,
+            "label": self.label,
+            "slot": self.slot,
+            "quality": self.quality,
+            "paintable": self.paintable,
+            "class": self.class,
+            "package": self.package,
+            "asset_package": self.asset_package,
+            "path": self.path
+        })
+    }
+
+    fn csv_fields(&self) -> [String; 10] {
+        [
+            self.id.map(|id| id.to_string()).unwrap_or_default(),
+            self.name.clone(),
+            self.label.clone().unwrap_or_default(),
+            self.slot.clone().unwrap_or_default(),
+            self.quality.clone().unwrap_or_default(),
+            self.paintable.map(|paintable| paintable.to_string()).unwrap_or_default(),
+            self.class.clone(),
+            self.package.clone(),
+            self.asset_package.clone(),
+            self.path.clone()
+        ]
+    }
+
+}
+
+/// Reads the product assets exported by `package`, the class defaults excluded.
+pub fn read_products<F: GameFile>(package: &UnPackage<F>, reader: &mut FPackageReader) -> Result<Vec<FProduct>> {
+    let package_name = package.file.get_filename();
+    let package_name = package_name.rsplit_once('.').map_or(package_name.as_str(), |(stem, _)| stem).to_string();
+
+    let mut products = vec![];
+    for (index, export) in package.exports.iter().enumerate() {
+        let class = match package.get_class_name(export) {
+            Some(class) if class.starts_with(PRODUCT_CLASS_PREFIX) => class,
+            _ => continue
+        };
+
+        let name = package.get_name(&export.object_name).unwrap_or_default();
+        if export.object_flags & RF_ClassDefaultObject != 0 || name.starts_with("Default__") {
+            continue;
+        }
+
+        // A product that can't be read is left out, the rest of the package is still listed.
+        let path = package.get_object_path(i32::try_from(index + 1)?).unwrap_or_default();
+        let properties = match reader.read_export(export).and_then(|data| read_object_properties(&mut package.object_archive(data), &package.names)) {
+            Ok(properties) => properties,
+            Err(err) => {
+                log::warn!("skipped product {}.{}: {}", package_name, path, err);
+                continue;
+            }
+        };
+        let first = |names: &[&str]| names.iter().find_map(|name| find_property(&properties, name));
+
+        let slot = find_property(&properties, "Slot")
+            .and_then(FPropertyTag::as_object)
+            .and_then(|slot| package.get_object_name(slot))
+            .map(|slot| slot.trim_start_matches(SLOT_PREFIX).to_string())
+            .or_else(|| class_slot(&class));
+
+        products.push(FProduct {
+            id: first(&ID_PROPERTIES).and_then(FPropertyTag::as_int),
+            label: first(&LABEL_PROPERTIES).and_then(FPropertyTag::as_str).map(str::to_string),
+            slot,
+            quality: find_property(&properties, "Quality").and_then(FPropertyTag::as_str).map(str::to_string),
+            paintable: find_property(&properties, "bPaintable").and_then(FPropertyTag::as_bool),
+            asset_package: first(&ASSET_PACKAGE_PROPERTIES).and_then(FPropertyTag::as_str).map_or_else(|| package_name.clone(), str::to_string),
+            package: package_name.clone(),
+            path,
+            name,
+            class
+        });
+    }
+
+    Ok(products)
+}
+
+/// The slot a product class is for, `ProductAsset_Body_TA` being for `Body`.
+fn class_slot(class: &str) -> Option<String> {
+    let slot = class.strip_prefix(PRODUCT_CLASS_PREFIX)?.strip_prefix('_')?;
+    let slot = slot.strip_suffix(CLASS_SUFFIX).unwrap_or(slot);
+
+    (!slot.is_empty()).then(|| slot.to_string())
+}
+
+/// Sorts products by ID, the ones without one last, then by path and package. The copies of a
+/// product cooked into several packages are dropped, the first package by name is kept.
+pub fn normalize_products(products: &mut Vec<FProduct>) {
+    products.sort_by(|a, b| {
+        (a.id.is_none(), a.id, &a.path, &a.package).cmp(&(b.id.is_none(), b.id, &b.path, &b.package))
+    });
+    products.dedup_by(|duplicate, kept| duplicate.id == kept.id && duplicate.path == kept.path && duplicate.class == kept.class);
+}
+
+pub fn write_products_json<W: Write>(products: &[FProduct], writer: &mut W) -> Result<()> {
+    let document: Vec<Value> = products.iter().map(FProduct::to_json).collect();
+    serde_json::to_writer_pretty(writer, &document)?;
+
+    Ok(())
+}
+
+/// Writes a CSV with a header row, quoting the fields as RFC 4180 does.
+pub fn write_products_csv<W: Write>(products: &[FProduct], writer: &mut W) -> Result<()> {
+    writeln!(writer, "{}", CSV_HEADER.join(","))?;
+    for product in products {
+        let fields: Vec<String> = product.csv_fields().iter().map(|field| csv_field(field)).collect();
+        writeln!(writer, "{}", fields.join(","))?;
+    }
+
+    Ok(())
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
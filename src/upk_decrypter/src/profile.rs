@@ -0,0 +1,94 @@
+use crate::package::ECompressionFlags;
+
+/// The first licensee version of Rocket League with the encrypted header and 64-bit offsets.
+pub const RL_LICENSEE_ENCRYPTED: u16 = 22;
+pub const RL_FILE_VERSION: u16 = 868;
+
+const VER_NET_OBJECT_COUNTS: u16 = 322;
+const VER_EXPORT_PACKAGE_FLAGS: u16 = 475;
+const VER_PACKAGE_SOURCE: u16 = 482;
+const VER_ADDITIONAL_PACKAGES_TO_COOK: u16 = 516;
+const VER_REMOVED_COMPONENT_MAP: u16 = 543;
+const VER_THUMBNAIL_TABLE: u16 = 584;
+const VER_IMPORT_EXPORT_GUIDS: u16 = 623;
+const VER_BYTE_PROPERTY_ENUM: u16 = 633;
+const VER_BOOL_PROPERTY_BYTE: u16 = 673;
+const VER_TEXTURE_ALLOCATIONS: u16 = 767;
+
+/// The version-dependent parts of the package layout. Picked from the summary's
+/// `file_version`/`licensee_version` with `GameProfile::detect`, or set explicitly on the package
+/// for titles that can't be told apart by their versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameProfile {
+    /// `import_export_guids_offset`, `import_guids_count` and `export_guids_count` after `depends_offset`.
+    pub has_import_export_guids: bool,
+    /// `thumbnail_table_offset` after `depends_offset`.
+    pub has_thumbnail_table: bool,
+    /// `package_source` after the compressed chunk array.
+    pub has_package_source: bool,
+    pub has_additional_packages_to_cook: bool,
+    pub has_texture_allocations: bool,
+    /// Rocket League's `garbage_size`, `compression_chunkinfo_offset` and `last_block_size` trailer,
+    /// which also means the chunk table lives in the encrypted region instead of the summary.
+    pub has_encrypted_chunk_info: bool,
+    /// 64-bit offsets in the compressed chunks and in the export table's serial offsets.
+    pub has_wide_offsets: bool,
+    /// `net_object_count` in the generations and the export table.
+    pub has_net_object_counts: bool,
+    /// The component map in each export entry, removed in later UE3 versions.
+    pub has_export_component_map: bool,
+    pub has_export_package_flags: bool,
+    /// The enum name in the tag of a `ByteProperty`.
+    pub has_byte_property_enum: bool,
+    /// `BoolProperty` values stored in a single byte of the tag instead of four.
+    pub has_byte_bool_properties: bool,
+    /// The method of fully compressed packages, which can't be read from the summary before
+    /// they're inflated. Detected from the compressed data when unset.
+    pub fully_compressed_method: Option<ECompressionFlags>
+}
+
+impl Default for GameProfile {
+    fn default() -> Self {
+        Self::rocket_league()
+    }
+}
+
+impl GameProfile {
+
+    pub fn detect(file_version: u16, licensee_version: u16) -> Self {
+        if file_version == RL_FILE_VERSION && licensee_version >= RL_LICENSEE_ENCRYPTED {
+            return Self::rocket_league();
+        }
+
+        Self::ue3(file_version)
+    }
+
+    /// The stock UE3 layout for a given `file_version`.
+    pub fn ue3(file_version: u16) -> Self {
+        Self {
+            has_import_export_guids: file_version >= VER_IMPORT_EXPORT_GUIDS,
+            has_thumbnail_table: file_version >= VER_THUMBNAIL_TABLE,
+            has_package_source: file_version >= VER_PACKAGE_SOURCE,
+            has_additional_packages_to_cook: file_version >= VER_ADDITIONAL_PACKAGES_TO_COOK,
+            has_texture_allocations: file_version >= VER_TEXTURE_ALLOCATIONS,
+            has_encrypted_chunk_info: false,
+            has_wide_offsets: false,
+            has_net_object_counts: file_version >= VER_NET_OBJECT_COUNTS,
+            has_export_component_map: file_version < VER_REMOVED_COMPONENT_MAP,
+            has_export_package_flags: file_version >= VER_EXPORT_PACKAGE_FLAGS,
+            has_byte_property_enum: file_version >= VER_BYTE_PROPERTY_ENUM,
+            has_byte_bool_properties: file_version >= VER_BOOL_PROPERTY_BYTE,
+            fully_compressed_method: None
+        }
+    }
+
+    /// Rocket League since the packages are encrypted.
+    pub fn rocket_league() -> Self {
+        Self {
+            has_encrypted_chunk_info: true,
+            has_wide_offsets: true,
+            ..Self::ue3(RL_FILE_VERSION)
+        }
+    }
+
+}
@@ -0,0 +1,224 @@
+use std::io::SeekFrom;
+
+use crate::archive::{FArchive, FByteArchive, read_serializable};
+use crate::file::GameFile;
+use crate::package::{FName, FNameEntry, UnPackage, resolve_name};
+use crate::{Result, ParserError};
+
+const NAME_NONE: &str = "None";
+const MAX_PROPERTIES: usize = 4096;
+
+/// The value of a tagged property. Arrays and structs are kept as their raw serialized data,
+/// their layout depends on the inner type which the tag doesn't describe.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UPropertyValue {
+    Int(i32),
+    Float(f32),
+    Bool(bool),
+    Byte(u8),
+    /// A `ByteProperty` holding an enum value, resolved to its name.
+    Enum { enum_name: String, value: String },
+    Name(String),
+    /// An object reference, negative indices are imports and positive ones exports.
+    Object(i32),
+    Str(String),
+    Struct { struct_name: String, data: Vec<u8> },
+    Array { count: i32, data: Vec<u8> },
+    Unknown(Vec<u8>)
+}
+
+#[derive(Debug, Clone)]
+pub struct FPropertyTag {
+    pub name: String,
+    pub type_name: String,
+    pub size: i32,
+    pub array_index: i32,
+    pub value: UPropertyValue
+}
+
+impl FPropertyTag {
+
+    pub fn as_int(&self) -> Option<i32> {
+        match self.value {
+            UPropertyValue::Int(val) => Some(val),
+            _ => None
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f32> {
+        match self.value {
+            UPropertyValue::Float(val) => Some(val),
+            _ => None
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.value {
+            UPropertyValue::Bool(val) => Some(val),
+            _ => None
+        }
+    }
+
+    pub fn as_object(&self) -> Option<i32> {
+        match self.value {
+            UPropertyValue::Object(val) => Some(val),
+            _ => None
+        }
+    }
+
+    /// The value of name, string and enum properties.
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.value {
+            UPropertyValue::Name(val) | UPropertyValue::Str(val) => Some(val),
+            UPropertyValue::Enum { value, .. } => Some(value),
+            _ => None
+        }
+    }
+
+}
+
+/// Finds the first element of a property by name.
+pub fn find_property<'a>(properties: &'a [FPropertyTag], name: &str) -> Option<&'a FPropertyTag> {
+    properties.iter().find(|property| property.name.eq_ignore_ascii_case(name))
+}
+
+/// Reads the elements of an `ArrayProperty` of ints, such as the compressed track offsets of an animation.
+pub fn read_int_array<F: GameFile>(package: &UnPackage<F>, property: &FPropertyTag) -> Result<Vec<i32>> {
+    read_array_elements(package, property, |archive| archive.read_i32())
+}
+
+/// Reads the elements of an `ArrayProperty` of object references.
+pub fn read_object_array<F: GameFile>(package: &UnPackage<F>, property: &FPropertyTag) -> Result<Vec<i32>> {
+    read_int_array(package, property)
+}
+
+/// Reads the elements of an `ArrayProperty` of bytes, such as the raw data of a movie.
+pub fn read_byte_array<F: GameFile>(package: &UnPackage<F>, property: &FPropertyTag) -> Result<Vec<u8>> {
+    read_array_elements(package, property, |archive| archive.read_u8())
+}
+
+/// Reads the elements of an `ArrayProperty` of names.
+pub fn read_name_array<F: GameFile>(package: &UnPackage<F>, property: &FPropertyTag) -> Result<Vec<String>> {
+    read_array_elements(package, property, |archive| read_name(archive, &package.names))
+}
+
+/// Reads a `Vector` struct property.
+pub fn read_vector_property<F: GameFile>(package: &UnPackage<F>, property: &FPropertyTag) -> Result<[f32; 3]> {
+    read_struct(package, property, "Vector", |archive| Ok([archive.read_f32()?, archive.read_f32()?, archive.read_f32()?]))
+}
+
+/// Reads a `Rotator` struct property as pitch, yaw and roll, 65536 units being a full turn.
+pub fn read_rotator_property<F: GameFile>(package: &UnPackage<F>, property: &FPropertyTag) -> Result<[i32; 3]> {
+    read_struct(package, property, "Rotator", |archive| Ok([archive.read_i32()?, archive.read_i32()?, archive.read_i32()?]))
+}
+
+fn read_struct<F, T, R>(package: &UnPackage<F>, property: &FPropertyTag, expected: &str, read: R) -> Result<T>
+where F: GameFile, R: Fn(&mut FByteArchive) -> Result<T> {
+    match &property.value {
+        UPropertyValue::Struct { struct_name, data } if struct_name == expected => read(&mut package.object_archive(data.clone())),
+        _ => Err(Box::new(ParserError::new(&format!("{} is not a {}", property.name, expected))))
+    }
+}
+
+fn read_array_elements<F, T, R>(package: &UnPackage<F>, property: &FPropertyTag, read: R) -> Result<Vec<T>>
+where F: GameFile, R: Fn(&mut FByteArchive) -> Result<T> {
+    let (count, data) = match &property.value {
+        UPropertyValue::Array { count, data } => (*count, data),
+        _ => return Err(Box::new(ParserError::new(&format!("{} is not an array", property.name))))
+    };
+
+    let mut archive = package.object_archive(data.clone());
+    let mut elements = Vec::with_capacity(usize::try_from(count)?.min(data.len()));
+    for _ in 0..count {
+        elements.push(read(&mut archive)?);
+    }
+
+    Ok(elements)
+}
+
+/// Reads the `UObject` part of an export, its net index followed by the tagged properties.
+pub fn read_object_properties<Ar: FArchive>(archive: &mut Ar, names: &[FNameEntry]) -> Result<Vec<FPropertyTag>> {
+    if archive.profile().has_net_object_counts {
+        archive.read_i32()?;
+    }
+
+    read_properties(archive, names)
+}
+
+/// Reads tagged properties up to the terminating `None` tag.
+pub fn read_properties<Ar: FArchive>(archive: &mut Ar, names: &[FNameEntry]) -> Result<Vec<FPropertyTag>> {
+    let mut properties = vec![];
+    loop {
+        let name = read_name(archive, names)?;
+        if name == NAME_NONE {
+            return Ok(properties);
+        }
+
+        if properties.len() >= MAX_PROPERTIES {
+            return Err(Box::new(ParserError::new("Too many properties, the tag stream is probably misaligned")));
+        }
+
+        let type_name = read_name(archive, names)?;
+        let size = archive.read_i32()?;
+        let array_index = archive.read_i32()?;
+        let value = read_value(archive, names, &name, &type_name, size)?;
+
+        properties.push(FPropertyTag { name, type_name, size, array_index, value });
+    }
+}
+
+/// Reads the value of a tag, leaving the archive at the end of the `size` bytes the tag claims
+/// whatever the value took, so a value read wrong doesn't misalign the tags after it.
+fn read_value<Ar: FArchive>(archive: &mut Ar, names: &[FNameEntry], name: &str, type_name: &str, size: i32) -> Result<UPropertyValue> {
+    let profile = archive.profile();
+    let len = usize::try_from(size).map_err(|_| ParserError::new(&format!("Invalid size {} for a {}", size, type_name)))?;
+
+    // The tag carries extra data for a few types, which isn't part of `size`.
+    let tag_name = match type_name {
+        "BoolProperty" if profile.has_byte_bool_properties => return Ok(UPropertyValue::Bool(archive.read_u8()? != 0)),
+        "BoolProperty" => return Ok(UPropertyValue::Bool(archive.read_u32()? != 0)),
+        "StructProperty" => read_name(archive, names)?,
+        "ByteProperty" if profile.has_byte_property_enum => read_name(archive, names)?,
+        _ => String::from(NAME_NONE)
+    };
+
+    let start = archive.seek(SeekFrom::Current(0))?;
+    let value = match type_name {
+        "StructProperty" => UPropertyValue::Struct { struct_name: tag_name, data: read_raw(archive, len)? },
+        "ByteProperty" if tag_name == NAME_NONE || len == 1 => UPropertyValue::Byte(archive.read_u8()?),
+        "ByteProperty" => UPropertyValue::Enum { enum_name: tag_name, value: read_name(archive, names)? },
+        "IntProperty" => UPropertyValue::Int(archive.read_i32()?),
+        "FloatProperty" => UPropertyValue::Float(archive.read_f32()?),
+        "NameProperty" => UPropertyValue::Name(read_name(archive, names)?),
+        "ObjectProperty" | "ClassProperty" | "ComponentProperty" | "InterfaceProperty" => UPropertyValue::Object(archive.read_i32()?),
+        "StrProperty" => UPropertyValue::Str(archive.read_fstring()?.trim_end_matches('\0').to_owned()),
+        "ArrayProperty" => {
+            let count = archive.read_i32()?;
+            UPropertyValue::Array { count, data: read_raw(archive, len.saturating_sub(4))? }
+        },
+        _ => UPropertyValue::Unknown(read_raw(archive, len)?)
+    };
+
+    let end = start + len as u64;
+    let position = archive.seek(SeekFrom::Current(0))?;
+    if position != end {
+        log::warn!("{} ({}) took {} bytes but its tag says {}", name, type_name, position as i64 - start as i64, len);
+        archive.seek(SeekFrom::Start(end))?;
+    }
+
+    Ok(value)
+}
+
+fn read_raw<Ar: FArchive>(archive: &mut Ar, len: usize) -> Result<Vec<u8>> {
+    let mut data = vec![0u8; len];
+    archive.read_bytes_vec(&mut data)?;
+
+    Ok(data)
+}
+
+fn read_name<Ar: FArchive>(archive: &mut Ar, names: &[FNameEntry]) -> Result<String> {
+    let name: FName = read_serializable(archive)?;
+    let name = resolve_name(names, &name).ok_or_else(|| ParserError::new(&format!("Invalid name index {}", name.index)))?;
+
+    Ok(name)
+}
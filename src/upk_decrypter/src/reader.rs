@@ -0,0 +1,288 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::archive::{FArchive, FByteArchive};
+use crate::compression::{FCompressedChunk, decompress_chunk};
+use crate::package::{FObjectExport, ECompressionFlags};
+use crate::Result;
+
+const DEFAULT_CACHED_CHUNKS: usize = 4;
+
+/// A `Read + Seek` view over the logical (uncompressed) offsets of a package.
+/// Reads are mapped onto the `FCompressedChunk` covering them and chunks are inflated on demand,
+/// keeping the most recently used ones around. Anything not covered by a chunk (the summary)
+/// is read straight from the decrypted file.
+pub struct FPackageReader {
+    source: FByteArchive,
+    chunks: Vec<FCompressedChunk>,
+    compression_flags: ECompressionFlags,
+    cache: HashMap<usize, Vec<u8>>,
+    cache_order: VecDeque<usize>,
+    cache_size: usize,
+    position: u64,
+    size: u64
+}
+
+impl FPackageReader {
+
+    pub(crate) fn new(mut source: FByteArchive, mut chunks: Vec<FCompressedChunk>, compression_flags: ECompressionFlags) -> Self {
+        chunks.sort_by_key(|chunk| chunk.uncompressed_offset);
+
+        let size = match chunks.last() {
+            Some(chunk) => u64::try_from(i64::from(chunk.uncompressed_offset) + i64::from(chunk.uncompressed_size)).unwrap_or(0),
+            None => source.len() as u64
+        };
+
+        Self {
+            source,
+            chunks,
+            compression_flags,
+            cache: HashMap::new(),
+            cache_order: VecDeque::new(),
+            cache_size: DEFAULT_CACHED_CHUNKS,
+            position: 0,
+            size
+        }
+    }
+
+    /// Sets how many inflated chunks are kept in memory at once.
+    pub fn set_cache_size(&mut self, cache_size: usize) {
+        self.cache_size = cache_size.max(1);
+        while self.cache_order.len() > self.cache_size {
+            if let Some(index) = self.cache_order.pop_front() {
+                self.cache.remove(&index);
+            }
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+        self.cache_order.clear();
+    }
+
+    /// The size of the package once it's fully decompressed.
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Reads the serialized data of a single export, only inflating the chunks it spans.
+    pub fn read_export(&mut self, export: &FObjectExport) -> Result<Vec<u8>> {
+        let mut data = vec![0u8; usize::try_from(export.serial_size)?];
+        self.seek(SeekFrom::Start(u64::try_from(export.serial_offset)?))?;
+        self.read_exact(&mut data)?;
+
+        Ok(data)
+    }
+
+    fn find_chunk(&self, position: u64) -> Option<usize> {
+        let index = self.chunks.partition_point(|chunk| u64::try_from(chunk.uncompressed_offset).unwrap_or(0) <= position);
+        if index == 0 {
+            return None;
+        }
+
+        let chunk = &self.chunks[index - 1];
+        let end = i64::from(chunk.uncompressed_offset) + i64::from(chunk.uncompressed_size);
+        if position < u64::try_from(end).unwrap_or(0) {
+            return Some(index - 1);
+        }
+
+        None
+    }
+
+    fn chunk_data(&mut self, index: usize) -> Result<&[u8]> {
+        if !self.cache.contains_key(&index) {
+            let data = decompress_chunk(&mut self.source, &self.chunks[index], self.compression_flags)?;
+            if self.cache_order.len() >= self.cache_size {
+                if let Some(evicted) = self.cache_order.pop_front() {
+                    self.cache.remove(&evicted);
+                }
+            }
+
+            self.cache.insert(index, data);
+            self.cache_order.push_back(index);
+        }
+
+        Ok(self.cache[&index].as_slice())
+    }
+
+    fn read_uncovered(&mut self, position: u64, buf: &mut [u8]) -> usize {
+        let next_chunk = self.chunks.iter()
+            .map(|chunk| u64::try_from(chunk.uncompressed_offset).unwrap_or(0))
+            .find(|offset| *offset > position)
+            .unwrap_or(self.size);
+
+        let data = self.source.get_mut();
+        let end = next_chunk.min(data.len() as u64);
+        if position >= end {
+            // Gaps past the end of the file read back as zeroes, like the fully decompressed buffer.
+            let len = buf.len().min(usize::try_from(next_chunk - position).unwrap_or(usize::MAX));
+            buf[..len].fill(0);
+            return len;
+        }
+
+        let start = usize::try_from(position).unwrap_or(usize::MAX);
+        let len = buf.len().min(usize::try_from(end - position).unwrap_or(usize::MAX));
+        buf[..len].copy_from_slice(&data[start..start + len]);
+
+        len
+    }
+
+}
+
+impl Read for FPackageReader {
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.size {
+            return Ok(0);
+        }
+
+        let position = self.position;
+        let read = match self.find_chunk(position) {
+            Some(index) => {
+                let offset = usize::try_from(position - u64::try_from(self.chunks[index].uncompressed_offset).unwrap_or(0))
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+                let data = self.chunk_data(index)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+
+                // The position is inside the chunk's declared size, so running out of data
+                // means the chunk inflated to fewer bytes than its header says.
+                if offset >= data.len() {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                        format!("Chunk {} inflated to {} bytes, reading at offset {}", index, data.len(), offset)));
+                }
+
+                let len = buf.len().min(data.len() - offset);
+                buf[..len].copy_from_slice(&data[offset..offset + len]);
+                len
+            },
+            None => self.read_uncovered(position, buf)
+        };
+
+        self.position += read as u64;
+        Ok(read)
+    }
+
+}
+
+impl Seek for FPackageReader {
+
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset)
+        };
+
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            },
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative position"))
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::compress_chunk;
+
+    /// Plain bytes standing in for the summary, read straight from the file.
+    const HEADER_SIZE: usize = 32;
+
+    fn pattern(seed: u8, len: usize) -> Vec<u8> {
+        (0..len).map(|i| seed.wrapping_add(i as u8)).collect()
+    }
+
+    /// A file with a plain header followed by the compressed `parts`, each placed at its
+    /// uncompressed offset.
+    fn package(parts: &[(usize, &[u8])]) -> FPackageReader {
+        let mut file = pattern(0, HEADER_SIZE);
+        let mut chunks = vec![];
+        for (uncompressed_offset, data) in parts {
+            let blob = compress_chunk(data, 16, false);
+            chunks.push(FCompressedChunk {
+                uncompressed_offset: *uncompressed_offset as i32,
+                uncompressed_size: data.len() as i32,
+                compressed_offset: file.len() as i32,
+                compressed_size: blob.len() as i32
+            });
+            file.extend(blob);
+        }
+
+        FPackageReader::new(FByteArchive::new(file), chunks, ECompressionFlags::Zlib)
+    }
+
+    fn read_at(reader: &mut FPackageReader, offset: u64, len: usize) -> Vec<u8> {
+        let mut data = vec![0u8; len];
+        reader.seek(SeekFrom::Start(offset)).unwrap();
+        reader.read_exact(&mut data).unwrap();
+
+        data
+    }
+
+    #[test]
+    fn reads_across_chunk_boundaries() {
+        let (first, second) = (pattern(100, 64), pattern(200, 50));
+        let mut reader = package(&[(HEADER_SIZE, &first), (HEADER_SIZE + 64, &second)]);
+        reader.set_cache_size(1);
+
+        assert_eq!(reader.len(), (HEADER_SIZE + 114) as u64);
+        assert_eq!(read_at(&mut reader, 0, HEADER_SIZE + 114), [pattern(0, HEADER_SIZE), first.clone(), second.clone()].concat());
+
+        // Going back to the evicted chunk inflates it again.
+        assert_eq!(read_at(&mut reader, (HEADER_SIZE + 60) as u64, 8), [&first[60..], &second[..4]].concat());
+        assert_eq!(read_at(&mut reader, 20, 16), [&pattern(0, HEADER_SIZE)[20..], &first[..4]].concat());
+
+        let mut rest = vec![];
+        reader.seek(SeekFrom::End(-10)).unwrap();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, second[40..]);
+    }
+
+    #[test]
+    fn gaps_between_chunks_read_from_the_file_or_as_zeroes() {
+        let (first, second) = (pattern(100, 64), pattern(200, 32));
+        let mut reader = package(&[(HEADER_SIZE, &first), (1024, &second)]);
+        let file = reader.source.get_mut().clone();
+
+        let data = read_at(&mut reader, (HEADER_SIZE + 60) as u64, 1024 + 8 - HEADER_SIZE - 60);
+        let gap_end = 1024 - HEADER_SIZE - 60;
+        let in_file = file.len() - HEADER_SIZE - 64;
+        assert_eq!(data[..4], first[60..]);
+        assert_eq!(data[4..4 + in_file], file[HEADER_SIZE + 64..]);
+        assert!(data[4 + in_file..gap_end].iter().all(|b| *b == 0));
+        assert_eq!(data[gap_end..], second[..8]);
+    }
+
+    #[test]
+    fn read_export_only_covers_its_data() {
+        let data = pattern(7, 200);
+        let mut reader = package(&[(HEADER_SIZE, &data)]);
+        let export = FObjectExport { serial_offset: (HEADER_SIZE + 150) as i64, serial_size: 40, ..FObjectExport::default() };
+
+        assert_eq!(reader.read_export(&export).unwrap(), data[150..190]);
+
+        let past_end = FObjectExport { serial_offset: (HEADER_SIZE + 190) as i64, serial_size: 40, ..FObjectExport::default() };
+        assert!(reader.read_export(&past_end).is_err());
+    }
+
+    #[test]
+    fn short_chunk_fails_the_read() {
+        let data = pattern(0, 48);
+        let mut reader = package(&[(HEADER_SIZE, &data)]);
+        reader.chunks[0].uncompressed_size = 64;
+        reader.size = (HEADER_SIZE + 64) as u64;
+
+        let mut buf = vec![0u8; 64];
+        reader.seek(SeekFrom::Start(HEADER_SIZE as u64)).unwrap();
+        assert!(reader.read_exact(&mut buf).is_err());
+    }
+
+}
@@ -0,0 +1,349 @@
+//! The compiled UnrealScript of a package: the fields of its classes, which are functions,
+//! states and properties linked through their `Next` field, and the bytecode of the structs.
+
+#![allow(non_upper_case_globals)]
+
+use std::collections::HashSet;
+
+use crate::archive::{FArchive, FByteArchive, read_serializable};
+use crate::file::GameFile;
+use crate::package::{FName, UnPackage};
+use crate::properties::{FPropertyTag, read_object_properties, read_properties};
+use crate::reader::FPackageReader;
+use crate::{Result, ParserError};
+
+pub const FUNC_Final: u32 = 0x00000001;
+pub const FUNC_Defined: u32 = 0x00000002;
+pub const FUNC_Iterator: u32 = 0x00000004;
+pub const FUNC_Latent: u32 = 0x00000008;
+pub const FUNC_PreOperator: u32 = 0x00000010;
+pub const FUNC_Singular: u32 = 0x00000020;
+pub const FUNC_Net: u32 = 0x00000040;
+pub const FUNC_NetReliable: u32 = 0x00000080;
+pub const FUNC_Simulated: u32 = 0x00000100;
+pub const FUNC_Exec: u32 = 0x00000200;
+pub const FUNC_Native: u32 = 0x00000400;
+pub const FUNC_Event: u32 = 0x00000800;
+pub const FUNC_Operator: u32 = 0x00001000;
+pub const FUNC_Static: u32 = 0x00002000;
+pub const FUNC_Public: u32 = 0x00020000;
+pub const FUNC_Private: u32 = 0x00040000;
+pub const FUNC_Protected: u32 = 0x00080000;
+pub const FUNC_Delegate: u32 = 0x00100000;
+pub const FUNC_NetServer: u32 = 0x00200000;
+pub const FUNC_NetClient: u32 = 0x01000000;
+
+pub const CPF_Edit: u64 = 0x0000000000000001;
+pub const CPF_Const: u64 = 0x0000000000000002;
+pub const CPF_OptionalParm: u64 = 0x0000000000000010;
+pub const CPF_Net: u64 = 0x0000000000000020;
+pub const CPF_Parm: u64 = 0x0000000000000080;
+pub const CPF_OutParm: u64 = 0x0000000000000100;
+pub const CPF_ReturnParm: u64 = 0x0000000000000400;
+pub const CPF_CoerceParm: u64 = 0x0000000000000800;
+pub const CPF_Native: u64 = 0x0000000000001000;
+pub const CPF_Transient: u64 = 0x0000000000002000;
+pub const CPF_Config: u64 = 0x0000000000004000;
+
+pub const CLASS_Native: u32 = 0x00000080;
+
+/// The probe mask shrank to 32 bits and the ignore mask went away in this version.
+const VER_REDUCED_PROBE_MASK: u16 = 691;
+const MAX_CHILDREN: usize = 65536;
+
+/// The fields every `UStruct` serializes, the bytecode included.
+#[derive(Debug, Clone)]
+pub struct UStruct {
+    pub properties: Vec<FPropertyTag>,
+    /// The next field of the outer struct.
+    pub next: i32,
+    pub super_struct: i32,
+    /// The first field of the struct, the others following through their `next`.
+    pub children: i32,
+    pub line: i32,
+    pub text_pos: i32,
+    /// The size of the bytecode once loaded, where object references take a pointer.
+    pub bytecode_size: i32,
+    /// The bytecode as serialized, object references taking four bytes.
+    pub script: Vec<u8>
+}
+
+impl UStruct {
+
+    /// Parses the serialized data of a struct export of `package`, the class defaults and
+    /// script structs included but not their own fields.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, data: Vec<u8>, is_class: bool) -> Result<Self> {
+        Self::read_from(package, &mut package.object_archive(data), is_class)
+    }
+
+    pub(crate) fn read_from<F: GameFile>(package: &UnPackage<F>, archive: &mut FByteArchive, is_class: bool) -> Result<Self> {
+        let properties = read_field_properties(package, archive, is_class)?;
+        let next = archive.read_i32()?;
+        let super_struct = archive.read_i32()?;
+        let _script_text = archive.read_i32()?;
+        let children = archive.read_i32()?;
+        let _cpp_text = archive.read_i32()?;
+        let line = archive.read_i32()?;
+        let text_pos = archive.read_i32()?;
+        let bytecode_size = archive.read_i32()?;
+        let storage_size = archive.read_i32()?;
+
+        let remaining = archive.len() as u64 - archive.seek(std::io::SeekFrom::Current(0))?;
+        if storage_size < 0 || u64::try_from(storage_size)? > remaining {
+            return Err(Box::new(ParserError::new(&format!("Invalid script size {}", storage_size))));
+        }
+
+        let mut script = vec![0u8; usize::try_from(storage_size)?];
+        archive.read_bytes(&mut script)?;
+
+        Ok(Self { properties, next, super_struct, children, line, text_pos, bytecode_size, script })
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct UFunction {
+    pub base: UStruct,
+    /// The index of the native implementation, 0 for script and non-indexed natives.
+    pub native_index: u16,
+    pub operator_precedence: u8,
+    pub function_flags: u32,
+    pub rep_offset: Option<u16>,
+    /// The name the function is called by, the operator symbol for operators.
+    pub friendly_name: String
+}
+
+impl UFunction {
+
+    /// Parses the serialized data of a `Function` export of `package`.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, data: Vec<u8>) -> Result<Self> {
+        let mut archive = package.object_archive(data);
+        let base = UStruct::read_from(package, &mut archive, false)?;
+        let native_index = archive.read_u16()?;
+        let operator_precedence = archive.read_u8()?;
+        let function_flags = archive.read_u32()?;
+        let rep_offset = if function_flags & FUNC_Net != 0 { Some(archive.read_u16()?) } else { None };
+        let friendly_name = read_name(package, &mut archive)?;
+
+        Ok(Self { base, native_index, operator_precedence, function_flags, rep_offset, friendly_name })
+    }
+
+    pub fn has_flag(&self, flag: u32) -> bool {
+        self.function_flags & flag != 0
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct UState {
+    pub base: UStruct,
+    pub probe_mask: u64,
+    /// The offset of the label table in the bytecode, `0xFFFF` without state code.
+    pub label_table_offset: u16,
+    pub state_flags: u32,
+    /// The functions of the state by name.
+    pub functions: Vec<(String, i32)>
+}
+
+impl UState {
+
+    /// Parses the serialized data of a `State` export of `package`, or the state part of a
+    /// `Class` export, whose auto state code lives there.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, data: Vec<u8>, is_class: bool) -> Result<Self> {
+        Self::read_from(package, &mut package.object_archive(data), is_class)
+    }
+
+    pub(crate) fn read_from<F: GameFile>(package: &UnPackage<F>, archive: &mut FByteArchive, is_class: bool) -> Result<Self> {
+        let base = UStruct::read_from(package, archive, is_class)?;
+        let probe_mask = if package.summary.file_version >= VER_REDUCED_PROBE_MASK {
+            u64::from(archive.read_u32()?)
+        } else {
+            let probe_mask = archive.read_u64()?;
+            let _ignore_mask = archive.read_u64()?;
+            probe_mask
+        };
+        let label_table_offset = archive.read_u16()?;
+        let state_flags = archive.read_u32()?;
+
+        let count = archive.read_i32()?;
+        let mut functions = vec![];
+        for _ in 0..count {
+            functions.push((read_name(package, archive)?, archive.read_i32()?));
+        }
+
+        Ok(Self { base, probe_mask, label_table_offset, state_flags, functions })
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct UScriptStruct {
+    pub base: UStruct,
+    pub struct_flags: u32,
+    /// The default values of the struct's properties.
+    pub defaults: Vec<FPropertyTag>
+}
+
+impl UScriptStruct {
+
+    /// Parses the serialized data of a `ScriptStruct` export of `package`.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, data: Vec<u8>) -> Result<Self> {
+        let mut archive = package.object_archive(data);
+        let base = UStruct::read_from(package, &mut archive, false)?;
+        let struct_flags = archive.read_u32()?;
+        let defaults = read_properties(&mut archive, &package.names)?;
+
+        Ok(Self { base, struct_flags, defaults })
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct UEnum {
+    pub next: i32,
+    /// The names of the values, the last one being the generated `_MAX`.
+    pub names: Vec<String>
+}
+
+impl UEnum {
+
+    /// Parses the serialized data of an `Enum` export of `package`.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, data: Vec<u8>) -> Result<Self> {
+        let mut archive = package.object_archive(data);
+        read_field_properties(package, &mut archive, false)?;
+        let next = archive.read_i32()?;
+
+        let count = archive.read_i32()?;
+        if count < 0 || usize::try_from(count)? > MAX_CHILDREN {
+            return Err(Box::new(ParserError::new(&format!("Invalid enum size {}", count))));
+        }
+
+        let names = (0..count).map(|_| read_name(package, &mut archive)).collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { next, names })
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct UProperty {
+    /// The property class, like `IntProperty`.
+    pub class: String,
+    pub next: i32,
+    pub array_dim: i32,
+    pub property_flags: u64,
+    pub category: String,
+    /// The enum giving the static array size, if any.
+    pub array_enum: i32,
+    pub rep_offset: Option<u16>,
+    /// The objects the type refers to: the enum of a byte, the class of an object (then the
+    /// meta class of a class), the struct of a struct, the inner property of an array, the
+    /// function of a delegate, the interface class of an interface, the key and value of a map.
+    pub references: Vec<i32>
+}
+
+impl UProperty {
+
+    /// Parses the serialized data of a property export of `package` whose class is `class`.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, class: &str, data: Vec<u8>) -> Result<Self> {
+        let mut archive = package.object_archive(data);
+        read_field_properties(package, &mut archive, false)?;
+        let next = archive.read_i32()?;
+        let array_dim = archive.read_i32()?;
+        let property_flags = archive.read_u64()?;
+        let category = read_name(package, &mut archive)?;
+        let array_enum = archive.read_i32()?;
+        let rep_offset = if property_flags & CPF_Net != 0 { Some(archive.read_u16()?) } else { None };
+
+        let reference_count = match class {
+            "ByteProperty" | "ObjectProperty" | "ComponentProperty" | "StructProperty" | "ArrayProperty" | "InterfaceProperty" => 1,
+            "ClassProperty" | "DelegateProperty" | "MapProperty" => 2,
+            _ => 0
+        };
+        let references = (0..reference_count).map(|_| archive.read_i32()).collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { class: class.to_string(), next, array_dim, property_flags, category, array_enum, rep_offset, references })
+    }
+
+    pub fn has_flag(&self, flag: u64) -> bool {
+        self.property_flags & flag != 0
+    }
+
+}
+
+/// Reads the `UObject` part of a field. Classes have no tagged properties, only their net index.
+fn read_field_properties<F: GameFile>(package: &UnPackage<F>, archive: &mut FByteArchive, is_class: bool) -> Result<Vec<FPropertyTag>> {
+    if !is_class {
+        return read_object_properties(archive, &package.names);
+    }
+
+    if archive.profile().has_net_object_counts {
+        archive.read_i32()?;
+    }
+
+    Ok(vec![])
+}
+
+fn read_name<F: GameFile>(package: &UnPackage<F>, archive: &mut FByteArchive) -> Result<String> {
+    let name: FName = read_serializable(archive)?;
+    let name = package.get_name(&name).ok_or_else(|| ParserError::new(&format!("Invalid name index {}", name.index)))?;
+
+    Ok(name)
+}
+
+/// The lowercase `package.path` of an object reference, the same whichever package refers to it.
+pub fn object_key<F: GameFile>(package: &UnPackage<F>, package_name: &str, index: i32) -> Option<String> {
+    let path = match index {
+        0 => return None,
+        index if index < 0 => package.get_object_path(index)?,
+        index => format!("{}.{}", package_name, package.get_object_path(index)?)
+    };
+
+    Some(path.to_lowercase())
+}
+
+/// The fields of a struct in declaration order, following `next` from `first`. Fields are
+/// always exported by the package of their struct.
+pub fn read_children<F: GameFile>(package: &UnPackage<F>, reader: &mut FPackageReader, first: i32) -> Result<Vec<i32>> {
+    let mut children = vec![];
+    let mut visited = HashSet::new();
+    let mut current = first;
+    while current > 0 && visited.insert(current) && children.len() < MAX_CHILDREN {
+        let export = package.exports.get(usize::try_from(current - 1)?).ok_or_else(|| ParserError::new(&format!("Invalid field index {}", current)))?;
+        let mut archive = package.object_archive(reader.read_export(export)?);
+        read_field_properties(package, &mut archive, export.class_index == 0)?;
+
+        children.push(current);
+        current = archive.read_i32()?;
+    }
+
+    Ok(children)
+}
+
+/// The UnrealScript type of a property export, like `array<Vector>`.
+pub fn property_type<F: GameFile>(package: &UnPackage<F>, reader: &mut FPackageReader, index: i32) -> Result<String> {
+    let export = package.exports.get(usize::try_from(index - 1)?).ok_or_else(|| ParserError::new(&format!("Invalid property index {}", index)))?;
+    let class = package.get_class_name(export).unwrap_or_default();
+    let property = UProperty::read(package, &class, reader.read_export(export)?)?;
+    let reference = |position: usize| property.references.get(position).and_then(|reference| package.get_object_name(*reference));
+
+    let type_name = match class.as_str() {
+        "IntProperty" => "int".to_string(),
+        "FloatProperty" => "float".to_string(),
+        "BoolProperty" => "bool".to_string(),
+        "NameProperty" => "name".to_string(),
+        "StrProperty" => "string".to_string(),
+        "ByteProperty" => reference(0).unwrap_or_else(|| "byte".to_string()),
+        "ObjectProperty" | "ComponentProperty" | "InterfaceProperty" | "StructProperty" => reference(0).unwrap_or_else(|| "Object".to_string()),
+        "ClassProperty" => format!("class<{}>", reference(1).unwrap_or_else(|| "Object".to_string())),
+        "DelegateProperty" => format!("delegate<{}>", reference(0).unwrap_or_default()),
+        "MapProperty" => format!("map<{}, {}>", reference(0).unwrap_or_default(), reference(1).unwrap_or_default()),
+        "ArrayProperty" => match property.references.first() {
+            Some(inner) if *inner > 0 && *inner != index => format!("array<{}>", property_type(package, reader, *inner)?),
+            _ => "array".to_string()
+        },
+        _ => class.trim_end_matches("Property").to_string()
+    };
+
+    Ok(type_name)
+}
@@ -0,0 +1,1027 @@
+//! The memory layout of the script types: the classes, script structs and enums of the packages,
+//! with the offset of every property, written as Rust or C++ definitions.
+//!
+//! Offsets aren't serialized, the game computes them when linking the classes. They are
+//! computed the same way for the 64-bit build: each property aligned on its type after the
+//! properties of the super struct, consecutive bools sharing a 32-bit bitfield and script structs
+//! padded to their alignment.
+//!
+//! Native classes may have members the script doesn't declare, so their layouts and those of the
+//! classes deriving from them are unverified unless their sizes are given, like from a dump of
+//! the running game. Only the given sizes are asserted in the output.
+
+#![allow(non_upper_case_globals)]
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::archive::FArchive;
+use crate::file::GameFile;
+use crate::package::UnPackage;
+use crate::reader::FPackageReader;
+use crate::script::{UEnum, UProperty, UState, UStruct, object_key, read_children, CLASS_Native, CPF_Config, CPF_Const, CPF_Edit, CPF_Native, CPF_Net, CPF_Transient};
+use crate::{Result, ParserError};
+
+const POINTER_SIZE: u32 = 8;
+/// `TArray` and `FString`: the data pointer, then the count and the capacity.
+const ARRAY_SIZE: u32 = POINTER_SIZE + 8;
+/// `FScriptDelegate`: the object, then the function name.
+const DELEGATE_SIZE: u32 = POINTER_SIZE + NAME_SIZE;
+/// `FScriptInterface`: the object, then its interface pointer.
+const INTERFACE_SIZE: u32 = 2 * POINTER_SIZE;
+/// `TMap`, which script only declares in native classes.
+const MAP_SIZE: u32 = 0x48;
+const NAME_SIZE: u32 = 8;
+const BITFIELD_SIZE: u32 = 4;
+const LAST_BITFIELD: u32 = 0x8000_0000;
+const MAX_TYPE_DEPTH: usize = 8;
+const MAX_SUPER_DEPTH: usize = 256;
+
+/// The classes deriving from this get the `A` prefix instead of `U`, as in the engine's headers.
+const ACTOR_CLASS: &str = "engine.actor";
+
+/// The types the generated files declare themselves.
+const PRELUDE_TYPES: [&str; 7] = ["Ptr", "TArray", "FName", "FString", "FScriptDelegate", "FScriptInterface", "FScriptMap"];
+
+const PROPERTY_FLAGS: [(u64, &str); 6] = [
+    (CPF_Edit, "edit"), (CPF_Const, "const"), (CPF_Net, "net"), (CPF_Native, "native"), (CPF_Transient, "transient"), (CPF_Config, "config")
+];
+
+const RUST_KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false", "fn", "for", "if", "impl", "in",
+    "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "type", "unsafe",
+    "use", "where", "while", "abstract", "box", "final", "macro"
+];
+const RUST_RESERVED: [&str; 5] = ["self", "Self", "super", "crate", "_"];
+
+const CPP_KEYWORDS: [&str; 40] = [
+    "alignas", "alignof", "auto", "bool", "break", "case", "catch", "char", "class", "const", "continue", "default", "delete", "do",
+    "double", "else", "enum", "explicit", "export", "extern", "false", "float", "for", "friend", "goto", "if", "inline", "int",
+    "long", "new", "operator", "private", "protected", "public", "return", "static", "struct", "template", "this", "union"
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FSdkType {
+    /// A byte, with the key of its enum if it has one.
+    Byte(Option<String>),
+    Int,
+    Float,
+    Bool,
+    Name,
+    Str,
+    /// An object, component or class reference, with the key of the class.
+    Object(Option<String>),
+    Interface,
+    Delegate,
+    Map,
+    Array(Box<FSdkType>),
+    /// A struct stored inline, by key.
+    Struct(String),
+    /// A property class without a known layout.
+    Unknown(String)
+}
+
+#[derive(Debug, Clone)]
+pub struct FSdkField {
+    pub name: String,
+    pub field_type: FSdkType,
+    /// The UnrealScript type, like `array<Vector>`.
+    pub script_type: String,
+    pub array_dim: u32,
+    pub property_flags: u64,
+    /// The offset in the object, once laid out.
+    pub offset: u32,
+    /// The bytes the field takes, static arrays included, once laid out.
+    pub size: u32,
+    /// The bit of a bool in its bitfield, 0 for the other types.
+    pub bit_mask: u32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ESdkKind {
+    Class,
+    Struct
+}
+
+#[derive(Debug, Clone)]
+pub struct FSdkStruct {
+    pub kind: ESdkKind,
+    /// The lowercase `package.path` the struct is referred to by, from any package.
+    pub key: String,
+    pub package: String,
+    pub name: String,
+    /// The class declaring a script struct.
+    pub outer: Option<String>,
+    pub super_key: Option<String>,
+    /// The properties declared by the struct itself, in declaration order.
+    pub fields: Vec<FSdkField>,
+    /// The size including the super struct, once laid out.
+    pub size: u32,
+    pub alignment: u32,
+    /// Whether the layout is known, which takes every struct it derives from or embeds.
+    pub is_laid_out: bool,
+    /// Whether the struct is a native class, whose C++ declaration may have members the script
+    /// doesn't declare. Script structs are declared whole.
+    pub is_native: bool,
+    /// Whether the size is given or the layout doesn't rest on a native class of unknown size.
+    pub is_verified: bool
+}
+
+#[derive(Debug, Clone)]
+pub struct FSdkEnum {
+    pub key: String,
+    pub package: String,
+    pub name: String,
+    /// The class declaring the enum.
+    pub outer: Option<String>,
+    pub values: Vec<String>
+}
+
+/// The script types exported by one package.
+#[derive(Debug, Clone, Default)]
+pub struct FSdkPackage {
+    pub name: String,
+    pub structs: Vec<FSdkStruct>,
+    pub enums: Vec<FSdkEnum>
+}
+
+impl FSdkPackage {
+
+    /// Reads the classes, script structs and enums exported by `package`, whose file name
+    /// without extension is `name`.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, reader: &mut FPackageReader, name: &str) -> Result<Self> {
+        let mut result = Self { name: name.to_string(), ..Self::default() };
+        for (index, export) in package.exports.iter().enumerate() {
+            let index = i32::try_from(index)? + 1;
+            let class = package.get_class_name(export).unwrap_or_default();
+            let read = match class.as_str() {
+                "Class" | "ScriptStruct" => read_struct(package, reader, name, index, class == "Class").map(|item| result.structs.push(item)),
+                "Enum" => read_enum(package, reader, name, index).map(|item| result.enums.push(item)),
+                _ => continue
+            };
+
+            if let Err(err) = read {
+                log::warn!("skipped {} {}.{}: {}", class, name, package.get_object_path(index).unwrap_or_default(), err);
+            }
+        }
+
+        Ok(result)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.structs.is_empty() && self.enums.is_empty()
+    }
+
+}
+
+/// The name of the outer of an export, if it isn't the package itself.
+fn outer_name<F: GameFile>(package: &UnPackage<F>, index: i32) -> Option<String> {
+    let export = package.exports.get(usize::try_from(index - 1).ok()?)?;
+    package.get_object_name(export.outer_index).filter(|_| export.outer_index != 0)
+}
+
+fn read_struct<F: GameFile>(package: &UnPackage<F>, reader: &mut FPackageReader, package_name: &str, index: i32, is_class: bool) -> Result<FSdkStruct> {
+    let export = &package.exports[usize::try_from(index - 1)?];
+    let mut archive = package.object_archive(reader.read_export(export)?);
+    let (base, is_native) = if is_class {
+        let state = UState::read_from(package, &mut archive, true)?;
+        (state.base, archive.read_u32()? & CLASS_Native != 0)
+    } else {
+        (UStruct::read_from(package, &mut archive, false)?, false)
+    };
+
+    let mut fields = vec![];
+    for child in read_children(package, reader, base.children)? {
+        let child_export = &package.exports[usize::try_from(child - 1)?];
+        let class = package.get_class_name(child_export).unwrap_or_default();
+        if !class.ends_with("Property") {
+            continue;
+        }
+
+        let property = UProperty::read(package, &class, reader.read_export(child_export)?)?;
+        fields.push(FSdkField {
+            name: package.get_object_name(child).unwrap_or_default(),
+            field_type: field_type(package, reader, package_name, &property, child, 0)?,
+            script_type: crate::script::property_type(package, reader, child)?,
+            array_dim: u32::try_from(property.array_dim.max(1))?,
+            property_flags: property.property_flags,
+            offset: 0,
+            size: 0,
+            bit_mask: 0
+        });
+    }
+
+    Ok(FSdkStruct {
+        kind: if is_class { ESdkKind::Class } else { ESdkKind::Struct },
+        key: object_key(package, package_name, index).unwrap_or_default(),
+        package: package_name.to_string(),
+        name: package.get_object_name(index).unwrap_or_default(),
+        outer: outer_name(package, index),
+        super_key: object_key(package, package_name, base.super_struct),
+        fields,
+        size: 0,
+        alignment: 1,
+        is_laid_out: false,
+        is_native,
+        is_verified: false
+    })
+}
+
+fn field_type<F: GameFile>(package: &UnPackage<F>, reader: &mut FPackageReader, package_name: &str, property: &UProperty, index: i32, depth: usize) -> Result<FSdkType> {
+    let reference = |position: usize| property.references.get(position).and_then(|reference| object_key(package, package_name, *reference));
+
+    let field_type = match property.class.as_str() {
+        "ByteProperty" => FSdkType::Byte(reference(0)),
+        "IntProperty" => FSdkType::Int,
+        "FloatProperty" => FSdkType::Float,
+        "BoolProperty" => FSdkType::Bool,
+        "NameProperty" => FSdkType::Name,
+        "StrProperty" => FSdkType::Str,
+        "ObjectProperty" | "ComponentProperty" | "ClassProperty" => FSdkType::Object(reference(0)),
+        "InterfaceProperty" => FSdkType::Interface,
+        "DelegateProperty" => FSdkType::Delegate,
+        "MapProperty" => FSdkType::Map,
+        "StructProperty" => reference(0).map_or_else(|| FSdkType::Unknown(property.class.clone()), FSdkType::Struct),
+        "ArrayProperty" => match property.references.first() {
+            Some(inner) if *inner > 0 && *inner != index && depth < MAX_TYPE_DEPTH => {
+                let export = package.exports.get(usize::try_from(inner - 1)?).ok_or_else(|| ParserError::new(&format!("Invalid property index {}", inner)))?;
+                let class = package.get_class_name(export).unwrap_or_default();
+                let inner_property = UProperty::read(package, &class, reader.read_export(export)?)?;
+                FSdkType::Array(Box::new(field_type(package, reader, package_name, &inner_property, *inner, depth + 1)?))
+            },
+            _ => FSdkType::Unknown(property.class.clone())
+        },
+        _ => FSdkType::Unknown(property.class.clone())
+    };
+
+    Ok(field_type)
+}
+
+fn read_enum<F: GameFile>(package: &UnPackage<F>, reader: &mut FPackageReader, package_name: &str, index: i32) -> Result<FSdkEnum> {
+    let export = &package.exports[usize::try_from(index - 1)?];
+    let item = UEnum::read(package, reader.read_export(export)?)?;
+
+    Ok(FSdkEnum {
+        key: object_key(package, package_name, index).unwrap_or_default(),
+        package: package_name.to_string(),
+        name: package.get_object_name(index).unwrap_or_default(),
+        outer: outer_name(package, index),
+        values: item.names
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ELayoutState {
+    Pending,
+    InProgress,
+    Done
+}
+
+/// A run of the fields of a struct taking its own bytes: a field, or the bools of a bitfield.
+struct FSdkSlot<'a> {
+    offset: u32,
+    size: u32,
+    fields: Vec<&'a FSdkField>
+}
+
+/// The script types of all the packages, laid out across them since classes derive from and
+/// embed the types of other packages.
+#[derive(Debug, Clone, Default)]
+pub struct FSdk {
+    structs: Vec<FSdkStruct>,
+    enums: Vec<FSdkEnum>,
+    struct_index: HashMap<String, usize>,
+    enum_index: HashMap<String, usize>,
+    /// The sizes given from outside the script, by key.
+    known_sizes: HashMap<String, u32>,
+    /// The laid out structs, each after the ones it derives from or embeds.
+    order: Vec<usize>
+}
+
+impl FSdk {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the types of a package, skipping those another package already added.
+    pub fn add_package(&mut self, package: FSdkPackage) {
+        for item in package.structs {
+            if !self.struct_index.contains_key(&item.key) {
+                self.struct_index.insert(item.key.clone(), self.structs.len());
+                self.structs.push(item);
+            }
+        }
+
+        for item in package.enums {
+            if !self.enum_index.contains_key(&item.key) {
+                self.enum_index.insert(item.key.clone(), self.enums.len());
+                self.enums.push(item);
+            }
+        }
+    }
+
+    /// Sets the sizes of structs by key, which the layout takes over the script's when they are
+    /// larger and the output asserts.
+    pub fn set_known_sizes(&mut self, sizes: HashMap<String, u32>) {
+        self.known_sizes = sizes;
+    }
+
+    /// Computes the offsets of the fields and the size of every struct. Returns the keys of the
+    /// structs that couldn't be laid out because a struct they derive from or embed is missing.
+    pub fn layout(&mut self) -> Vec<String> {
+        let mut states = vec![ELayoutState::Pending; self.structs.len()];
+        self.order.clear();
+        for index in 0..self.structs.len() {
+            self.layout_struct(index, &mut states);
+        }
+
+        self.structs.iter().filter(|item| !item.is_laid_out).map(|item| item.key.clone()).collect()
+    }
+
+    /// The laid out structs, each after the ones it derives from or embeds.
+    pub fn structs(&self) -> impl Iterator<Item = &FSdkStruct> {
+        self.order.iter().map(|index| &self.structs[*index])
+    }
+
+    pub fn enums(&self) -> &[FSdkEnum] {
+        &self.enums
+    }
+
+    fn layout_struct(&mut self, index: usize, states: &mut [ELayoutState]) -> Option<(u32, u32)> {
+        match states[index] {
+            ELayoutState::Done => return Some((self.structs[index].size, self.structs[index].alignment)).filter(|_| self.structs[index].is_laid_out),
+            ELayoutState::InProgress => return None,
+            ELayoutState::Pending => states[index] = ELayoutState::InProgress
+        }
+
+        let layout = self.layout_fields(index, states);
+        states[index] = ELayoutState::Done;
+
+        let (size, alignment) = layout?;
+        let is_super_verified = self.structs[index].super_key.as_ref()
+            .and_then(|key| self.struct_index.get(key))
+            .is_none_or(|super_index| self.structs[*super_index].is_verified);
+        let known_size = self.known_sizes.get(&self.structs[index].key).copied();
+
+        let item = &mut self.structs[index];
+        let size = if item.kind == ESdkKind::Struct { size.next_multiple_of(alignment) } else { size };
+        item.size = match known_size {
+            Some(known_size) if known_size < size => {
+                log::warn!("{} takes 0x{:X} bytes by its script but its given size is 0x{:X}", item.key, size, known_size);
+                size
+            },
+            Some(known_size) => known_size,
+            None => size
+        };
+        item.alignment = alignment;
+        item.is_laid_out = true;
+        item.is_verified = is_super_verified && known_size.map_or(!item.is_native, |known_size| known_size == item.size);
+        self.order.push(index);
+
+        Some((item.size, alignment))
+    }
+
+    /// Sets the offsets of the fields of a struct, returning the end of its last field and its alignment.
+    fn layout_fields(&mut self, index: usize, states: &mut [ELayoutState]) -> Option<(u32, u32)> {
+        let (mut offset, mut alignment) = match self.structs[index].super_key.clone() {
+            Some(key) => {
+                let super_index = *self.struct_index.get(&key)?;
+                self.layout_struct(super_index, states)?
+            },
+            None => (0, 1)
+        };
+
+        let mut fields = std::mem::take(&mut self.structs[index].fields);
+        let mut previous_bit: Option<(u32, u32)> = None;
+        let mut result = Some(());
+        for field in &mut fields {
+            if field.field_type == FSdkType::Bool {
+                if let Some((bit_offset, bit_mask)) = previous_bit.filter(|(_, bit_mask)| *bit_mask != LAST_BITFIELD) {
+                    field.offset = bit_offset;
+                    field.size = BITFIELD_SIZE;
+                    field.bit_mask = bit_mask << 1;
+                    previous_bit = Some((bit_offset, field.bit_mask));
+                    continue;
+                }
+            }
+
+            let Some((size, field_alignment)) = self.type_layout(&field.field_type, states) else {
+                result = None;
+                break;
+            };
+
+            offset = offset.next_multiple_of(field_alignment);
+            field.offset = offset;
+            field.size = size * field.array_dim;
+            offset += field.size;
+            alignment = alignment.max(field_alignment);
+
+            previous_bit = if field.field_type == FSdkType::Bool {
+                field.bit_mask = 1;
+                Some((field.offset, 1))
+            } else {
+                None
+            };
+        }
+
+        self.structs[index].fields = fields;
+        result.map(|()| (offset, alignment))
+    }
+
+    /// The size and alignment of a type.
+    fn type_layout(&mut self, field_type: &FSdkType, states: &mut [ELayoutState]) -> Option<(u32, u32)> {
+        match field_type {
+            FSdkType::Byte(_) => Some((1, 1)),
+            FSdkType::Int | FSdkType::Float => Some((4, 4)),
+            FSdkType::Bool => Some((BITFIELD_SIZE, BITFIELD_SIZE)),
+            FSdkType::Name => Some((NAME_SIZE, 4)),
+            FSdkType::Str | FSdkType::Array(_) => Some((ARRAY_SIZE, POINTER_SIZE)),
+            FSdkType::Object(_) => Some((POINTER_SIZE, POINTER_SIZE)),
+            FSdkType::Interface => Some((INTERFACE_SIZE, POINTER_SIZE)),
+            FSdkType::Delegate => Some((DELEGATE_SIZE, POINTER_SIZE)),
+            FSdkType::Map => Some((MAP_SIZE, POINTER_SIZE)),
+            FSdkType::Struct(key) => {
+                let index = *self.struct_index.get(key)?;
+                self.layout_struct(index, states)
+            },
+            FSdkType::Unknown(_) => None
+        }
+    }
+
+    /// The names of the generated types, prefixed like the engine's headers. Names used by more
+    /// than one type are qualified with their outer class, then their package.
+    fn type_names(&self) -> (Vec<String>, Vec<String>) {
+        let mut candidates = vec![];
+        for (index, item) in self.structs.iter().enumerate() {
+            let prefix = match item.kind {
+                ESdkKind::Struct => "F",
+                ESdkKind::Class if self.is_actor(index) => "A",
+                ESdkKind::Class => "U"
+            };
+            candidates.push(qualified_names(prefix, &item.name, item.outer.as_deref(), &item.package));
+        }
+
+        for item in &self.enums {
+            candidates.push(qualified_names("", &item.name, item.outer.as_deref(), &item.package));
+        }
+
+        let mut names = unique_names(&candidates);
+        let enum_names = names.split_off(self.structs.len());
+
+        (names, enum_names)
+    }
+
+    fn is_actor(&self, index: usize) -> bool {
+        let mut current = Some(index);
+        for _ in 0..MAX_SUPER_DEPTH {
+            let Some(item) = current.map(|index| &self.structs[index]) else {
+                return false;
+            };
+
+            if item.key == ACTOR_CLASS {
+                return true;
+            }
+
+            current = item.super_key.as_ref().and_then(|key| self.struct_index.get(key)).copied();
+        }
+
+        false
+    }
+
+    /// The type name of a laid out struct by key.
+    fn struct_name<'a>(&self, names: &'a [String], key: &str) -> Option<&'a str> {
+        self.struct_index.get(key).filter(|index| self.structs[**index].is_laid_out).map(|index| names[*index].as_str())
+    }
+
+    /// Writes the types as a Rust module of packed `repr(C)` structs, asserting the given sizes.
+    pub fn write_rust<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let (names, enum_names) = self.type_names();
+
+        writeln!(writer, "//! The script types of the game, generated from its packages: {} classes and structs, {} enums.", self.order.len(), self.enums.len())?;
+        writeln!(writer, "#![allow(non_camel_case_types, non_snake_case, dead_code)]")?;
+        writeln!(writer)?;
+        writeln!(writer, "use std::marker::PhantomData;")?;
+        writeln!(writer)?;
+        writeln!(writer, "/// An address in the game's memory.")?;
+        writeln!(writer, "#[repr(C, packed)]")?;
+        writeln!(writer, "pub struct Ptr<T> {{ pub address: u64, pub target: PhantomData<T> }}")?;
+        writeln!(writer)?;
+        writeln!(writer, "#[repr(C, packed)]")?;
+        writeln!(writer, "pub struct TArray<T> {{ pub data: Ptr<T>, pub count: i32, pub max: i32 }}")?;
+        writeln!(writer)?;
+        writeln!(writer, "#[repr(C, packed)]")?;
+        writeln!(writer, "pub struct FName {{ pub index: i32, pub number: i32 }}")?;
+        writeln!(writer)?;
+        writeln!(writer, "pub type FString = TArray<u16>;")?;
+        writeln!(writer)?;
+        writeln!(writer, "#[repr(C, packed)]")?;
+        writeln!(writer, "pub struct FScriptDelegate {{ pub object: Ptr<()>, pub function_name: FName }}")?;
+        writeln!(writer)?;
+        writeln!(writer, "#[repr(C, packed)]")?;
+        writeln!(writer, "pub struct FScriptInterface {{ pub object: Ptr<()>, pub interface: Ptr<()> }}")?;
+        writeln!(writer)?;
+        writeln!(writer, "#[repr(C, packed)]")?;
+        writeln!(writer, "pub struct FScriptMap {{ pub data: [u8; 0x{:X}] }}", MAP_SIZE)?;
+
+        for (item, name) in self.enums.iter().zip(&enum_names).filter(|(item, _)| !item.values.is_empty()) {
+            writeln!(writer)?;
+            writeln!(writer, "/// Enum {}", item_path(&item.package, item.outer.as_deref(), &item.name))?;
+            writeln!(writer, "#[repr(u8)]")?;
+            writeln!(writer, "#[derive(Debug, Clone, Copy, PartialEq, Eq)]")?;
+            writeln!(writer, "pub enum {} {{", name)?;
+            for (value, value_name) in item.values.iter().take(256).enumerate() {
+                writeln!(writer, "    {} = {},", rust_identifier(value_name), value)?;
+            }
+            writeln!(writer, "}}")?;
+        }
+
+        for item in self.structs() {
+            let index = self.struct_index[&item.key];
+            let name = &names[index];
+            writeln!(writer)?;
+            writeln!(writer, "/// {} {}, 0x{:04X} bytes{}.", kind_name(item.kind), item_path(&item.package, item.outer.as_deref(), &item.name), item.size, verified_comment(item))?;
+            writeln!(writer, "#[repr(C, packed)]")?;
+            writeln!(writer, "pub struct {} {{", name)?;
+
+            let mut cursor = 0;
+            if let Some(super_name) = item.super_key.as_deref().and_then(|key| self.struct_name(&names, key)) {
+                writeln!(writer, "    pub base: {},", super_name)?;
+                cursor = self.structs[self.struct_index[item.super_key.as_deref().unwrap_or_default()]].size;
+            }
+
+            for slot in slots(item) {
+                if slot.offset < cursor {
+                    writeln!(writer, "    // 0x{:04X} {} overlaps the previous field", slot.offset, slot.fields[0].name)?;
+                    continue;
+                }
+
+                if slot.offset > cursor {
+                    writeln!(writer, "    _pad_0x{:04X}: [u8; 0x{:X}],", cursor, slot.offset - cursor)?;
+                }
+
+                let field = slot.fields[0];
+                if field.field_type == FSdkType::Bool {
+                    for bit in &slot.fields {
+                        writeln!(writer, "    /// 0x{:04X} bool {} (0x{:08X}){}", bit.offset, bit.name, bit.bit_mask, flags_comment(bit.property_flags))?;
+                    }
+                    writeln!(writer, "    pub bitfield_0x{:04X}: u32,", slot.offset)?;
+                } else {
+                    let mut field_name = rust_identifier(&field.name);
+                    if field_name == "base" {
+                        field_name.push('_');
+                    }
+
+                    let mut type_name = self.rust_type(&names, &field.field_type);
+                    if field.array_dim > 1 {
+                        type_name = format!("[{}; {}]", type_name, field.array_dim);
+                    }
+
+                    writeln!(writer, "    /// 0x{:04X} (0x{:04X}) {}{}{}", field.offset, slot.size, field.script_type, array_suffix(field.array_dim), flags_comment(field.property_flags))?;
+                    writeln!(writer, "    pub {}: {},", field_name, type_name)?;
+                }
+                cursor = slot.offset + slot.size;
+            }
+
+            if item.size > cursor {
+                writeln!(writer, "    _pad_0x{:04X}: [u8; 0x{:X}],", cursor, item.size - cursor)?;
+            }
+
+            writeln!(writer, "}}")?;
+            if let Some(known_size) = self.known_sizes.get(&item.key) {
+                writeln!(writer, "const _: () = assert!(std::mem::size_of::<{}>() == 0x{:04X});", name, known_size)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn rust_type(&self, names: &[String], field_type: &FSdkType) -> String {
+        match field_type {
+            FSdkType::Byte(_) => "u8".to_string(),
+            FSdkType::Int => "i32".to_string(),
+            FSdkType::Float => "f32".to_string(),
+            FSdkType::Bool => "u32".to_string(),
+            FSdkType::Name => "FName".to_string(),
+            FSdkType::Str => "FString".to_string(),
+            FSdkType::Object(class) => format!("Ptr<{}>", class.as_deref().and_then(|key| self.struct_name(names, key)).unwrap_or("()")),
+            FSdkType::Interface => "FScriptInterface".to_string(),
+            FSdkType::Delegate => "FScriptDelegate".to_string(),
+            FSdkType::Map => "FScriptMap".to_string(),
+            FSdkType::Array(inner) => format!("TArray<{}>", self.rust_type(names, inner)),
+            FSdkType::Struct(key) => self.struct_name(names, key).unwrap_or("()").to_string(),
+            FSdkType::Unknown(_) => "()".to_string()
+        }
+    }
+
+    /// Writes the types as a C++ header of packed structs and classes, asserting the given sizes.
+    pub fn write_cpp<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let (names, enum_names) = self.type_names();
+
+        writeln!(writer, "// The script types of the game, generated from its packages: {} classes and structs, {} enums.", self.order.len(), self.enums.len())?;
+        writeln!(writer, "#pragma once")?;
+        writeln!(writer)?;
+        writeln!(writer, "#include <cstdint>")?;
+        writeln!(writer)?;
+        writeln!(writer, "#pragma pack(push, 1)")?;
+        writeln!(writer)?;
+        writeln!(writer, "template<typename T> struct TArray {{ T* Data; int32_t Count; int32_t Max; }};")?;
+        writeln!(writer, "struct FName {{ int32_t Index; int32_t Number; }};")?;
+        writeln!(writer, "using FString = TArray<char16_t>;")?;
+        writeln!(writer, "struct FScriptDelegate {{ void* Object; FName FunctionName; }};")?;
+        writeln!(writer, "struct FScriptInterface {{ void* Object; void* Interface; }};")?;
+        writeln!(writer, "struct FScriptMap {{ uint8_t Data[0x{:X}]; }};", MAP_SIZE)?;
+
+        for (item, name) in self.enums.iter().zip(&enum_names).filter(|(item, _)| !item.values.is_empty()) {
+            writeln!(writer)?;
+            writeln!(writer, "// Enum {}", item_path(&item.package, item.outer.as_deref(), &item.name))?;
+            writeln!(writer, "enum class {} : uint8_t {{", name)?;
+            for (value, value_name) in item.values.iter().take(256).enumerate() {
+                writeln!(writer, "    {} = {},", cpp_identifier(value_name), value)?;
+            }
+            writeln!(writer, "}};")?;
+        }
+
+        writeln!(writer)?;
+        for item in self.structs().filter(|item| item.kind == ESdkKind::Class) {
+            writeln!(writer, "class {};", names[self.struct_index[&item.key]])?;
+        }
+
+        for item in self.structs() {
+            let index = self.struct_index[&item.key];
+            let name = &names[index];
+            let keyword = if item.kind == ESdkKind::Class { "class" } else { "struct" };
+            writeln!(writer)?;
+            writeln!(writer, "// {} {}, 0x{:04X} bytes{}.", kind_name(item.kind), item_path(&item.package, item.outer.as_deref(), &item.name), item.size, verified_comment(item))?;
+
+            let mut cursor = 0;
+            match item.super_key.as_deref().and_then(|key| self.struct_name(&names, key)) {
+                Some(super_name) => {
+                    writeln!(writer, "{} {} : public {} {{", keyword, name, super_name)?;
+                    cursor = self.structs[self.struct_index[item.super_key.as_deref().unwrap_or_default()]].size;
+                },
+                None => writeln!(writer, "{} {} {{", keyword, name)?
+            }
+            writeln!(writer, "public:")?;
+
+            for slot in slots(item) {
+                if slot.offset < cursor {
+                    writeln!(writer, "    // 0x{:04X} {} overlaps the previous field", slot.offset, slot.fields[0].name)?;
+                    continue;
+                }
+
+                if slot.offset > cursor {
+                    writeln!(writer, "    uint8_t UnknownData_0x{:04X}[0x{:X}];", cursor, slot.offset - cursor)?;
+                }
+
+                let field = slot.fields[0];
+                if field.field_type == FSdkType::Bool {
+                    for bit in &slot.fields {
+                        writeln!(writer, "    uint32_t {} : 1; // 0x{:04X} (0x{:08X}){}", cpp_identifier(&bit.name), bit.offset, bit.bit_mask, flags_comment(bit.property_flags))?;
+                    }
+                    // Fills the bitfield, so that the next field doesn't start in its bytes.
+                    if slot.fields.len() < 32 {
+                        writeln!(writer, "    uint32_t : {};", 32 - slot.fields.len())?;
+                    }
+                } else {
+                    let array = if field.array_dim > 1 { format!("[{}]", field.array_dim) } else { String::new() };
+                    writeln!(writer, "    {} {}{}; // 0x{:04X} (0x{:04X}) {}{}{}", self.cpp_type(&names, &field.field_type), cpp_identifier(&field.name), array,
+                        field.offset, slot.size, field.script_type, array_suffix(field.array_dim), flags_comment(field.property_flags))?;
+                }
+                cursor = slot.offset + slot.size;
+            }
+
+            if item.size > cursor {
+                writeln!(writer, "    uint8_t UnknownData_0x{:04X}[0x{:X}];", cursor, item.size - cursor)?;
+            }
+
+            writeln!(writer, "}};")?;
+            if let Some(known_size) = self.known_sizes.get(&item.key) {
+                writeln!(writer, "static_assert(sizeof({}) == 0x{:04X}, \"Wrong size of {}\");", name, known_size, name)?;
+            }
+        }
+
+        writeln!(writer)?;
+        writeln!(writer, "#pragma pack(pop)")?;
+
+        Ok(())
+    }
+
+    fn cpp_type(&self, names: &[String], field_type: &FSdkType) -> String {
+        match field_type {
+            FSdkType::Byte(_) => "uint8_t".to_string(),
+            FSdkType::Int => "int32_t".to_string(),
+            FSdkType::Float => "float".to_string(),
+            FSdkType::Bool => "uint32_t".to_string(),
+            FSdkType::Name => "FName".to_string(),
+            FSdkType::Str => "FString".to_string(),
+            FSdkType::Object(class) => match class.as_deref().and_then(|key| self.struct_name(names, key)) {
+                Some(class) => format!("class {}*", class),
+                None => "void*".to_string()
+            },
+            FSdkType::Interface => "FScriptInterface".to_string(),
+            FSdkType::Delegate => "FScriptDelegate".to_string(),
+            FSdkType::Map => "FScriptMap".to_string(),
+            FSdkType::Array(inner) => format!("TArray<{}>", self.cpp_type(names, inner)),
+            FSdkType::Struct(key) => self.struct_name(names, key).unwrap_or("void").to_string(),
+            FSdkType::Unknown(_) => "void".to_string()
+        }
+    }
+
+}
+
+/// Groups the fields of a laid out struct by the bytes they take, bools sharing their bitfield.
+fn slots(item: &FSdkStruct) -> Vec<FSdkSlot<'_>> {
+    let mut slots: Vec<FSdkSlot> = vec![];
+    for field in &item.fields {
+        if field.field_type == FSdkType::Bool && field.bit_mask != 1 {
+            if let Some(slot) = slots.last_mut().filter(|slot| slot.offset == field.offset) {
+                slot.fields.push(field);
+                continue;
+            }
+        }
+
+        slots.push(FSdkSlot { offset: field.offset, size: field.size, fields: vec![field] });
+    }
+
+    slots
+}
+
+fn qualified_names(prefix: &str, name: &str, outer: Option<&str>, package: &str) -> [String; 3] {
+    let outer = outer.unwrap_or(package);
+    [
+        format!("{}{}", prefix, name),
+        format!("{}{}_{}", prefix, outer, name),
+        format!("{}{}_{}_{}", prefix, package, outer, name)
+    ]
+}
+
+/// Picks the first candidate of each type that no other type uses, the prelude types included.
+fn unique_names(candidates: &[[String; 3]]) -> Vec<String> {
+    let mut names: Vec<String> = candidates.iter().map(|candidate| candidate[0].clone()).collect();
+    for level in [1, 2] {
+        let mut counts: HashMap<&str, usize> = PRELUDE_TYPES.iter().map(|name| (*name, 1)).collect();
+        for name in &names {
+            *counts.entry(name.as_str()).or_default() += 1;
+        }
+
+        let duplicates: Vec<bool> = names.iter().map(|name| counts[name.as_str()] > 1).collect();
+        for (index, duplicate) in duplicates.into_iter().enumerate() {
+            if duplicate {
+                names[index] = candidates[index][level].clone();
+            }
+        }
+    }
+
+    names
+}
+
+/// Reads the sizes of structs from lines of their path and size, like `Core.Object 0x60`, the
+/// size being hexadecimal with the `0x` prefix and decimal without. `#` starts a comment.
+pub fn read_known_sizes(text: &str) -> Result<HashMap<String, u32>> {
+    let mut sizes = HashMap::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let invalid = || ParserError::new(&format!("Invalid size on line {}: {}", number + 1, line));
+        let (path, size) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+        let size = size.trim();
+        let size = match size.strip_prefix("0x").or_else(|| size.strip_prefix("0X")) {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => size.parse()
+        }.map_err(|_| invalid())?;
+
+        sizes.insert(path.to_lowercase(), size);
+    }
+
+    Ok(sizes)
+}
+
+fn verified_comment(item: &FSdkStruct) -> &'static str {
+    if item.is_verified { "" } else { ", unverified as native classes may have members the script doesn't declare" }
+}
+
+fn kind_name(kind: ESdkKind) -> &'static str {
+    match kind {
+        ESdkKind::Class => "Class",
+        ESdkKind::Struct => "Struct"
+    }
+}
+
+fn item_path(package: &str, outer: Option<&str>, name: &str) -> String {
+    match outer {
+        Some(outer) => format!("{}.{}.{}", package, outer, name),
+        None => format!("{}.{}", package, name)
+    }
+}
+
+fn array_suffix(array_dim: u32) -> String {
+    if array_dim > 1 { format!("[{}]", array_dim) } else { String::new() }
+}
+
+fn flags_comment(property_flags: u64) -> String {
+    let flags: Vec<&str> = PROPERTY_FLAGS.iter().filter(|(flag, _)| property_flags & flag != 0).map(|(_, name)| *name).collect();
+    if flags.is_empty() { String::new() } else { format!(" [{}]", flags.join(", ")) }
+}
+
+fn sanitize_identifier(name: &str) -> String {
+    let mut identifier: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+    if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+
+    identifier
+}
+
+fn rust_identifier(name: &str) -> String {
+    let identifier = sanitize_identifier(name);
+    if RUST_RESERVED.contains(&identifier.as_str()) {
+        format!("{}_", identifier)
+    } else if RUST_KEYWORDS.contains(&identifier.as_str()) {
+        format!("r#{}", identifier)
+    } else {
+        identifier
+    }
+}
+
+fn cpp_identifier(name: &str) -> String {
+    let identifier = sanitize_identifier(name);
+    if CPP_KEYWORDS.contains(&identifier.as_str()) { format!("{}_", identifier) } else { identifier }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str, field_type: FSdkType) -> FSdkField {
+        FSdkField { name: name.to_string(), field_type, script_type: String::new(), array_dim: 1, property_flags: 0, offset: 0, size: 0, bit_mask: 0 }
+    }
+
+    fn item(kind: ESdkKind, name: &str, super_name: Option<&str>, fields: Vec<FSdkField>) -> FSdkStruct {
+        FSdkStruct {
+            kind,
+            key: format!("test.{}", name.to_lowercase()),
+            package: "Test".to_string(),
+            name: name.to_string(),
+            outer: None,
+            super_key: super_name.map(|name| format!("test.{}", name.to_lowercase())),
+            fields,
+            size: 0,
+            alignment: 1,
+            is_laid_out: false,
+            is_native: false,
+            is_verified: false
+        }
+    }
+
+    fn sdk(structs: Vec<FSdkStruct>) -> FSdk {
+        let mut sdk = FSdk::new();
+        sdk.add_package(FSdkPackage { name: "Test".to_string(), structs, enums: vec![] });
+        sdk
+    }
+
+    fn get<'a>(sdk: &'a FSdk, name: &str) -> &'a FSdkStruct {
+        &sdk.structs[sdk.struct_index[&format!("test.{}", name.to_lowercase())]]
+    }
+
+    /// The offset, size and bit mask of every field.
+    fn offsets(sdk: &FSdk, name: &str) -> Vec<(u32, u32, u32)> {
+        get(sdk, name).fields.iter().map(|field| (field.offset, field.size, field.bit_mask)).collect()
+    }
+
+    #[test]
+    fn fields_are_aligned() {
+        let fields = || vec![
+            field("A", FSdkType::Byte(None)), field("B", FSdkType::Int), field("C", FSdkType::Byte(None)),
+            field("D", FSdkType::Name), field("E", FSdkType::Object(None)), field("F", FSdkType::Byte(None))
+        ];
+        let mut sdk = sdk(vec![item(ESdkKind::Struct, "Padded", None, fields()), item(ESdkKind::Class, "Unpadded", None, fields())]);
+
+        assert!(sdk.layout().is_empty());
+        assert_eq!(offsets(&sdk, "Padded"), vec![(0, 1, 0), (4, 4, 0), (8, 1, 0), (12, 8, 0), (24, 8, 0), (32, 1, 0)]);
+        // Script structs are padded to their alignment, classes aren't.
+        assert_eq!((get(&sdk, "Padded").size, get(&sdk, "Padded").alignment), (40, 8));
+        assert_eq!(get(&sdk, "Unpadded").size, 33);
+    }
+
+    #[test]
+    fn embedded_structs_and_static_arrays() {
+        let vector = item(ESdkKind::Struct, "Vector", None, vec![field("X", FSdkType::Float), field("Y", FSdkType::Float), field("Z", FSdkType::Float)]);
+        let mut slots = field("Slots", FSdkType::Int);
+        slots.array_dim = 3;
+        let holder = item(ESdkKind::Struct, "Holder", None, vec![
+            field("A", FSdkType::Byte(None)), field("V", FSdkType::Struct("test.vector".to_string())), field("B", FSdkType::Byte(None)), slots,
+            field("Tags", FSdkType::Array(Box::new(FSdkType::Name)))
+        ]);
+        // Declared before what it embeds, which is laid out first.
+        let mut sdk = sdk(vec![holder, vector]);
+
+        assert!(sdk.layout().is_empty());
+        assert_eq!(offsets(&sdk, "Holder"), vec![(0, 1, 0), (4, 12, 0), (16, 1, 0), (20, 12, 0), (32, 16, 0)]);
+        assert_eq!(get(&sdk, "Holder").size, 48);
+        let order: Vec<&str> = sdk.structs().map(|item| item.name.as_str()).collect();
+        assert_eq!(order, vec!["Vector", "Holder"]);
+    }
+
+    #[test]
+    fn bools_share_bitfields() {
+        let mut fields: Vec<FSdkField> = (0..33).map(|bit| field(&format!("b{}", bit), FSdkType::Bool)).collect();
+        fields.extend([field("Count", FSdkType::Int), field("bAfter", FSdkType::Bool), field("Flag", FSdkType::Byte(None)), field("bLast", FSdkType::Bool)]);
+        let mut sdk = sdk(vec![item(ESdkKind::Class, "Bits", None, fields)]);
+
+        assert!(sdk.layout().is_empty());
+        let offsets = offsets(&sdk, "Bits");
+        for (bit, offset) in offsets[..32].iter().enumerate() {
+            assert_eq!(*offset, (0, BITFIELD_SIZE, 1 << bit));
+        }
+        // The 33rd bool starts a bitfield, as does a bool after any other field.
+        assert_eq!(offsets[32..], [(4, 4, 1), (8, 4, 0), (12, 4, 1), (16, 1, 0), (20, 4, 1)]);
+        assert_eq!(slots(get(&sdk, "Bits")).len(), 6);
+    }
+
+    #[test]
+    fn fields_follow_the_super_struct() {
+        let base = item(ESdkKind::Class, "Base", None, vec![field("A", FSdkType::Int), field("B", FSdkType::Byte(None))]);
+        let derived = item(ESdkKind::Class, "Derived", Some("Base"), vec![field("C", FSdkType::Byte(None)), field("D", FSdkType::Int)]);
+        let orphan = item(ESdkKind::Class, "Orphan", Some("Missing"), vec![field("E", FSdkType::Int)]);
+        let mut sdk = sdk(vec![derived, base, orphan]);
+
+        assert_eq!(sdk.layout(), vec!["test.orphan".to_string()]);
+        assert_eq!(offsets(&sdk, "Derived"), vec![(5, 1, 0), (8, 4, 0)]);
+        assert_eq!(get(&sdk, "Derived").size, 12);
+        assert!(get(&sdk, "Derived").is_verified);
+    }
+
+    #[test]
+    fn native_classes_are_unverified() {
+        let mut base = item(ESdkKind::Class, "Base", None, vec![field("A", FSdkType::Int), field("B", FSdkType::Byte(None))]);
+        base.is_native = true;
+        let derived = item(ESdkKind::Class, "Derived", Some("Base"), vec![field("C", FSdkType::Byte(None))]);
+        let other = item(ESdkKind::Class, "Other", None, vec![field("D", FSdkType::Int)]);
+        let mut sdk = sdk(vec![base, derived, other]);
+
+        assert!(sdk.layout().is_empty());
+        assert!(!get(&sdk, "Base").is_verified);
+        assert!(!get(&sdk, "Derived").is_verified);
+        assert!(get(&sdk, "Other").is_verified);
+    }
+
+    #[test]
+    fn known_sizes_move_the_fields() {
+        let mut base = item(ESdkKind::Class, "Base", None, vec![field("A", FSdkType::Int), field("B", FSdkType::Byte(None))]);
+        base.is_native = true;
+        let mut small = item(ESdkKind::Class, "Small", None, vec![field("E", FSdkType::Object(None))]);
+        small.is_native = true;
+        let derived = item(ESdkKind::Class, "Derived", Some("Base"), vec![field("C", FSdkType::Byte(None))]);
+        let mut sdk = sdk(vec![base, derived, small]);
+        sdk.set_known_sizes(HashMap::from([("test.base".to_string(), 0x10), ("test.small".to_string(), 4)]));
+
+        assert!(sdk.layout().is_empty());
+        assert_eq!((get(&sdk, "Base").size, get(&sdk, "Base").is_verified), (0x10, true));
+        assert_eq!(offsets(&sdk, "Derived"), vec![(0x10, 1, 0)]);
+        assert!(get(&sdk, "Derived").is_verified);
+        // A given size smaller than the script's fields is wrong, the script's is kept.
+        assert_eq!((get(&sdk, "Small").size, get(&sdk, "Small").is_verified), (8, false));
+    }
+
+    #[test]
+    fn only_given_sizes_are_asserted() {
+        let mut base = item(ESdkKind::Class, "Base", None, vec![field("A", FSdkType::Int), field("bB", FSdkType::Bool)]);
+        base.is_native = true;
+        let derived = item(ESdkKind::Class, "Derived", Some("Base"), vec![field("C", FSdkType::Int)]);
+        let mut native = item(ESdkKind::Class, "Native", None, vec![field("D", FSdkType::Int)]);
+        native.is_native = true;
+        let mut sdk = sdk(vec![base, derived, native]);
+        sdk.set_known_sizes(HashMap::from([("test.base".to_string(), 0x10)]));
+        sdk.layout();
+
+        let mut rust = vec![];
+        sdk.write_rust(&mut rust).unwrap();
+        let rust = String::from_utf8(rust).unwrap();
+        assert!(rust.contains("const _: () = assert!(std::mem::size_of::<UBase>() == 0x0010);"));
+        assert!(!rust.contains("size_of::<UDerived>()"));
+        assert!(!rust.contains("size_of::<UNative>()"));
+        assert!(rust.contains("/// Class Test.Native, 0x0004 bytes, unverified as"));
+        assert!(rust.contains("    _pad_0x0008: [u8; 0x8],"));
+
+        let mut cpp = vec![];
+        sdk.write_cpp(&mut cpp).unwrap();
+        let cpp = String::from_utf8(cpp).unwrap();
+        assert!(cpp.contains("static_assert(sizeof(UBase) == 0x0010, \"Wrong size of UBase\");"));
+        assert_eq!(cpp.matches("static_assert").count(), 1);
+        assert!(cpp.contains("class UDerived : public UBase {"));
+        assert!(cpp.contains("    int32_t C; // 0x0010"));
+    }
+
+    #[test]
+    fn known_sizes() {
+        let sizes = read_known_sizes("Core.Object 0x60\n# native classes\n\nEngine.Actor  400 # decimal\n").unwrap();
+
+        assert_eq!(sizes, HashMap::from([("core.object".to_string(), 0x60), ("engine.actor".to_string(), 400)]));
+        assert!(read_known_sizes("Core.Object").is_err());
+        assert!(read_known_sizes("Core.Object 0xZZ").is_err());
+    }
+}
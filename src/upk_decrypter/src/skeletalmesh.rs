@@ -0,0 +1,413 @@
+//! `SkeletalMesh` exports, in the cooked layout of Rocket League's package version where every
+//! vertex is in the GPU skinning buffer.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::animation::{UAnimSequence, UAnimSet};
+use crate::archive::{FArchive, read_serializable};
+use crate::bulkdata::FUntypedBulkData;
+use crate::file::GameFile;
+use crate::gltf::{GltfAnimation, GltfBone, GltfMesh, GltfPrimitive, GltfTrack, write_glb};
+use crate::mesh::{ElementReader, FBulkArray, read_vector};
+use crate::package::{FName, UnPackage};
+use crate::properties::{FPropertyTag, find_property, read_object_properties};
+use crate::{Result, ParserError};
+
+const MAX_BONES: i32 = 1024;
+const MAX_LODS: i32 = 8;
+const MAX_SECTIONS: i32 = 256;
+const MAX_UV_CHANNELS: u32 = 4;
+const MAX_INFLUENCES: usize = 4;
+
+/// The packed tangents, bone indices and weights before the position of a vertex.
+const INFLUENCES_SIZE: usize = 16;
+
+#[derive(Debug, Default, Clone)]
+pub struct FMeshBone {
+    pub name: String,
+    pub flags: u32,
+    /// The rotation relative to the parent, as X, Y, Z, W.
+    pub orientation: [f32; 4],
+    pub position: [f32; 3],
+    pub num_children: i32,
+    /// The index of the parent bone, the root is its own parent.
+    pub parent_index: i32
+}
+
+/// A material slot and the range of the index buffer that uses it.
+#[derive(Debug, Default, Clone)]
+pub struct FSkelMeshSection {
+    pub material_index: u16,
+    pub chunk_index: u16,
+    pub first_index: u32,
+    pub num_triangles: u32
+}
+
+/// A range of vertices skinned with a subset of the skeleton.
+#[derive(Debug, Default, Clone)]
+pub struct FSkelMeshChunk {
+    pub first_vertex: u32,
+    /// Maps the bone indices of the chunk's vertices to the skeleton.
+    pub bone_map: Vec<u16>,
+    pub max_influences: i32
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FSkeletalMeshLODModel {
+    pub sections: Vec<FSkelMeshSection>,
+    pub chunks: Vec<FSkelMeshChunk>,
+    pub indices: Vec<u32>,
+    pub positions: Vec<[f32; 3]>,
+    /// `TangentX` and `TangentZ` of each vertex, the latter is the normal.
+    pub tangents: Vec<[[f32; 4]; 2]>,
+    pub uvs: Vec<Vec<[f32; 2]>>,
+    /// The skeleton bones of each vertex, already mapped through its chunk.
+    pub bones: Vec<[u16; 4]>,
+    pub weights: Vec<[u8; 4]>,
+    /// Vertex colors as BGRA, empty when the mesh has none.
+    pub colors: Vec<[u8; 4]>
+}
+
+#[derive(Debug, Clone)]
+pub struct USkeletalMesh {
+    pub properties: Vec<FPropertyTag>,
+    pub materials: Vec<i32>,
+    pub skeleton: Vec<FMeshBone>,
+    pub lods: Vec<FSkeletalMeshLODModel>
+}
+
+impl USkeletalMesh {
+
+    /// Parses the serialized data of a `SkeletalMesh` export of `package`.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, data: Vec<u8>) -> Result<Self> {
+        let mut archive = package.object_archive(data);
+        let properties = read_object_properties(&mut archive, &package.names)?;
+        let has_vertex_colors = find_property(&properties, "bHasVertexColors").and_then(FPropertyTag::as_bool).unwrap_or(false);
+
+        // Bounds: origin, extent and radius.
+        read_vector(&mut archive)?;
+        read_vector(&mut archive)?;
+        archive.read_f32()?;
+
+        let count = archive.read_i32()?;
+        if !(0..=MAX_SECTIONS).contains(&count) {
+            return Err(Box::new(ParserError::new(&format!("Invalid material count: {}", count))));
+        }
+        let materials = (0..count).map(|_| archive.read_i32()).collect::<Result<Vec<_>>>()?;
+
+        // Origin and RotOrigin
+        read_vector(&mut archive)?;
+        for _ in 0..3 {
+            archive.read_i32()?;
+        }
+
+        let count = archive.read_i32()?;
+        if !(0..=MAX_BONES).contains(&count) {
+            return Err(Box::new(ParserError::new(&format!("Invalid bone count: {}", count))));
+        }
+        let mut skeleton = Vec::with_capacity(usize::try_from(count)?);
+        for _ in 0..count {
+            skeleton.push(read_bone(package, &mut archive)?);
+        }
+
+        // SkeletalDepth
+        archive.read_i32()?;
+
+        let count = archive.read_i32()?;
+        if !(0..=MAX_LODS).contains(&count) {
+            return Err(Box::new(ParserError::new(&format!("Invalid LOD count: {}", count))));
+        }
+        let mut lods = Vec::with_capacity(usize::try_from(count)?);
+        for _ in 0..count {
+            lods.push(read_lod_model(&mut archive, skeleton.len(), has_vertex_colors)?);
+        }
+
+        Ok(Self { properties, materials, skeleton, lods })
+    }
+
+    /// Writes a LOD as a skinned glTF binary. Every sequence is added as an animation, its tracks
+    /// are matched to the bones by the `TrackBoneNames` of its anim set.
+    pub fn save_glb<F: GameFile, P: AsRef<Path>>(&self, package: &UnPackage<F>, path: P, lod: usize, animations: &[(&UAnimSet, &UAnimSequence)]) -> Result<()> {
+        let model = self.lods.get(lod).ok_or_else(|| ParserError::new(&format!("Mesh has no LOD {}", lod)))?;
+        let name = path.as_ref().file_stem().and_then(|name| name.to_str()).unwrap_or_default().to_owned();
+
+        let mut mesh = GltfMesh {
+            name,
+            positions: model.positions.clone(),
+            normals: model.tangents.iter().map(|[_, normal]| [normal[0], normal[1], normal[2]]).collect(),
+            uvs: model.uvs.clone(),
+            colors: model.colors.iter().map(|[b, g, r, a]| [*r, *g, *b, *a]).collect(),
+            joints: model.bones.clone(),
+            weights: model.weights.clone(),
+            bones: self.gltf_bones()?,
+            ..GltfMesh::default()
+        };
+
+        for section in &model.sections {
+            let first = usize::try_from(section.first_index)?;
+            let count = usize::try_from(section.num_triangles)? * 3;
+            let indices = model.indices.get(first..first + count)
+                .ok_or_else(|| ParserError::new("Section is out of the index buffer"))?;
+
+            let material = self.materials.get(usize::from(section.material_index)).copied().unwrap_or(0);
+            mesh.primitives.push(GltfPrimitive { indices: indices.to_vec(), material: mesh.materials.len() });
+            mesh.materials.push(package.get_object_name(material).unwrap_or_else(|| String::from("None")));
+        }
+
+        mesh.animations = animations.iter().map(|(set, sequence)| self.gltf_animation(set, sequence)).collect();
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_glb(&mut writer, &mesh)
+    }
+
+    /// The index of a bone by name, ignoring case like Unreal's names.
+    pub fn find_bone(&self, name: &str) -> Option<usize> {
+        self.skeleton.iter().position(|bone| bone.name.eq_ignore_ascii_case(name))
+    }
+
+    fn gltf_bones(&self) -> Result<Vec<GltfBone>> {
+        let mut bones = Vec::with_capacity(self.skeleton.len());
+        for (index, bone) in self.skeleton.iter().enumerate() {
+            let parent = usize::try_from(bone.parent_index).ok().filter(|parent| *parent != index);
+            if parent.is_some_and(|parent| parent > index) {
+                return Err(Box::new(ParserError::new(&format!("Bone {} comes before its parent", bone.name))));
+            }
+
+            bones.push(GltfBone { name: bone.name.clone(), parent, translation: bone.position, rotation: bone.orientation });
+        }
+
+        Ok(bones)
+    }
+
+    fn gltf_animation(&self, set: &UAnimSet, sequence: &UAnimSequence) -> GltfAnimation {
+        let tracks = sequence.tracks.iter().zip(&set.track_bone_names)
+            .filter_map(|(track, bone)| Some(GltfTrack {
+                bone: self.find_bone(bone)?,
+                translation_times: track.translation_times.clone(),
+                translations: track.translations.clone(),
+                rotation_times: track.rotation_times.clone(),
+                rotations: track.rotations.clone()
+            }))
+            .collect();
+
+        GltfAnimation { name: sequence.name.clone(), tracks }
+    }
+
+}
+
+fn read_bone<F: GameFile, Ar: FArchive>(package: &UnPackage<F>, archive: &mut Ar) -> Result<FMeshBone> {
+    let name: FName = read_serializable(archive)?;
+    let bone = FMeshBone {
+        name: package.get_name(&name).ok_or_else(|| ParserError::new(&format!("Invalid name index {}", name.index)))?,
+        flags: archive.read_u32()?,
+        orientation: [archive.read_f32()?, archive.read_f32()?, archive.read_f32()?, archive.read_f32()?],
+        position: read_vector(archive)?,
+        num_children: archive.read_i32()?,
+        parent_index: archive.read_i32()?
+    };
+
+    // BoneColor
+    archive.read_u32()?;
+
+    Ok(bone)
+}
+
+fn read_lod_model<Ar: FArchive>(archive: &mut Ar, bone_count: usize, has_vertex_colors: bool) -> Result<FSkeletalMeshLODModel> {
+    let count = archive.read_i32()?;
+    if !(0..=MAX_SECTIONS).contains(&count) {
+        return Err(Box::new(ParserError::new(&format!("Invalid section count: {}", count))));
+    }
+
+    let mut sections = Vec::with_capacity(usize::try_from(count)?);
+    for _ in 0..count {
+        sections.push(FSkelMeshSection {
+            material_index: archive.read_u16()?,
+            chunk_index: archive.read_u16()?,
+            first_index: archive.read_u32()?,
+            num_triangles: archive.read_u32()?
+        });
+        // TriangleSorting
+        archive.read_u8()?;
+    }
+
+    let indices = read_index_container(archive)?;
+
+    // UsedBones
+    read_short_array(archive)?;
+
+    let count = archive.read_i32()?;
+    if !(0..=MAX_SECTIONS).contains(&count) {
+        return Err(Box::new(ParserError::new(&format!("Invalid chunk count: {}", count))));
+    }
+    let mut chunks = Vec::with_capacity(usize::try_from(count)?);
+    for _ in 0..count {
+        chunks.push(read_chunk(archive)?);
+    }
+
+    // Size, then NumVertices
+    archive.read_u32()?;
+    let num_vertices = usize::try_from(archive.read_u32()?)?;
+
+    // RequiredBones
+    let count = archive.read_i32()?;
+    for _ in 0..count.max(0) {
+        archive.read_u8()?;
+    }
+
+    // RawPointIndices
+    FUntypedBulkData::serialize(archive)?;
+
+    // NumTexCoords, repeated by the vertex buffer
+    archive.read_u32()?;
+    let mut model = read_gpu_skin_buffer(archive, &chunks, bone_count)?;
+    model.sections = sections;
+    model.chunks = chunks;
+    model.indices = indices;
+
+    if has_vertex_colors {
+        archive.read_u32()?;
+        if archive.read_u32()? > 0 {
+            let big_endian = archive.is_big_endian();
+            let array = FBulkArray::read_sized(archive, 4, "color")?;
+            model.colors = array.elements().map(|element| ElementReader::new(element, big_endian).u32().map(u32::to_le_bytes)).collect::<Result<_>>()?;
+        }
+    }
+
+    if archive.read_i32()? != 0 {
+        return Err(Box::new(ParserError::new("Meshes with alternate vertex influences are not supported")));
+    }
+
+    // AdjacencyIndexBuffer
+    read_index_container(archive)?;
+
+    if model.positions.len() != num_vertices || (!model.colors.is_empty() && model.colors.len() != num_vertices) {
+        return Err(Box::new(ParserError::new("The vertex buffers don't have the same number of vertices")));
+    }
+
+    Ok(model)
+}
+
+fn read_chunk<Ar: FArchive>(archive: &mut Ar) -> Result<FSkelMeshChunk> {
+    let first_vertex = archive.read_u32()?;
+    let rigid_vertices = archive.read_i32()?;
+    let soft_vertices = archive.read_i32()?;
+    if rigid_vertices != 0 || soft_vertices != 0 {
+        return Err(Box::new(ParserError::new("Meshes with CPU skinned vertices are not supported")));
+    }
+
+    let bone_map = read_short_array(archive)?;
+
+    // NumRigidVertices and NumSoftVertices
+    archive.read_i32()?;
+    archive.read_i32()?;
+    let max_influences = archive.read_i32()?;
+
+    Ok(FSkelMeshChunk { first_vertex, bone_map, max_influences })
+}
+
+fn read_short_array<Ar: FArchive>(archive: &mut Ar) -> Result<Vec<u16>> {
+    let count = archive.read_i32()?;
+    if !(0..=MAX_BONES).contains(&count) {
+        return Err(Box::new(ParserError::new(&format!("Invalid bone list length: {}", count))));
+    }
+
+    (0..count).map(|_| archive.read_u16()).collect()
+}
+
+/// `FMultiSizeIndexContainer`, 16 or 32-bit indices depending on the vertex count.
+fn read_index_container<Ar: FArchive>(archive: &mut Ar) -> Result<Vec<u32>> {
+    // NeedsCPUAccess
+    archive.read_u32()?;
+    let size = archive.read_u8()?;
+    if size != 2 && size != 4 {
+        return Err(Box::new(ParserError::new(&format!("Invalid index size: {}", size))));
+    }
+
+    let big_endian = archive.is_big_endian();
+    let array = FBulkArray::read_sized(archive, usize::from(size), "index")?;
+    array.elements().map(|element| {
+        let mut reader = ElementReader::new(element, big_endian);
+        if size == 2 { reader.u16().map(u32::from) } else { reader.u32() }
+    }).collect()
+}
+
+/// `FSkeletalMeshVertexBuffer`, the vertices with their influences, positions and UVs.
+fn read_gpu_skin_buffer<Ar: FArchive>(archive: &mut Ar, chunks: &[FSkelMeshChunk], bone_count: usize) -> Result<FSkeletalMeshLODModel> {
+    let channels = archive.read_u32()?;
+    let full_precision_uvs = archive.read_u32()? != 0;
+    let packed_positions = archive.read_u32()? != 0;
+    let extension = read_vector(archive)?;
+    let origin = read_vector(archive)?;
+    if channels > MAX_UV_CHANNELS {
+        return Err(Box::new(ParserError::new(&format!("Invalid UV channel count: {}", channels))));
+    }
+
+    let channels = usize::try_from(channels)?;
+    let position_size = if packed_positions { 4 } else { 12 };
+    let uv_size = if full_precision_uvs { 8 } else { 4 };
+
+    let big_endian = archive.is_big_endian();
+    let array = FBulkArray::read_sized(archive, INFLUENCES_SIZE + position_size + channels * uv_size, "skinned vertex")?;
+    let mut model = FSkeletalMeshLODModel { uvs: vec![Vec::with_capacity(array.count); channels], ..FSkeletalMeshLODModel::default() };
+
+    let mut chunk = 0;
+    for (index, element) in array.elements().enumerate() {
+        while chunks.get(chunk + 1).is_some_and(|next| usize::try_from(next.first_vertex).is_ok_and(|first| first <= index)) {
+            chunk += 1;
+        }
+        let bone_map = chunks.get(chunk).map_or(&[][..], |chunk| &chunk.bone_map[..]);
+
+        let mut reader = ElementReader::new(element, big_endian);
+        model.tangents.push([reader.packed_normal()?, reader.packed_normal()?]);
+
+        let mut bones = [0u16; MAX_INFLUENCES];
+        let mut weights = [0u8; MAX_INFLUENCES];
+        let influences = element.get(8..INFLUENCES_SIZE).ok_or_else(|| ParserError::new("Vertex is too short"))?;
+        for i in 0..MAX_INFLUENCES {
+            weights[i] = influences[MAX_INFLUENCES + i];
+            if weights[i] == 0 {
+                continue;
+            }
+
+            let local = usize::from(influences[i]);
+            bones[i] = *bone_map.get(local).ok_or_else(|| ParserError::new(&format!("Bone {} is not in the chunk", local)))?;
+            if usize::from(bones[i]) >= bone_count {
+                return Err(Box::new(ParserError::new(&format!("Bone {} is out of the skeleton", bones[i]))));
+            }
+        }
+        model.bones.push(bones);
+        model.weights.push(weights);
+
+        let mut reader = ElementReader::new(&element[INFLUENCES_SIZE..], big_endian);
+        let position = if packed_positions {
+            let packed = unpack_position(reader.u32()?);
+            [0, 1, 2].map(|i| packed[i] * extension[i] + origin[i])
+        } else {
+            reader.vector()?
+        };
+        model.positions.push(position);
+
+        for channel in &mut model.uvs {
+            let uv = if full_precision_uvs {
+                [reader.f32()?, reader.f32()?]
+            } else {
+                [reader.f16()?, reader.f16()?]
+            };
+            channel.push(uv);
+        }
+    }
+
+    Ok(model)
+}
+
+/// `FPackedPosition`, signed 11, 11 and 10-bit components relative to the mesh extension.
+fn unpack_position(packed: u32) -> [f32; 3] {
+    let signed = |value: u32, bits: u32| {
+        let shift = 32 - bits;
+        (((value << shift) as i32) >> shift) as f32 / ((1 << (bits - 1)) - 1) as f32
+    };
+
+    [signed(packed & 0x7FF, 11), signed((packed >> 11) & 0x7FF, 11), signed(packed >> 22, 10)]
+}
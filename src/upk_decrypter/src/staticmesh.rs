@@ -0,0 +1,242 @@
+//! `StaticMesh` exports, in the cooked layout of Rocket League's package version.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::archive::FArchive;
+use crate::bulkdata::FUntypedBulkData;
+use crate::file::GameFile;
+use crate::gltf::{GltfMesh, GltfPrimitive, write_glb};
+use crate::mesh::{ElementReader, FBulkArray, read_index_buffer, read_vector, read_vector_array};
+use crate::package::UnPackage;
+use crate::properties::{FPropertyTag, read_object_properties};
+use crate::{Result, ParserError};
+
+const MAX_LODS: i32 = 8;
+const MAX_SECTIONS: i32 = 256;
+const MAX_UV_CHANNELS: u32 = 8;
+
+/// The size of the two packed tangents before the UVs of a vertex.
+const TANGENTS_SIZE: usize = 8;
+/// `FkDOPNode` and `FkDOPTriangle` in the collision tree.
+const KDOP_NODE_SIZE: usize = 6;
+const KDOP_TRIANGLE_SIZE: usize = 8;
+
+/// A material slot and the range of the index buffer that uses it.
+#[derive(Debug, Default, Clone)]
+pub struct FStaticMeshSection {
+    /// The material object reference, 0 when the slot is empty.
+    pub material: i32,
+    pub enable_collision: bool,
+    pub first_index: u32,
+    pub num_triangles: u32,
+    pub min_vertex_index: u32,
+    pub max_vertex_index: u32,
+    pub material_index: i32
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FStaticMeshLODModel {
+    pub sections: Vec<FStaticMeshSection>,
+    pub positions: Vec<[f32; 3]>,
+    /// `TangentX` and `TangentZ` of each vertex, the latter is the normal.
+    pub tangents: Vec<[[f32; 4]; 2]>,
+    /// The UV channels, each with one coordinate per vertex.
+    pub uvs: Vec<Vec<[f32; 2]>>,
+    /// Vertex colors as BGRA, empty when the mesh has none.
+    pub colors: Vec<[u8; 4]>,
+    pub indices: Vec<u32>
+}
+
+#[derive(Debug, Clone)]
+pub struct UStaticMesh {
+    pub properties: Vec<FPropertyTag>,
+    pub bounds_origin: [f32; 3],
+    pub bounds_extent: [f32; 3],
+    pub bounds_radius: f32,
+    /// The `RB_BodySetup` holding the simplified collision, 0 when the mesh has none.
+    pub body_setup: i32,
+    pub lods: Vec<FStaticMeshLODModel>
+}
+
+impl UStaticMesh {
+
+    /// Parses the serialized data of a `StaticMesh` export of `package`.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, data: Vec<u8>) -> Result<Self> {
+        let mut archive = package.object_archive(data);
+        let properties = read_object_properties(&mut archive, &package.names)?;
+
+        let bounds_origin = read_vector(&mut archive)?;
+        let bounds_extent = read_vector(&mut archive)?;
+        let bounds_radius = archive.read_f32()?;
+        let body_setup = archive.read_i32()?;
+
+        // The compact kDOP tree used for per-poly collision, its root bounds then the nodes and triangles.
+        read_vector(&mut archive)?;
+        read_vector(&mut archive)?;
+        FBulkArray::read_sized(&mut archive, KDOP_NODE_SIZE, "kDOP node")?;
+        FBulkArray::read_sized(&mut archive, KDOP_TRIANGLE_SIZE, "kDOP triangle")?;
+
+        // InternalVersion
+        archive.read_i32()?;
+
+        let count = archive.read_i32()?;
+        if !(0..=MAX_LODS).contains(&count) {
+            return Err(Box::new(ParserError::new(&format!("Invalid LOD count: {}", count))));
+        }
+
+        let mut lods = Vec::with_capacity(usize::try_from(count)?);
+        for _ in 0..count {
+            lods.push(read_lod_model(&mut archive)?);
+        }
+
+        Ok(Self { properties, bounds_origin, bounds_extent, bounds_radius, body_setup, lods })
+    }
+
+    /// Writes a LOD as a glTF binary, one primitive per section with the material slots named
+    /// after their materials.
+    pub fn save_glb<F: GameFile, P: AsRef<Path>>(&self, package: &UnPackage<F>, path: P, lod: usize) -> Result<()> {
+        let model = self.lods.get(lod).ok_or_else(|| ParserError::new(&format!("Mesh has no LOD {}", lod)))?;
+        let name = path.as_ref().file_stem().and_then(|name| name.to_str()).unwrap_or_default().to_owned();
+
+        let mut mesh = GltfMesh {
+            name,
+            positions: model.positions.clone(),
+            normals: model.tangents.iter().map(|[_, normal]| [normal[0], normal[1], normal[2]]).collect(),
+            uvs: model.uvs.clone(),
+            colors: model.colors.iter().map(|[b, g, r, a]| [*r, *g, *b, *a]).collect(),
+            ..GltfMesh::default()
+        };
+
+        for section in &model.sections {
+            let first = usize::try_from(section.first_index)?;
+            let count = usize::try_from(section.num_triangles)? * 3;
+            let indices = model.indices.get(first..first + count)
+                .ok_or_else(|| ParserError::new("Section is out of the index buffer"))?;
+
+            mesh.primitives.push(GltfPrimitive { indices: indices.to_vec(), material: mesh.materials.len() });
+            mesh.materials.push(package.get_object_name(section.material).unwrap_or_else(|| String::from("None")));
+        }
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_glb(&mut writer, &mesh)
+    }
+
+}
+
+fn read_lod_model<Ar: FArchive>(archive: &mut Ar) -> Result<FStaticMeshLODModel> {
+    // The raw triangles of the source mesh, empty once cooked.
+    FUntypedBulkData::serialize(archive)?;
+
+    let count = archive.read_i32()?;
+    if !(0..=MAX_SECTIONS).contains(&count) {
+        return Err(Box::new(ParserError::new(&format!("Invalid section count: {}", count))));
+    }
+
+    let mut sections = Vec::with_capacity(usize::try_from(count)?);
+    for _ in 0..count {
+        sections.push(read_section(archive)?);
+    }
+
+    // FPositionVertexBuffer: stride and vertex count ahead of the positions.
+    archive.read_u32()?;
+    let num_vertices = archive.read_u32()?;
+    let positions = read_vector_array(archive, "position")?;
+
+    let (tangents, uvs) = read_vertex_buffer(archive)?;
+    let colors = read_color_buffer(archive)?;
+
+    // NumVertices, then the index buffers for rendering, wireframe and adjacency.
+    archive.read_u32()?;
+    let indices = read_index_buffer(archive)?;
+    read_index_buffer(archive)?;
+    read_index_buffer(archive)?;
+
+    let vertex_count = usize::try_from(num_vertices)?;
+    if positions.len() != vertex_count || tangents.len() != vertex_count || (!colors.is_empty() && colors.len() != vertex_count) {
+        return Err(Box::new(ParserError::new("The vertex buffers don't have the same number of vertices")));
+    }
+
+    Ok(FStaticMeshLODModel { sections, positions, tangents, uvs, colors, indices })
+}
+
+fn read_section<Ar: FArchive>(archive: &mut Ar) -> Result<FStaticMeshSection> {
+    let material = archive.read_i32()?;
+    let enable_collision = archive.read_u32()? != 0;
+    // OldEnableCollision and bEnableShadowCasting
+    archive.read_u32()?;
+    archive.read_u32()?;
+
+    let section = FStaticMeshSection {
+        material,
+        enable_collision,
+        first_index: archive.read_u32()?,
+        num_triangles: archive.read_u32()?,
+        min_vertex_index: archive.read_u32()?,
+        max_vertex_index: archive.read_u32()?,
+        material_index: archive.read_i32()?
+    };
+
+    // Fragments, pairs of base index and triangle count.
+    let fragments = archive.read_i32()?;
+    for _ in 0..fragments.max(0) {
+        archive.read_i32()?;
+        archive.read_i32()?;
+    }
+
+    if archive.read_u8()? != 0 {
+        return Err(Box::new(ParserError::new("Sections with PS3 data are not supported")));
+    }
+
+    Ok(section)
+}
+
+/// `FStaticMeshVertexBuffer`, the packed tangents and UVs of each vertex.
+#[allow(clippy::type_complexity)]
+fn read_vertex_buffer<Ar: FArchive>(archive: &mut Ar) -> Result<(Vec<[[f32; 4]; 2]>, Vec<Vec<[f32; 2]>>)> {
+    let channels = archive.read_u32()?;
+    let stride = archive.read_u32()?;
+    archive.read_u32()?;
+    let full_precision_uvs = archive.read_u32()? != 0;
+    if channels > MAX_UV_CHANNELS {
+        return Err(Box::new(ParserError::new(&format!("Invalid UV channel count: {}", channels))));
+    }
+
+    let channels = usize::try_from(channels)?;
+    let uv_size = if full_precision_uvs { 8 } else { 4 };
+    if usize::try_from(stride)? != TANGENTS_SIZE + channels * uv_size {
+        return Err(Box::new(ParserError::new(&format!("Unexpected vertex stride {} for {} UV channels", stride, channels))));
+    }
+
+    let big_endian = archive.is_big_endian();
+    let array = FBulkArray::read_sized(archive, usize::try_from(stride)?, "vertex")?;
+    let mut tangents = Vec::with_capacity(array.count);
+    let mut uvs = vec![Vec::with_capacity(array.count); channels];
+    for element in array.elements() {
+        let mut reader = ElementReader::new(element, big_endian);
+        tangents.push([reader.packed_normal()?, reader.packed_normal()?]);
+        for channel in &mut uvs {
+            let uv = if full_precision_uvs {
+                [reader.f32()?, reader.f32()?]
+            } else {
+                [reader.f16()?, reader.f16()?]
+            };
+            channel.push(uv);
+        }
+    }
+
+    Ok((tangents, uvs))
+}
+
+/// `FColorVertexBuffer`, whose colors are only serialized when there are some.
+fn read_color_buffer<Ar: FArchive>(archive: &mut Ar) -> Result<Vec<[u8; 4]>> {
+    archive.read_u32()?;
+    if archive.read_u32()? == 0 {
+        return Ok(vec![]);
+    }
+
+    let big_endian = archive.is_big_endian();
+    let array = FBulkArray::read_sized(archive, 4, "color")?;
+    array.elements().map(|element| ElementReader::new(element, big_endian).u32().map(u32::to_le_bytes)).collect()
+}
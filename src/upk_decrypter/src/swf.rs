@@ -0,0 +1,82 @@
+//! Scaleform movies. A `SwfMovie` keeps the GFx file it was imported from in its `RawData`
+//! property, and the textures the movie imports in `References`.
+
+use crate::file::GameFile;
+use crate::package::UnPackage;
+use crate::properties::{FPropertyTag, find_property, read_byte_array, read_object_array, read_object_properties};
+use crate::{Result, ParserError};
+
+/// GFx signatures and their SWF counterparts, the second and third letters of SWF's being swapped.
+const SIGNATURES: [(&[u8; 3], &[u8; 3]); 2] = [(b"GFX", b"FWS"), (b"CFX", b"CWS")];
+const HEADER_SIZE: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct USwfMovie {
+    pub properties: Vec<FPropertyTag>,
+    /// The GFx file, uncompressed (`GFX`) or with its body zlib compressed (`CFX`).
+    pub data: Vec<u8>,
+    /// The objects the movie imports, mostly textures.
+    pub references: Vec<i32>
+}
+
+impl USwfMovie {
+
+    /// Parses the serialized data of a `SwfMovie` or `GFxMovieInfo` export of `package`.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, data: Vec<u8>) -> Result<Self> {
+        let mut archive = package.object_archive(data);
+        let properties = read_object_properties(&mut archive, &package.names)?;
+
+        let data = match find_property(&properties, "RawData") {
+            Some(property) => read_byte_array(package, property)?,
+            None => return Err(Box::new(ParserError::new("Movie has no RawData")))
+        };
+
+        let references = match find_property(&properties, "References") {
+            Some(property) => read_object_array(package, property)?,
+            None => vec![]
+        };
+
+        if data.len() < HEADER_SIZE {
+            return Err(Box::new(ParserError::new(&format!("Movie data is too short: {} bytes", data.len()))));
+        }
+
+        Ok(Self { properties, data, references })
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.data[0] == b'C'
+    }
+
+    /// The movie with the signature of a SWF file, as the formats only differ by their signature
+    /// and the tags Scaleform adds, which SWF tools skip.
+    pub fn to_swf(&self) -> Result<Vec<u8>> {
+        let signature = &self.data[..3];
+        let swf_signature = SIGNATURES.iter()
+            .find_map(|(gfx, swf)| if signature == gfx.as_slice() || signature == swf.as_slice() { Some(swf) } else { None })
+            .ok_or_else(|| ParserError::new(&format!("Unknown movie signature {:?}", String::from_utf8_lossy(signature))))?;
+
+        let mut swf = self.data.clone();
+        swf[..3].copy_from_slice(swf_signature.as_slice());
+
+        Ok(swf)
+    }
+
+    /// The paths of the textures the movie imports.
+    pub fn referenced_textures<F: GameFile>(&self, package: &UnPackage<F>) -> Vec<String> {
+        self.references.iter()
+            .filter(|reference| reference_class(package, **reference).is_some_and(|class| class.starts_with("Texture")))
+            .filter_map(|reference| package.get_object_path(*reference))
+            .collect()
+    }
+
+}
+
+/// The class name of an object reference, imports included.
+fn reference_class<F: GameFile>(package: &UnPackage<F>, index: i32) -> Option<String> {
+    if index < 0 {
+        let import = package.imports.get(usize::try_from(-index - 1).ok()?)?;
+        return package.get_name(&import.class_name);
+    }
+
+    package.get_class_name(package.exports.get(usize::try_from(index - 1).ok()?)?)
+}
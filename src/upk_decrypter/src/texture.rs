@@ -0,0 +1,257 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek};
+use std::path::Path;
+
+use crate::archive::FArchive;
+use crate::bulkdata::{EBulkDataLocation, FUntypedBulkData};
+use crate::dds::{mip_size, write_dds};
+use crate::dxt;
+use crate::file::GameFile;
+use crate::package::UnPackage;
+use crate::properties::{FPropertyTag, find_property, read_object_properties};
+use crate::{Result, ParserError};
+
+const MAX_MIPS: i32 = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EPixelFormat {
+    A8R8G8B8,
+    G8,
+    DXT1,
+    DXT3,
+    DXT5,
+    BC5,
+    Unknown(String)
+}
+
+impl From<&str> for EPixelFormat {
+    fn from(val: &str) -> Self {
+        match val {
+            "PF_A8R8G8B8" => Self::A8R8G8B8,
+            "PF_G8" => Self::G8,
+            "PF_DXT1" => Self::DXT1,
+            "PF_DXT3" => Self::DXT3,
+            "PF_DXT5" => Self::DXT5,
+            "PF_BC5" => Self::BC5,
+            other => Self::Unknown(other.to_owned())
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FTexture2DMipMap {
+    pub data: FUntypedBulkData,
+    pub size_x: i32,
+    pub size_y: i32
+}
+
+impl FTexture2DMipMap {
+
+    /// Whether the mip lives in a texture file cache instead of the package.
+    pub fn is_external(&self) -> bool {
+        self.data.location() == EBulkDataLocation::External
+    }
+
+    /// The uncompressed mip, empty when it's stored elsewhere and hasn't been loaded.
+    pub fn pixels(&self) -> &[u8] {
+        &self.data.data
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct UTexture2D {
+    pub properties: Vec<FPropertyTag>,
+    pub format: EPixelFormat,
+    pub size_x: i32,
+    pub size_y: i32,
+    pub texture_file_cache_name: Option<String>,
+    pub mips: Vec<FTexture2DMipMap>
+}
+
+impl UTexture2D {
+
+    /// Parses the serialized data of a `Texture2D` export of `package`.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, data: Vec<u8>) -> Result<Self> {
+        let mut archive = package.object_archive(data);
+        let properties = read_object_properties(&mut archive, &package.names)?;
+
+        // UTexture::SourceArt, empty in cooked packages but still serialized.
+        FUntypedBulkData::serialize(&mut archive)?;
+
+        let count = archive.read_i32()?;
+        if !(0..=MAX_MIPS).contains(&count) {
+            return Err(Box::new(ParserError::new(&format!("Invalid mip count: {}", count))));
+        }
+
+        let mut mips = Vec::with_capacity(usize::try_from(count)?);
+        for _ in 0..count {
+            mips.push(FTexture2DMipMap {
+                data: FUntypedBulkData::serialize(&mut archive)?,
+                size_x: archive.read_i32()?,
+                size_y: archive.read_i32()?
+            });
+        }
+
+        let format = find_property(&properties, "Format")
+            .and_then(FPropertyTag::as_str)
+            .map_or(EPixelFormat::Unknown(String::from("None")), EPixelFormat::from);
+        let size_x = find_property(&properties, "SizeX").and_then(FPropertyTag::as_int).unwrap_or(0);
+        let size_y = find_property(&properties, "SizeY").and_then(FPropertyTag::as_int).unwrap_or(0);
+        let texture_file_cache_name = find_property(&properties, "TextureFileCacheName")
+            .and_then(FPropertyTag::as_str)
+            .filter(|name| *name != "None")
+            .map(str::to_owned);
+
+        Ok(Self { properties, format, size_x, size_y, texture_file_cache_name, mips })
+    }
+
+    /// Reads the mips stored in the texture file cache `cache`, which is the `.tfc` named by
+    /// `texture_file_cache_name`. Returns how many mips were loaded.
+    pub fn load_external_mips<R: Read + Seek>(&mut self, cache: &mut R, big_endian: bool) -> Result<usize> {
+        self.load_mips(EBulkDataLocation::External, cache, big_endian)
+    }
+
+    /// Reads the mips stored at the end of the package, `reader` being the package's reader.
+    /// Returns how many mips were loaded.
+    pub fn load_package_mips<R: Read + Seek>(&mut self, reader: &mut R, big_endian: bool) -> Result<usize> {
+        self.load_mips(EBulkDataLocation::EndOfPackage, reader, big_endian)
+    }
+
+    fn load_mips<R: Read + Seek>(&mut self, location: EBulkDataLocation, source: &mut R, big_endian: bool) -> Result<usize> {
+        let mut loaded = 0;
+        for mip in self.mips.iter_mut().filter(|mip| mip.data.location() == location && !mip.data.is_loaded()) {
+            mip.data.load_from(source, big_endian)?;
+            loaded += 1;
+        }
+
+        Ok(loaded)
+    }
+
+    /// Whether some mips are in a texture file cache and haven't been loaded yet.
+    pub fn has_external_mips(&self) -> bool {
+        self.mips.iter().any(|mip| mip.is_external() && !mip.data.is_loaded())
+    }
+
+    /// The largest mip that has been loaded, either from the package or its texture file cache.
+    pub fn first_available_mip(&self) -> Option<usize> {
+        self.mips.iter().position(|mip| !mip.pixels().is_empty())
+    }
+
+    /// Decodes a mip to RGBA8, row by row from the top left.
+    pub fn decode_mip(&self, index: usize) -> Result<Vec<u8>> {
+        let mip = self.mips.get(index).ok_or_else(|| ParserError::new(&format!("Texture has no mip {}", index)))?;
+        let data = mip.pixels();
+        if data.is_empty() {
+            return Err(Box::new(ParserError::new(&format!("Mip {} is not loaded", index))));
+        }
+
+        let width = usize::try_from(mip.size_x)?;
+        let height = usize::try_from(mip.size_y)?;
+        match &self.format {
+            EPixelFormat::DXT1 => dxt::decode_dxt1(data, width, height),
+            EPixelFormat::DXT3 => dxt::decode_dxt3(data, width, height),
+            EPixelFormat::DXT5 => dxt::decode_dxt5(data, width, height),
+            EPixelFormat::BC5 => dxt::decode_bc5(data, width, height),
+            EPixelFormat::A8R8G8B8 => {
+                let pixels = data.get(..width * height * 4).ok_or_else(|| ParserError::new("Not enough data for the mip"))?;
+                Ok(pixels.chunks_exact(4).flat_map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]]).collect())
+            },
+            EPixelFormat::G8 => {
+                let pixels = data.get(..width * height).ok_or_else(|| ParserError::new("Not enough data for the mip"))?;
+                Ok(pixels.iter().flat_map(|gray| [*gray, *gray, *gray, 255]).collect())
+            },
+            EPixelFormat::Unknown(format) => Err(Box::new(ParserError::new(&format!("Unsupported pixel format: {}", format))))
+        }
+    }
+
+    /// Writes a mip as an RGBA PNG.
+    pub fn save_png<P: AsRef<Path>>(&self, path: P, mip_index: usize) -> Result<()> {
+        let pixels = self.decode_mip(mip_index)?;
+        let mip = &self.mips[mip_index];
+
+        let writer = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(writer, u32::try_from(mip.size_x)?, u32::try_from(mip.size_y)?);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&pixels)?;
+
+        Ok(())
+    }
+
+    /// Writes the mips from `first_mip` down as a DDS in the original pixel format.
+    /// The chain stops at the first mip that isn't loaded.
+    pub fn save_dds<P: AsRef<Path>>(&self, path: P, first_mip: usize) -> Result<()> {
+        let mips = self.mip_chain(first_mip)?;
+        let first = &self.mips[first_mip];
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_dds(&mut writer, &self.format, usize::try_from(first.size_x)?, usize::try_from(first.size_y)?, &[mips])
+    }
+
+    /// The data of the loaded mips from `first` on, trimmed to their exact size.
+    fn mip_chain(&self, first: usize) -> Result<Vec<&[u8]>> {
+        let mut chain = vec![];
+        for mip in self.mips.iter().skip(first).take_while(|mip| !mip.pixels().is_empty()) {
+            let size = mip_size(&self.format, usize::try_from(mip.size_x)?, usize::try_from(mip.size_y)?)?;
+            let data = mip.pixels().get(..size).ok_or_else(|| ParserError::new("Not enough data for the mip"))?;
+            chain.push(data);
+        }
+
+        if chain.is_empty() {
+            return Err(Box::new(ParserError::new(&format!("Mip {} is not loaded", first))));
+        }
+
+        Ok(chain)
+    }
+
+}
+
+/// The faces of a `TextureCube` in DDS order, +X, -X, +Y, -Y, +Z, -Z.
+const CUBE_FACES: [&str; 6] = ["FacePosX", "FaceNegX", "FacePosY", "FaceNegY", "FacePosZ", "FaceNegZ"];
+
+/// A cubemap, its faces are `Texture2D` objects referenced by its properties.
+#[derive(Debug, Clone)]
+pub struct UTextureCube {
+    pub properties: Vec<FPropertyTag>,
+    /// The object references of the faces in DDS order, 0 when a face is missing.
+    pub faces: [i32; 6]
+}
+
+impl UTextureCube {
+
+    pub fn read<F: GameFile>(package: &UnPackage<F>, data: Vec<u8>) -> Result<Self> {
+        let mut archive = package.object_archive(data);
+        let properties = read_object_properties(&mut archive, &package.names)?;
+
+        let mut faces = [0; 6];
+        for (face, name) in faces.iter_mut().zip(CUBE_FACES) {
+            *face = find_property(&properties, name).and_then(FPropertyTag::as_object).unwrap_or(0);
+        }
+
+        Ok(Self { properties, faces })
+    }
+
+    /// Writes the six faces as a cubemap DDS. The faces have to share their format and size,
+    /// the mip chain is cut to the mips every face has from `first_mip` on.
+    pub fn save_dds<P: AsRef<Path>>(&self, path: P, faces: &[UTexture2D], first_mip: usize) -> Result<()> {
+        if faces.len() != CUBE_FACES.len() {
+            return Err(Box::new(ParserError::new("A cubemap needs six faces")));
+        }
+
+        let first = faces[0].mips.get(first_mip).ok_or_else(|| ParserError::new(&format!("Texture has no mip {}", first_mip)))?;
+        if faces.iter().any(|face| face.format != faces[0].format || face.mips.get(first_mip).map(|mip| (mip.size_x, mip.size_y)) != Some((first.size_x, first.size_y))) {
+            return Err(Box::new(ParserError::new("The faces of the cubemap don't share their format and size")));
+        }
+
+        let mut chains = faces.iter().map(|face| face.mip_chain(first_mip)).collect::<Result<Vec<_>>>()?;
+        let mip_count = chains.iter().map(Vec::len).min().unwrap_or(0);
+        for chain in &mut chains {
+            chain.truncate(mip_count);
+        }
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_dds(&mut writer, &faces[0].format, usize::try_from(first.size_x)?, usize::try_from(first.size_y)?, &chains)
+    }
+
+}
@@ -0,0 +1,762 @@
+//! Rebuilds standard Ogg Vorbis from Wwise Vorbis. Wwise strips the Vorbis headers down to
+//! what differs between files, packs the codebooks (or stores their IDs in a shared library)
+//! and can drop the packet type and window bits of the audio packets. Everything is rebuilt
+//! bit for bit the way `ww2ogg` does, and the granule positions are computed from the block
+//! sizes so the stream seeks and ends correctly without another pass.
+
+use crate::wem::{CodebookLibrary, FWem, FWemVorbis};
+use crate::{Result, ParserError};
+
+const VORBIS_CODEBOOK_SYNC: u32 = 0x564342;
+const OGG_SEGMENT_SIZE: usize = 255;
+const OGG_MAX_SEGMENTS: usize = 255;
+const OGG_HEADER_SIZE: usize = 27;
+const OGG_CRC_POLYNOMIAL: u32 = 0x04c11db7;
+const VENDOR: &str = "converted from Audiokinetic Wwise by upk_decrypter";
+
+/// Where the codebooks of the setup packet come from.
+#[derive(Clone, Copy)]
+pub(crate) enum CodebookSource<'a> {
+    /// 10-bit IDs into a codebook library.
+    External(&'a CodebookLibrary),
+    /// Codebooks stored in the setup packet in Wwise's packed form.
+    InlinePacked,
+    /// A complete Vorbis setup packet, codebooks included.
+    InlineFull
+}
+
+/// The window information rebuilt audio packets need, which only stripped setups give.
+struct Modes {
+    blockflags: Vec<bool>,
+    bits: u32
+}
+
+/// Reads bits from the least significant one of each byte, as Vorbis packs them.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize
+}
+
+impl<'a> BitReader<'a> {
+
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn read(&mut self, bits: u32) -> Result<u32> {
+        let mut value = 0;
+        for bit in 0..bits {
+            let byte = self.data.get(self.position / 8).ok_or_else(|| ParserError::new("Ran out of bits in a Vorbis packet"))?;
+            if byte & (1 << (self.position % 8)) != 0 {
+                value |= 1 << bit;
+            }
+            self.position += 1;
+        }
+
+        Ok(value)
+    }
+
+    fn bits_read(&self) -> usize {
+        self.position
+    }
+
+}
+
+/// Writes bits to Ogg pages, one packet per page unless it takes more than one.
+struct OggWriter {
+    output: Vec<u8>,
+    payload: Vec<u8>,
+    bit_buffer: u8,
+    bits_stored: u32,
+    granule: u64,
+    sequence: u32,
+    first: bool
+}
+
+impl OggWriter {
+
+    fn new() -> Self {
+        Self { output: vec![], payload: vec![], bit_buffer: 0, bits_stored: 0, granule: 0, sequence: 0, first: true }
+    }
+
+    fn write(&mut self, value: u32, bits: u32) -> Result<()> {
+        for bit in 0..bits {
+            if value & (1 << bit) != 0 {
+                self.bit_buffer |= 1 << self.bits_stored;
+            }
+
+            self.bits_stored += 1;
+            if self.bits_stored == 8 {
+                self.flush_bits();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies `bits` bits from `input`.
+    fn copy(&mut self, input: &mut BitReader, bits: u32) -> Result<u32> {
+        let value = input.read(bits)?;
+        self.write(value, bits)?;
+
+        Ok(value)
+    }
+
+    fn write_packet_header(&mut self, packet_type: u32) -> Result<()> {
+        self.write(packet_type, 8)?;
+        for byte in b"vorbis" {
+            self.write(u32::from(*byte), 8)?;
+        }
+
+        Ok(())
+    }
+
+    fn flush_bits(&mut self) {
+        if self.bits_stored != 0 {
+            self.payload.push(self.bit_buffer);
+            self.bits_stored = 0;
+            self.bit_buffer = 0;
+        }
+    }
+
+    /// Writes the packet to pages. A page holds up to 255 segments of 255 bytes, a longer packet
+    /// goes on through the next pages, which are flagged as continued.
+    fn flush_page(&mut self, last: bool) -> Result<()> {
+        self.flush_bits();
+        if self.payload.is_empty() {
+            return Ok(());
+        }
+
+        // A packet ending on a segment boundary takes an empty segment, on the next page if need be.
+        let payload = std::mem::take(&mut self.payload);
+        let mut lacing = vec![OGG_SEGMENT_SIZE; payload.len() / OGG_SEGMENT_SIZE];
+        lacing.push(payload.len() % OGG_SEGMENT_SIZE);
+
+        let pages = lacing.len().div_ceil(OGG_MAX_SEGMENTS);
+        let mut offset = 0;
+        for (index, segments) in lacing.chunks(OGG_MAX_SEGMENTS).enumerate() {
+            let size: usize = segments.iter().sum();
+            let is_final = index + 1 == pages;
+
+            // The granule position is the one of the last packet ending on the page, none for pages
+            // the packet only goes through.
+            let granule = if is_final { self.granule } else { u64::MAX };
+            self.write_page(segments, &payload[offset..offset + size], index > 0, last && is_final, granule)?;
+            offset += size;
+        }
+
+        Ok(())
+    }
+
+    fn write_page(&mut self, segments: &[usize], data: &[u8], continued: bool, last: bool, granule: u64) -> Result<()> {
+        let mut page = Vec::with_capacity(OGG_HEADER_SIZE + segments.len() + data.len());
+        page.extend_from_slice(b"OggS");
+        page.push(0);
+        page.push(u8::from(continued) | (u8::from(self.first) << 1) | (u8::from(last) << 2));
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&1u32.to_le_bytes());
+        page.extend_from_slice(&self.sequence.to_le_bytes());
+        page.extend_from_slice(&0u32.to_le_bytes());
+        page.push(u8::try_from(segments.len())?);
+        for lacing in segments {
+            page.push(u8::try_from(*lacing)?);
+        }
+        page.extend_from_slice(data);
+
+        let checksum = ogg_checksum(&page);
+        page[22..26].copy_from_slice(&checksum.to_le_bytes());
+        self.output.extend_from_slice(&page);
+
+        self.sequence += 1;
+        self.first = false;
+
+        Ok(())
+    }
+
+}
+
+fn ogg_checksum(data: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    for byte in data {
+        crc ^= u32::from(*byte) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ OGG_CRC_POLYNOMIAL } else { crc << 1 };
+        }
+    }
+
+    crc
+}
+
+/// The number of bits needed to store `value`.
+fn ilog(value: u32) -> u32 {
+    u32::BITS - value.leading_zeros()
+}
+
+/// The number of values per dimension of a lookup type 1 codebook, the largest one whose
+/// power of `dimensions` doesn't exceed `entries`.
+fn maptype1_quantvals(entries: u32, dimensions: u32) -> u32 {
+    if dimensions == 0 {
+        return 0;
+    }
+
+    let bits = ilog(entries);
+    let mut values = entries >> ((bits.max(1) - 1) * (dimensions - 1) / dimensions);
+    loop {
+        let power = |base: u32| (0..dimensions).try_fold(1u64, |acc, _| acc.checked_mul(u64::from(base))).unwrap_or(u64::MAX);
+        let (accumulated, next) = (power(values), power(values + 1));
+        if accumulated <= u64::from(entries) && next > u64::from(entries) {
+            return values;
+        }
+
+        if accumulated > u64::from(entries) {
+            values -= 1;
+        } else {
+            values += 1;
+        }
+    }
+}
+
+/// Rebuilds a codebook from Wwise's packed form: shorter fields for the dimensions, entries,
+/// codeword lengths and lookup type. `size` is the exact byte size for library codebooks.
+fn rebuild_codebook(input: &mut BitReader, output: &mut OggWriter, size: Option<usize>) -> Result<()> {
+    let dimensions = input.read(4)?;
+    let entries = input.read(14)?;
+    output.write(VORBIS_CODEBOOK_SYNC, 24)?;
+    output.write(dimensions, 16)?;
+    output.write(entries, 24)?;
+
+    let ordered = output.copy(input, 1)?;
+    if ordered != 0 {
+        output.copy(input, 5)?;
+        let mut current = 0;
+        while current < entries {
+            current += output.copy(input, ilog(entries - current))?;
+        }
+
+        if current > entries {
+            return Err(Box::new(ParserError::new("Codebook entry count out of range")));
+        }
+    } else {
+        let length_bits = input.read(3)?;
+        let sparse = input.read(1)?;
+        if length_bits == 0 || length_bits > 5 {
+            return Err(Box::new(ParserError::new("Invalid codeword length size")));
+        }
+
+        output.write(sparse, 1)?;
+        for _ in 0..entries {
+            let present = sparse == 0 || output.copy(input, 1)? != 0;
+            if present {
+                let length = input.read(length_bits)?;
+                output.write(length, 5)?;
+            }
+        }
+    }
+
+    let lookup_type = input.read(1)?;
+    output.write(lookup_type, 4)?;
+    if lookup_type == 1 {
+        copy_lookup_table(input, output, entries, dimensions)?;
+    }
+
+    // The packed codebook ends with its last byte, an extra one when the bits end on a byte boundary.
+    if let Some(size) = size {
+        if input.bits_read() / 8 + 1 != size {
+            return Err(Box::new(ParserError::new(&format!("Codebook size mismatch: expected {}, read {}", size, input.bits_read() / 8 + 1))));
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies a complete Vorbis codebook.
+fn copy_codebook(input: &mut BitReader, output: &mut OggWriter) -> Result<()> {
+    if output.copy(input, 24)? != VORBIS_CODEBOOK_SYNC {
+        return Err(Box::new(ParserError::new("Invalid codebook sync pattern")));
+    }
+
+    let dimensions = output.copy(input, 16)?;
+    let entries = output.copy(input, 24)?;
+    let ordered = output.copy(input, 1)?;
+    if ordered != 0 {
+        output.copy(input, 5)?;
+        let mut current = 0;
+        while current < entries {
+            current += output.copy(input, ilog(entries - current))?;
+        }
+
+        if current > entries {
+            return Err(Box::new(ParserError::new("Codebook entry count out of range")));
+        }
+    } else {
+        let sparse = output.copy(input, 1)?;
+        for _ in 0..entries {
+            let present = sparse == 0 || output.copy(input, 1)? != 0;
+            if present {
+                output.copy(input, 5)?;
+            }
+        }
+    }
+
+    let lookup_type = output.copy(input, 4)?;
+    match lookup_type {
+        0 => Ok(()),
+        1 => copy_lookup_table(input, output, entries, dimensions),
+        _ => Err(Box::new(ParserError::new(&format!("Unexpected codebook lookup type {}", lookup_type))))
+    }
+}
+
+fn copy_lookup_table(input: &mut BitReader, output: &mut OggWriter, entries: u32, dimensions: u32) -> Result<()> {
+    output.copy(input, 32)?;
+    output.copy(input, 32)?;
+    let value_length = output.copy(input, 4)?;
+    output.copy(input, 1)?;
+
+    for _ in 0..maptype1_quantvals(entries, dimensions) {
+        output.copy(input, value_length + 1)?;
+    }
+
+    Ok(())
+}
+
+/// Rebuilds the floors, residues, mappings and modes of a stripped setup packet.
+fn rebuild_setup(input: &mut BitReader, output: &mut OggWriter, channels: u32, codebook_count: u32) -> Result<Modes> {
+    let invalid = |what: &str| Err(Box::new(ParserError::new(&format!("Invalid {} in the Vorbis setup", what))) as Box<dyn std::error::Error>);
+
+    let floor_count = output.copy(input, 6)? + 1;
+    for _ in 0..floor_count {
+        output.write(1, 16)?;
+        let partitions = output.copy(input, 5)?;
+        let classes = (0..partitions).map(|_| output.copy(input, 4)).collect::<Result<Vec<_>>>()?;
+        let maximum_class = classes.iter().copied().max().unwrap_or(0);
+
+        let mut dimensions = vec![];
+        for _ in 0..=maximum_class {
+            dimensions.push(output.copy(input, 3)? + 1);
+            let subclasses = output.copy(input, 2)?;
+            if subclasses != 0 && output.copy(input, 8)? >= codebook_count {
+                return invalid("floor masterbook");
+            }
+
+            for _ in 0..1 << subclasses {
+                let book = output.copy(input, 8)?;
+                if book > codebook_count {
+                    return invalid("floor subclass book");
+                }
+            }
+        }
+
+        output.copy(input, 2)?;
+        let range_bits = output.copy(input, 4)?;
+        for class in classes {
+            for _ in 0..dimensions[usize::try_from(class)?] {
+                output.copy(input, range_bits)?;
+            }
+        }
+    }
+
+    let residue_count = output.copy(input, 6)? + 1;
+    for _ in 0..residue_count {
+        let residue_type = input.read(2)?;
+        output.write(residue_type, 16)?;
+        if residue_type > 2 {
+            return invalid("residue type");
+        }
+
+        output.copy(input, 24)?;
+        output.copy(input, 24)?;
+        output.copy(input, 24)?;
+        let classifications = output.copy(input, 6)? + 1;
+        if output.copy(input, 8)? >= codebook_count {
+            return invalid("residue classbook");
+        }
+
+        let mut cascades = vec![];
+        for _ in 0..classifications {
+            let low_bits = output.copy(input, 3)?;
+            let high_bits = if output.copy(input, 1)? != 0 { output.copy(input, 5)? } else { 0 };
+            cascades.push(high_bits * 8 + low_bits);
+        }
+
+        for cascade in cascades {
+            for bit in 0..8 {
+                if cascade & (1 << bit) != 0 && output.copy(input, 8)? >= codebook_count {
+                    return invalid("residue book");
+                }
+            }
+        }
+    }
+
+    let mapping_count = output.copy(input, 6)? + 1;
+    for _ in 0..mapping_count {
+        output.write(0, 16)?;
+        let submaps = if output.copy(input, 1)? != 0 { output.copy(input, 4)? + 1 } else { 1 };
+
+        if output.copy(input, 1)? != 0 {
+            let coupling_steps = output.copy(input, 8)? + 1;
+            let bits = ilog(channels.saturating_sub(1));
+            for _ in 0..coupling_steps {
+                let magnitude = output.copy(input, bits)?;
+                let angle = output.copy(input, bits)?;
+                if angle == magnitude || magnitude >= channels || angle >= channels {
+                    return invalid("channel coupling");
+                }
+            }
+        }
+
+        if output.copy(input, 2)? != 0 {
+            return invalid("mapping reserved field");
+        }
+
+        if submaps > 1 {
+            for _ in 0..channels {
+                if output.copy(input, 4)? >= submaps {
+                    return invalid("channel submap");
+                }
+            }
+        }
+
+        for _ in 0..submaps {
+            output.copy(input, 8)?;
+            if output.copy(input, 8)? >= floor_count {
+                return invalid("mapping floor");
+            }
+
+            if output.copy(input, 8)? >= residue_count {
+                return invalid("mapping residue");
+            }
+        }
+    }
+
+    let mode_count = output.copy(input, 6)? + 1;
+    let mut blockflags = vec![];
+    for _ in 0..mode_count {
+        blockflags.push(output.copy(input, 1)? != 0);
+        output.write(0, 16)?;
+        output.write(0, 16)?;
+        if output.copy(input, 8)? >= mapping_count {
+            return invalid("mode mapping");
+        }
+    }
+
+    output.write(1, 1)?;
+    Ok(Modes { blockflags, bits: ilog(mode_count - 1) })
+}
+
+/// A packet of the `data` chunk: its payload and the offset of the next one.
+struct Packet {
+    payload_offset: usize,
+    size: usize,
+    granule: u32,
+    next_offset: usize
+}
+
+fn packet_header_size(vorbis: &FWemVorbis) -> usize {
+    if vorbis.no_granule { 2 } else { 6 }
+}
+
+fn read_packet(wem: &FWem, vorbis: &FWemVorbis, offset: usize) -> Result<Packet> {
+    let data = wem.data.as_slice();
+    let header_size = packet_header_size(vorbis);
+    let header = data.get(offset..offset + header_size).ok_or_else(|| ParserError::new("Vorbis packet header is truncated"))?;
+    let read_u16 = |bytes: &[u8]| if wem.big_endian { u16::from_be_bytes([bytes[0], bytes[1]]) } else { u16::from_le_bytes([bytes[0], bytes[1]]) };
+    let read_u32 = |bytes: &[u8]| {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if wem.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+    };
+
+    let size = usize::from(read_u16(header));
+    let granule = if vorbis.no_granule { 0 } else { read_u32(&header[2..]) };
+    let payload_offset = offset + header_size;
+
+    Ok(Packet { payload_offset, size, granule, next_offset: payload_offset + size })
+}
+
+/// Converts the Wwise Vorbis of `wem` to an Ogg Vorbis file.
+pub(crate) fn convert(wem: &FWem, codebooks: CodebookSource) -> Result<Vec<u8>> {
+    let data = wem.data.as_slice();
+    let vorbis = wem.vorbis.as_ref().ok_or_else(|| ParserError::new("Not a Wwise Vorbis file"))?;
+    if vorbis.header_triad_present {
+        return Err(Box::new(ParserError::new("Wwise Vorbis with the full Vorbis headers (before Wwise 2010) isn't supported")));
+    }
+
+    let channels = u32::from(wem.channels);
+    let mut output = OggWriter::new();
+
+    output.write_packet_header(1)?;
+    output.write(0, 32)?;
+    output.write(channels, 8)?;
+    output.write(wem.sample_rate, 32)?;
+    output.write(0, 32)?;
+    output.write(wem.avg_bytes_per_second.wrapping_mul(8), 32)?;
+    output.write(0, 32)?;
+    output.write(u32::from(vorbis.blocksize_0_pow), 4)?;
+    output.write(u32::from(vorbis.blocksize_1_pow), 4)?;
+    output.write(1, 1)?;
+    output.flush_page(false)?;
+
+    output.write_packet_header(3)?;
+    output.write(u32::try_from(VENDOR.len())?, 32)?;
+    for byte in VENDOR.bytes() {
+        output.write(u32::from(byte), 8)?;
+    }
+    output.write(0, 32)?;
+    output.write(1, 1)?;
+    output.flush_page(false)?;
+
+    let data_end = wem.data_offset + wem.data_size;
+    let setup = read_packet(wem, vorbis, wem.data_offset + vorbis.setup_packet_offset)?;
+    if setup.granule != 0 {
+        return Err(Box::new(ParserError::new("Setup packet granule is not 0")));
+    }
+
+    let setup_data = data.get(setup.payload_offset..setup.next_offset).ok_or_else(|| ParserError::new("Setup packet is truncated"))?;
+    let mut input = BitReader::new(setup_data);
+    output.write_packet_header(5)?;
+    let codebook_count = output.copy(&mut input, 8)? + 1;
+    for _ in 0..codebook_count {
+        match codebooks {
+            CodebookSource::External(library) => {
+                let id = input.read(10)?;
+                let codebook = library.codebook(id).ok_or_else(|| ParserError::new(&format!("Codebook {} is not in the library", id)))?;
+                rebuild_codebook(&mut BitReader::new(codebook), &mut output, Some(codebook.len()))?;
+            },
+            CodebookSource::InlinePacked => rebuild_codebook(&mut input, &mut output, None)?,
+            CodebookSource::InlineFull => copy_codebook(&mut input, &mut output)?
+        }
+    }
+
+    // The time domain transforms, placeholders.
+    output.write(0, 6)?;
+    output.write(0, 16)?;
+
+    let modes = match codebooks {
+        CodebookSource::InlineFull => {
+            while input.bits_read() < setup.size * 8 {
+                output.copy(&mut input, 1)?;
+            }
+            None
+        },
+        _ => Some(rebuild_setup(&mut input, &mut output, channels, codebook_count)?)
+    };
+    output.flush_page(false)?;
+
+    if input.bits_read().div_ceil(8) != setup.size {
+        return Err(Box::new(ParserError::new("Setup packet wasn't read exactly")));
+    }
+
+    if setup.next_offset != wem.data_offset + vorbis.first_audio_packet_offset {
+        return Err(Box::new(ParserError::new("First audio packet doesn't follow the setup packet")));
+    }
+
+    if vorbis.mod_packets && modes.is_none() {
+        return Err(Box::new(ParserError::new("Modified audio packets need a stripped setup packet")));
+    }
+
+    let blocksizes = [1u64 << vorbis.blocksize_0_pow, 1u64 << vorbis.blocksize_1_pow];
+    let mut previous_blockflag = false;
+    let mut previous_blocksize = None;
+    let mut granule = 0u64;
+    let mut offset = wem.data_offset + vorbis.first_audio_packet_offset;
+    while offset < data_end {
+        let packet = read_packet(wem, vorbis, offset)?;
+        let payload = data.get(packet.payload_offset..packet.next_offset).ok_or_else(|| ParserError::new("Audio packet is truncated"))?;
+        if payload.is_empty() {
+            offset = packet.next_offset;
+            continue;
+        }
+
+        let mut bits = BitReader::new(payload);
+        let mode = match (&modes, vorbis.mod_packets) {
+            (Some(modes), true) => {
+                output.write(0, 1)?;
+                let mode = output.copy(&mut bits, modes.bits)?;
+                let remainder = bits.read(8 - modes.bits)?;
+                let blockflag = *modes.blockflags.get(usize::try_from(mode)?).ok_or_else(|| ParserError::new("Invalid audio packet mode"))?;
+
+                if blockflag {
+                    let next_blockflag = next_blockflag(wem, vorbis, packet.next_offset, data_end, modes)?;
+                    output.write(u32::from(previous_blockflag), 1)?;
+                    output.write(u32::from(next_blockflag), 1)?;
+                }
+
+                previous_blockflag = blockflag;
+                output.write(remainder, 8 - modes.bits)?;
+                Some(mode)
+            },
+            (Some(modes), false) => {
+                bits.read(1)?;
+                let mode = bits.read(modes.bits)?;
+                output.write(u32::from(payload[0]), 8)?;
+                Some(mode)
+            },
+            (None, _) => {
+                output.write(u32::from(payload[0]), 8)?;
+                None
+            }
+        };
+
+        for byte in &payload[1..] {
+            output.write(u32::from(*byte), 8)?;
+        }
+
+        let last = packet.next_offset >= data_end;
+        output.granule = match (&modes, mode) {
+            (Some(modes), Some(mode)) => {
+                let blockflag = modes.blockflags.get(usize::try_from(mode)?).copied().unwrap_or(false);
+                let blocksize = blocksizes[usize::from(blockflag)];
+                if let Some(previous) = previous_blocksize {
+                    granule += (previous + blocksize) / 4;
+                }
+                previous_blocksize = Some(blocksize);
+
+                if last && vorbis.sample_count > 0 { granule.min(u64::from(vorbis.sample_count)) } else { granule }
+            },
+            _ if packet.granule == u32::MAX => 1,
+            _ => u64::from(packet.granule)
+        };
+
+        output.flush_page(last)?;
+        offset = packet.next_offset;
+    }
+
+    if offset > data_end {
+        return Err(Box::new(ParserError::new("Last audio packet is truncated")));
+    }
+
+    Ok(output.output)
+}
+
+/// Whether the packet at `offset` uses a long window, false at the end of the stream.
+fn next_blockflag(wem: &FWem, vorbis: &FWemVorbis, offset: usize, data_end: usize, modes: &Modes) -> Result<bool> {
+    if offset + packet_header_size(vorbis) > data_end {
+        return Ok(false);
+    }
+
+    let packet = read_packet(wem, vorbis, offset)?;
+    if packet.size == 0 {
+        return Ok(false);
+    }
+
+    let payload = wem.data.get(packet.payload_offset..packet.next_offset).ok_or_else(|| ParserError::new("Audio packet is truncated"))?;
+    let mode = BitReader::new(payload).read(modes.bits)?;
+
+    Ok(modes.blockflags.get(usize::try_from(mode)?).copied().unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The header flags, granule, segment table and data of every page, checking each checksum.
+    fn pages(output: &[u8]) -> Vec<(u8, u64, Vec<u8>, Vec<u8>)> {
+        let mut pages = vec![];
+        let mut offset = 0;
+        while offset < output.len() {
+            assert_eq!(&output[offset..offset + 4], b"OggS");
+            let count = usize::from(output[offset + 26]);
+            let segments = output[offset + OGG_HEADER_SIZE..offset + OGG_HEADER_SIZE + count].to_vec();
+            let size = OGG_HEADER_SIZE + count + segments.iter().map(|lacing| usize::from(*lacing)).sum::<usize>();
+
+            let mut page = output[offset..offset + size].to_vec();
+            let checksum = u32::from_le_bytes([page[22], page[23], page[24], page[25]]);
+            page[22..26].fill(0);
+            assert_eq!(ogg_checksum(&page), checksum);
+
+            let granule = u64::from_le_bytes(page[6..14].try_into().unwrap());
+            pages.push((page[5], granule, segments, page[OGG_HEADER_SIZE + count..].to_vec()));
+            offset += size;
+        }
+
+        pages
+    }
+
+    fn write_packet(packet: &[u8], granule: u64, last: bool) -> Vec<u8> {
+        let mut writer = OggWriter::new();
+        writer.payload.extend_from_slice(packet);
+        writer.granule = granule;
+        writer.flush_page(last).unwrap();
+
+        writer.output
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(ogg_checksum(b"123456789"), 0x89A1897F);
+        assert_eq!(ogg_checksum(&[0xFF]), 0xB1F740B4);
+        assert_eq!(ogg_checksum(&[]), 0);
+    }
+
+    #[test]
+    fn short_packet() {
+        let pages = pages(&write_packet(&[1, 2, 3], 42, true));
+
+        assert_eq!(pages, vec![(0x02 | 0x04, 42, vec![3], vec![1, 2, 3])]);
+    }
+
+    #[test]
+    fn bits_are_written_low_first() {
+        let mut writer = OggWriter::new();
+        writer.write(0b101, 3).unwrap();
+        writer.write(0x1F, 5).unwrap();
+        writer.write(1, 1).unwrap();
+        writer.flush_page(false).unwrap();
+
+        assert_eq!(pages(&writer.output)[0].3, vec![0b1111_1101, 0b1]);
+    }
+
+    #[test]
+    fn packet_on_segment_boundary() {
+        // A packet filling whole segments ends with an empty one.
+        let pages = pages(&write_packet(&[7; 765], 0, false));
+
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].2, vec![255, 255, 255, 0]);
+        assert_eq!(pages[0].3.len(), 765);
+    }
+
+    #[test]
+    fn packet_across_pages() {
+        let packet: Vec<u8> = (0..70000u32).map(|index| (index % 251) as u8).collect();
+        let pages = pages(&write_packet(&packet, 1234, true));
+
+        assert_eq!(pages.len(), 2);
+        let (flags, granule, segments, _) = &pages[0];
+        assert_eq!((*flags, *granule), (0x02, u64::MAX));
+        assert_eq!(segments, &vec![255; 255]);
+
+        let (flags, granule, segments, _) = &pages[1];
+        assert_eq!((*flags, *granule), (0x01 | 0x04, 1234));
+        assert_eq!(segments.len(), 20);
+        assert!(segments[..19].iter().all(|lacing| *lacing == 255));
+        assert_eq!(segments[19], 130);
+
+        assert_eq!([pages[0].3.as_slice(), pages[1].3.as_slice()].concat(), packet);
+    }
+
+    #[test]
+    fn full_page_packet() {
+        // 255 full segments leave the terminating empty segment for a continued page.
+        let pages = pages(&write_packet(&[9; 65025], 5, false));
+
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].2.len(), 255);
+        assert_eq!(pages[0].3.len(), 65025);
+        assert_eq!(pages[1], (0x01, 5, vec![0], vec![]));
+    }
+
+    #[test]
+    fn pages_are_numbered() {
+        let mut writer = OggWriter::new();
+        for packet in 0..3u32 {
+            writer.write(packet, 8).unwrap();
+            writer.flush_page(packet == 2).unwrap();
+        }
+
+        let sequences: Vec<u32> = (0..3).map(|page| {
+            let offset = page * (OGG_HEADER_SIZE + 2);
+            u32::from_le_bytes(writer.output[offset + 18..offset + 22].try_into().unwrap())
+        }).collect();
+        assert_eq!(sequences, vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,427 @@
+//! Wwise media files (`.wem`), the RIFF files banks embed or stream. Wwise Vorbis is rebuilt
+//! to Ogg Vorbis, PCM and Wwise IMA ADPCM are written as PCM WAV.
+
+use crate::vorbis::{self, CodebookSource};
+use crate::{Result, ParserError};
+
+pub const WAVE_FORMAT_PCM: u16 = 0x0001;
+pub const WAVE_FORMAT_WWISE_IMA: u16 = 0x0002;
+pub const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+pub const WAVE_FORMAT_WWISE_VORBIS: u16 = 0xFFFF;
+
+/// The `fmt ` chunk size of Wwise Vorbis files without a `vorb` chunk, which is then inside it.
+const VORBIS_FMT_SIZE: usize = 0x42;
+const VORBIS_FMT_VORB_OFFSET: usize = 0x18;
+const IMA_HEADER_SIZE: usize = 4;
+
+const IMA_INDEX_TABLE: [i32; 8] = [-1, -1, -1, -1, 2, 4, 6, 8];
+const IMA_STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66, 73, 80, 88, 97, 107,
+    118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449, 494, 544, 598, 658, 724, 796, 876, 963,
+    1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272, 2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894,
+    6484, 7132, 7845, 8630, 9493, 10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794,
+    32767
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EAudioFormat {
+    Ogg,
+    Wav
+}
+
+impl EAudioFormat {
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            EAudioFormat::Ogg => "ogg",
+            EAudioFormat::Wav => "wav"
+        }
+    }
+
+}
+
+/// The Wwise Vorbis parameters of the `vorb` chunk, or of the end of the `fmt ` chunk.
+#[derive(Debug, Clone)]
+pub struct FWemVorbis {
+    pub sample_count: u32,
+    /// Whether the audio packets lost their packet type and window bits.
+    pub mod_packets: bool,
+    /// Whether the audio packet headers lack a granule position.
+    pub no_granule: bool,
+    /// Whether the file keeps the three Vorbis headers, as the earliest versions do.
+    pub header_triad_present: bool,
+    /// Offsets of the setup and first audio packets in the `data` chunk.
+    pub setup_packet_offset: usize,
+    pub first_audio_packet_offset: usize,
+    pub blocksize_0_pow: u8,
+    pub blocksize_1_pow: u8
+}
+
+/// The Vorbis codebooks Wwise leaves out of files, referenced by ID. The library files of
+/// `ww2ogg` are the codebooks in their packed form followed by a table of their offsets, the
+/// last four bytes giving where the table starts.
+#[derive(Debug, Clone)]
+pub struct CodebookLibrary {
+    data: Vec<u8>,
+    offsets: Vec<usize>
+}
+
+impl CodebookLibrary {
+
+    pub fn from_bytes(mut data: Vec<u8>) -> Result<Self> {
+        let table_offset = match data.len().checked_sub(4) {
+            Some(end) => usize::try_from(read_u32(&data, end, false)?)?,
+            None => return Err(Box::new(ParserError::new("Codebook library is too short")))
+        };
+
+        if table_offset > data.len() - 4 {
+            return Err(Box::new(ParserError::new("Invalid codebook library offset table")));
+        }
+
+        let offsets = (table_offset..data.len()).step_by(4)
+            .map(|offset| Ok(usize::try_from(read_u32(&data, offset, false)?)?))
+            .collect::<Result<Vec<_>>>()?;
+        if offsets.iter().any(|offset| *offset > table_offset) || offsets.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(Box::new(ParserError::new("Invalid codebook library offset table")));
+        }
+
+        data.truncate(table_offset);
+        Ok(Self { data, offsets })
+    }
+
+    /// The packed codebook with the given ID.
+    pub fn codebook(&self, id: u32) -> Option<&[u8]> {
+        let id = usize::try_from(id).ok()?;
+        let (start, end) = (*self.offsets.get(id)?, *self.offsets.get(id + 1)?);
+
+        self.data.get(start..end)
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct FWem {
+    pub format: u16,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub avg_bytes_per_second: u32,
+    pub block_align: u16,
+    pub bits_per_sample: u16,
+    /// Whether the file is a big-endian `RIFX` one.
+    pub big_endian: bool,
+    /// The `data` chunk, as a range of `data`.
+    pub data_offset: usize,
+    pub data_size: usize,
+    pub vorbis: Option<FWemVorbis>,
+    /// The whole file.
+    pub data: Vec<u8>
+}
+
+impl FWem {
+
+    pub fn parse(data: Vec<u8>) -> Result<Self> {
+        let big_endian = match data.get(..4) {
+            Some(b"RIFF") => false,
+            Some(b"RIFX") => true,
+            _ => return Err(Box::new(ParserError::new("Not a RIFF file")))
+        };
+
+        if data.get(8..12) != Some(b"WAVE".as_slice()) {
+            return Err(Box::new(ParserError::new("Not a WAVE file")));
+        }
+
+        let riff_end = (usize::try_from(read_u32(&data, 4, big_endian)?)? + 8).min(data.len());
+        let mut fmt = None;
+        let mut vorb = None;
+        let mut data_chunk = None;
+        let mut offset = 12;
+        while offset + 8 <= riff_end {
+            let size = usize::try_from(read_u32(&data, offset + 4, big_endian)?)?;
+            let chunk = (offset + 8, size);
+            match &data[offset..offset + 4] {
+                b"fmt " => fmt = Some(chunk),
+                b"vorb" => vorb = Some(chunk),
+                b"data" => data_chunk = Some(chunk),
+                _ => {}
+            }
+
+            offset += 8 + size;
+        }
+
+        let (fmt_offset, fmt_size) = fmt.ok_or_else(|| ParserError::new("WAVE file has no fmt chunk"))?;
+        let (data_offset, data_size) = data_chunk.ok_or_else(|| ParserError::new("WAVE file has no data chunk"))?;
+        if fmt_size < 0x10 || data_offset + data_size > data.len() {
+            return Err(Box::new(ParserError::new("WAVE file is truncated")));
+        }
+
+        let format = read_u16(&data, fmt_offset, big_endian)?;
+        let vorbis = if format == WAVE_FORMAT_WWISE_VORBIS {
+            let vorb = match vorb {
+                Some((offset, size)) => (offset, Some(size)),
+                None if fmt_size == VORBIS_FMT_SIZE => (fmt_offset + VORBIS_FMT_VORB_OFFSET, None),
+                None => return Err(Box::new(ParserError::new("Wwise Vorbis file has no vorb chunk")))
+            };
+            Some(read_vorbis(&data, vorb, big_endian)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            format,
+            channels: read_u16(&data, fmt_offset + 2, big_endian)?,
+            sample_rate: read_u32(&data, fmt_offset + 4, big_endian)?,
+            avg_bytes_per_second: read_u32(&data, fmt_offset + 8, big_endian)?,
+            block_align: read_u16(&data, fmt_offset + 12, big_endian)?,
+            bits_per_sample: read_u16(&data, fmt_offset + 14, big_endian)?,
+            big_endian,
+            data_offset,
+            data_size,
+            vorbis,
+            data
+        })
+    }
+
+    /// Converts the file to a format players read: Ogg Vorbis for Wwise Vorbis, which needs
+    /// `codebooks` when the file references external codebooks, and PCM WAV otherwise.
+    pub fn to_audio(&self, codebooks: Option<&CodebookLibrary>) -> Result<(EAudioFormat, Vec<u8>)> {
+        if self.channels == 0 {
+            return Err(Box::new(ParserError::new("WAVE file has no channels")));
+        }
+
+        match self.format {
+            WAVE_FORMAT_WWISE_VORBIS => Ok((EAudioFormat::Ogg, self.to_ogg(codebooks)?)),
+            WAVE_FORMAT_PCM | WAVE_FORMAT_EXTENSIBLE => Ok((EAudioFormat::Wav, self.pcm_to_wav()?)),
+            WAVE_FORMAT_WWISE_IMA => Ok((EAudioFormat::Wav, self.ima_to_wav()?)),
+            format => Err(Box::new(ParserError::new(&format!("Unsupported Wwise codec 0x{:04X}", format))))
+        }
+    }
+
+    /// Tries the codebook sources in turn, as files don't tell which one they use: the rebuilt
+    /// setup packet must take exactly the bits of the original.
+    fn to_ogg(&self, codebooks: Option<&CodebookLibrary>) -> Result<Vec<u8>> {
+        let mut sources = vec![];
+        if let Some(library) = codebooks {
+            sources.push(CodebookSource::External(library));
+        }
+        sources.push(CodebookSource::InlinePacked);
+        sources.push(CodebookSource::InlineFull);
+
+        let mut first_error = None;
+        for source in sources {
+            match vorbis::convert(self, source) {
+                Ok(ogg) => return Ok(ogg),
+                Err(error) => { first_error.get_or_insert(error); }
+            }
+        }
+
+        match (first_error, codebooks) {
+            (Some(error), None) => Err(Box::new(ParserError::new(&format!("{} (the file may use external codebooks, which need a codebook library)", error)))),
+            (Some(error), Some(_)) => Err(error),
+            (None, _) => Err(Box::new(ParserError::new("No codebook source")))
+        }
+    }
+
+    fn pcm_to_wav(&self) -> Result<Vec<u8>> {
+        if self.bits_per_sample != 8 && self.bits_per_sample != 16 && self.bits_per_sample != 24 && self.bits_per_sample != 32 {
+            return Err(Box::new(ParserError::new(&format!("Unsupported PCM sample size {}", self.bits_per_sample))));
+        }
+
+        let mut samples = self.data[self.data_offset..self.data_offset + self.data_size].to_vec();
+        let sample_size = usize::from(self.bits_per_sample / 8);
+        if self.big_endian && sample_size > 1 {
+            samples.chunks_exact_mut(sample_size).for_each(<[u8]>::reverse);
+        }
+
+        Ok(write_wav(self.channels, self.sample_rate, self.bits_per_sample, &samples))
+    }
+
+    /// Decodes Wwise IMA ADPCM. Each block holds a header per channel, then the nibbles of each
+    /// channel one after the other; the header sample is the first of the block and the last
+    /// nibble of a channel is unused, for an even number of samples.
+    fn ima_to_wav(&self) -> Result<Vec<u8>> {
+        let channels = usize::from(self.channels);
+        let block_size = usize::from(self.block_align);
+        if block_size <= IMA_HEADER_SIZE * channels || block_size % channels != 0 {
+            return Err(Box::new(ParserError::new(&format!("Invalid IMA ADPCM block size {}", block_size))));
+        }
+
+        let channel_data_size = block_size / channels - IMA_HEADER_SIZE;
+        let block_samples = channel_data_size * 2;
+        let mut samples = vec![];
+        for block in self.data[self.data_offset..self.data_offset + self.data_size].chunks_exact(block_size) {
+            let mut decoded = vec![0i16; block_samples * channels];
+            for channel in 0..channels {
+                let header = &block[channel * IMA_HEADER_SIZE..];
+                let mut predictor = i32::from(if self.big_endian { i16::from_be_bytes([header[0], header[1]]) } else { i16::from_le_bytes([header[0], header[1]]) });
+                let mut index = i32::from(header[2]).min(88);
+                decoded[channel] = i16::try_from(predictor)?;
+
+                let nibbles = &block[IMA_HEADER_SIZE * channels + channel * channel_data_size..][..channel_data_size];
+                for sample in 1..block_samples {
+                    let byte = nibbles[(sample - 1) / 2];
+                    let nibble = if (sample - 1) % 2 == 0 { byte & 0x0F } else { byte >> 4 };
+
+                    let step = IMA_STEP_TABLE[usize::try_from(index)?];
+                    let mut difference = step >> 3;
+                    if nibble & 1 != 0 { difference += step >> 2; }
+                    if nibble & 2 != 0 { difference += step >> 1; }
+                    if nibble & 4 != 0 { difference += step; }
+                    if nibble & 8 != 0 { difference = -difference; }
+
+                    predictor = (predictor + difference).clamp(i32::from(i16::MIN), i32::from(i16::MAX));
+                    index = (index + IMA_INDEX_TABLE[usize::from(nibble & 7)]).clamp(0, 88);
+                    decoded[sample * channels + channel] = i16::try_from(predictor)?;
+                }
+            }
+
+            samples.extend(decoded.iter().flat_map(|sample| sample.to_le_bytes()));
+        }
+
+        Ok(write_wav(self.channels, self.sample_rate, 16, &samples))
+    }
+
+}
+
+fn read_vorbis(data: &[u8], (offset, size): (usize, Option<usize>), big_endian: bool) -> Result<FWemVorbis> {
+    let read = |relative: usize| -> Result<usize> { Ok(usize::try_from(read_u32(data, offset + relative, big_endian)?)?) };
+    let byte = |relative: usize| data.get(offset + relative).copied().ok_or_else(|| ParserError::new("vorb chunk is truncated"));
+
+    // Sizes of the vorb chunk over the Wwise versions, None being the one merged into fmt.
+    let (no_granule, header_triad_present, packet_offsets) = match size {
+        None | Some(0x2A) => (true, false, 0x10),
+        Some(0x28) | Some(0x2C) => (false, true, 0x18),
+        Some(0x32) | Some(0x34) => (false, false, 0x18),
+        Some(size) => return Err(Box::new(ParserError::new(&format!("Unexpected vorb chunk size 0x{:X}", size))))
+    };
+
+    // The packets lose their type and window bits in most versions without granules, a few
+    // known values of this field telling the ones that keep them.
+    let mod_packets = no_granule && !matches!(read(0x4)?, 0x4A | 0x4B | 0x69 | 0x70);
+    let blocksizes = match size {
+        None | Some(0x2A) => 0x28,
+        Some(0x32) | Some(0x34) => 0x30,
+        _ => 0
+    };
+
+    Ok(FWemVorbis {
+        sample_count: read_u32(data, offset, big_endian)?,
+        mod_packets,
+        no_granule,
+        header_triad_present,
+        setup_packet_offset: read(packet_offsets)?,
+        first_audio_packet_offset: read(packet_offsets + 4)?,
+        blocksize_0_pow: if header_triad_present { 0 } else { byte(blocksizes)? },
+        blocksize_1_pow: if header_triad_present { 0 } else { byte(blocksizes + 1)? }
+    })
+}
+
+/// A canonical PCM WAV file.
+fn write_wav(channels: u16, sample_rate: u32, bits_per_sample: u16, samples: &[u8]) -> Vec<u8> {
+    let block_align = channels * (bits_per_sample / 8);
+    let size = u32::try_from(samples.len()).unwrap_or(u32::MAX);
+
+    let mut wav = Vec::with_capacity(44 + samples.len());
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&size.saturating_add(36).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&WAVE_FORMAT_PCM.to_le_bytes());
+    wav.extend_from_slice(&channels.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * u32::from(block_align)).to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&bits_per_sample.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&size.to_le_bytes());
+    wav.extend_from_slice(samples);
+
+    wav
+}
+
+fn read_u16(data: &[u8], offset: usize, big_endian: bool) -> Result<u16> {
+    let bytes = data.get(offset..offset + 2).ok_or_else(|| ParserError::new("RIFF file is truncated"))?;
+    let bytes = [bytes[0], bytes[1]];
+
+    Ok(if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+}
+
+fn read_u32(data: &[u8], offset: usize, big_endian: bool) -> Result<u32> {
+    let bytes = data.get(offset..offset + 4).ok_or_else(|| ParserError::new("RIFF file is truncated"))?;
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+
+    Ok(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ima(channels: u16, block_align: u16, blocks: &[u8]) -> FWem {
+        FWem {
+            format: WAVE_FORMAT_WWISE_IMA,
+            channels,
+            sample_rate: 48000,
+            avg_bytes_per_second: 0,
+            block_align,
+            bits_per_sample: 4,
+            big_endian: false,
+            data_offset: 0,
+            data_size: blocks.len(),
+            vorbis: None,
+            data: blocks.to_vec()
+        }
+    }
+
+    fn wav_samples(wav: &[u8]) -> Vec<i16> {
+        wav[44..].chunks_exact(2).map(|sample| i16::from_le_bytes([sample[0], sample[1]])).collect()
+    }
+
+    #[test]
+    fn ima_mono() {
+        // Predictor 0 at index 0, then the nibbles 4, 4, C, 0, 7, F, 1 and an unused one.
+        let wem = ima(1, 8, &[0, 0, 0, 0, 0x44, 0x0C, 0xF7, 0x01]);
+        let (format, wav) = wem.to_audio(None).unwrap();
+
+        assert!(matches!(format, EAudioFormat::Wav));
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(u16::from_le_bytes([wav[20], wav[21]]), WAVE_FORMAT_PCM);
+        assert_eq!(u16::from_le_bytes([wav[34], wav[35]]), 16);
+        assert_eq!(wav_samples(&wav), vec![0, 7, 17, 5, 6, 28, -18, 1]);
+    }
+
+    #[test]
+    fn ima_stereo_interleaves() {
+        // Both headers come first, then the nibbles of the left channel and of the right one.
+        let block = [0, 0, 0, 0, 100, 0, 0, 0, 0x44, 0x0C, 0xF7, 0x01, 0, 0, 0, 0];
+        let wav = ima(2, 16, &block).ima_to_wav().unwrap();
+
+        let samples = wav_samples(&wav);
+        let left: Vec<i16> = samples.iter().step_by(2).copied().collect();
+        let right: Vec<i16> = samples.iter().skip(1).step_by(2).copied().collect();
+        assert_eq!(left, vec![0, 7, 17, 5, 6, 28, -18, 1]);
+        assert_eq!(right, vec![100; 8]);
+    }
+
+    #[test]
+    fn ima_clamps() {
+        // 32767 at index 88 can't go any higher.
+        let [low, high] = i16::MAX.to_le_bytes();
+        let wav = ima(1, 6, &[low, high, 88, 0, 0x77, 0]).ima_to_wav().unwrap();
+
+        assert_eq!(wav_samples(&wav), vec![i16::MAX; 4]);
+    }
+
+    #[test]
+    fn ima_invalid_block() {
+        assert!(ima(2, 8, &[0; 8]).ima_to_wav().is_err());
+        assert!(ima(2, 13, &[0; 13]).ima_to_wav().is_err());
+    }
+}
@@ -0,0 +1,359 @@
+//! Wwise audio. An `AkBank` export embeds its `.bnk` in a bulk data after its properties, and
+//! an `AkEvent` names a Wwise event, whose ID is the hash of its name. Banks are read for the
+//! media they embed (`DIDX`/`DATA`) and for the objects of their hierarchy (`HIRC`), which link
+//! events to the media they play.
+
+#![allow(non_upper_case_globals)]
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek, SeekFrom};
+
+use serde_json::{Value, json};
+
+use crate::archive::{FArchive, FByteArchive};
+use crate::bulkdata::{EBulkDataLocation, FUntypedBulkData};
+use crate::file::GameFile;
+use crate::package::UnPackage;
+use crate::properties::{FPropertyTag, find_property, read_object_properties};
+use crate::script::object_key;
+use crate::{Result, ParserError};
+
+pub const HIRC_Sound: u8 = 2;
+pub const HIRC_Action: u8 = 3;
+pub const HIRC_Event: u8 = 4;
+pub const HIRC_RandomSequenceContainer: u8 = 5;
+pub const HIRC_SwitchContainer: u8 = 6;
+pub const HIRC_ActorMixer: u8 = 7;
+pub const HIRC_LayerContainer: u8 = 9;
+
+/// The bank versions whose hierarchy layout is known, Wwise 2013 to 2019.
+const MIN_HIERARCHY_VERSION: u32 = 89;
+const MAX_HIERARCHY_VERSION: u32 = 135;
+/// Event action lists have a variable-length count from this version on.
+const VARIABLE_COUNT_VERSION: u32 = 123;
+
+const FNV_OFFSET_BASIS: u32 = 2166136261;
+const FNV_PRIME: u32 = 16777619;
+const MAX_HIERARCHY_DEPTH: usize = 32;
+
+/// The ID Wwise gives to a named object, the FNV-1 hash of its lowercase name.
+pub fn wwise_hash(name: &str) -> u32 {
+    name.to_lowercase().bytes().fold(FNV_OFFSET_BASIS, |hash, byte| hash.wrapping_mul(FNV_PRIME) ^ u32::from(byte))
+}
+
+#[derive(Debug, Clone)]
+pub struct UAkBank {
+    pub properties: Vec<FPropertyTag>,
+    /// The `.bnk` file.
+    pub bulk: FUntypedBulkData
+}
+
+impl UAkBank {
+
+    /// Parses the serialized data of an `AkBank` export of `package`.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, data: Vec<u8>) -> Result<Self> {
+        let mut archive = package.object_archive(data);
+        let properties = read_object_properties(&mut archive, &package.names)?;
+        if archive.seek(SeekFrom::Current(0))? >= archive.len() as u64 {
+            return Err(Box::new(ParserError::new("AkBank has no embedded bank")));
+        }
+
+        let bulk = FUntypedBulkData::serialize(&mut archive)?;
+        Ok(Self { properties, bulk })
+    }
+
+    /// Reads the bank when it's stored at the end of the package, `reader` being the package's reader.
+    pub fn load_package_data<R: Read + Seek>(&mut self, reader: &mut R, big_endian: bool) -> Result<()> {
+        if self.bulk.location() == EBulkDataLocation::EndOfPackage && !self.bulk.is_loaded() {
+            self.bulk.load_from(reader, big_endian)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.bulk.data
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct UAkEvent {
+    pub properties: Vec<FPropertyTag>,
+    pub name: String,
+    /// The `AkBank` holding the event, as the lowercase `package.path` it's found by from any package.
+    pub required_bank: Option<String>
+}
+
+impl UAkEvent {
+
+    /// Parses the serialized data of the `AkEvent` export `name` of `package`, whose file name
+    /// without extension is `package_name`.
+    pub fn read<F: GameFile>(package: &UnPackage<F>, package_name: &str, name: &str, data: Vec<u8>) -> Result<Self> {
+        let properties = read_object_properties(&mut package.object_archive(data), &package.names)?;
+        let required_bank = find_property(&properties, "RequiredBank").and_then(FPropertyTag::as_object)
+            .and_then(|bank| object_key(package, package_name, bank));
+
+        Ok(Self { properties, name: name.to_string(), required_bank })
+    }
+
+    /// The ID of the event in the banks.
+    pub fn id(&self) -> u32 {
+        wwise_hash(&self.name)
+    }
+
+}
+
+/// A media file embedded in a bank, usually a `.wem`.
+#[derive(Debug, Clone)]
+pub struct FBankMedia {
+    pub id: u32,
+    pub data: Vec<u8>
+}
+
+/// An object of the bank's hierarchy, its data starting after its ID.
+#[derive(Debug, Clone)]
+pub struct FHircObject {
+    pub kind: u8,
+    pub data: Vec<u8>
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FSoundBank {
+    pub version: u32,
+    pub id: u32,
+    pub media: Vec<FBankMedia>,
+    pub objects: HashMap<u32, FHircObject>
+}
+
+impl FSoundBank {
+
+    /// Parses the sections of a `.bnk` file, the unknown ones are skipped.
+    pub fn parse(data: &[u8], big_endian: bool) -> Result<Self> {
+        let mut archive = FByteArchive::new(data.to_vec());
+        archive.set_big_endian(big_endian);
+
+        let mut bank = Self::default();
+        let mut index = vec![];
+        let mut media_data = None;
+        while archive.seek(SeekFrom::Current(0))? + 8 <= data.len() as u64 {
+            let mut tag = [0u8; 4];
+            archive.read_bytes(&mut tag)?;
+            let size = usize::try_from(archive.read_u32()?)?;
+            let start = usize::try_from(archive.seek(SeekFrom::Current(0))?)?;
+            let section = data.get(start..start + size).ok_or_else(|| ParserError::new(&format!("Bank section {} is truncated", String::from_utf8_lossy(&tag))))?;
+
+            match &tag {
+                b"BKHD" => {
+                    bank.version = archive.read_u32()?;
+                    bank.id = archive.read_u32()?;
+                },
+                b"DIDX" => {
+                    for _ in 0..size / 12 {
+                        index.push((archive.read_u32()?, archive.read_u32()?, archive.read_u32()?));
+                    }
+                },
+                b"DATA" => media_data = Some(section),
+                b"HIRC" => bank.objects = read_hierarchy(&mut archive)?,
+                _ => {}
+            }
+
+            archive.seek(SeekFrom::Start(u64::try_from(start + size)?))?;
+        }
+
+        if let Some(media_data) = media_data {
+            for (id, offset, size) in index {
+                let (offset, size) = (usize::try_from(offset)?, usize::try_from(size)?);
+                let data = media_data.get(offset..offset + size).ok_or_else(|| ParserError::new(&format!("Media {} is out of the DATA section", id)))?;
+                bank.media.push(FBankMedia { id, data: data.to_vec() });
+            }
+        }
+
+        Ok(bank)
+    }
+
+    /// The IDs of the media the event plays, through its actions and the containers they
+    /// target. Only the bank versions whose hierarchy layout is known can be followed.
+    pub fn event_media(&self, event_id: u32, big_endian: bool) -> Result<Vec<u32>> {
+        self.check_hierarchy_version()?;
+        self.played_media(event_id, &self.children(big_endian), big_endian)
+    }
+
+    fn check_hierarchy_version(&self) -> Result<()> {
+        if !(MIN_HIERARCHY_VERSION..=MAX_HIERARCHY_VERSION).contains(&self.version) {
+            return Err(Box::new(ParserError::new(&format!("Unsupported bank version {} for the hierarchy", self.version))));
+        }
+
+        Ok(())
+    }
+
+    fn played_media(&self, event_id: u32, children: &HashMap<u32, Vec<u32>>, big_endian: bool) -> Result<Vec<u32>> {
+        let event = match self.objects.get(&event_id) {
+            Some(event) if event.kind == HIRC_Event => event,
+            _ => return Ok(vec![])
+        };
+
+        let mut archive = self.object_archive(event, big_endian);
+        let count = if self.version >= VARIABLE_COUNT_VERSION { read_variable_count(&mut archive)? } else { archive.read_u32()? };
+
+        let mut media = vec![];
+        for _ in 0..count {
+            let action = match self.objects.get(&archive.read_u32()?) {
+                Some(action) if action.kind == HIRC_Action => action,
+                _ => continue
+            };
+
+            let mut action = self.object_archive(action, big_endian);
+            let _action_type = action.read_u16()?;
+            let target = action.read_u32()?;
+            self.collect_media(target, children, big_endian, &mut media, 0)?;
+        }
+
+        media.sort_unstable();
+        media.dedup();
+        Ok(media)
+    }
+
+    /// A listing of the bank: its media with the events playing them, and the events found in
+    /// the bank with the media they play. Events are `null` when the hierarchy can't be followed.
+    pub fn to_json(&self, events: &[UAkEvent], big_endian: bool) -> Value {
+        let supported = self.check_hierarchy_version().is_ok();
+        let children = if supported { self.children(big_endian) } else { HashMap::new() };
+        let mut seen = HashSet::new();
+        let mut media_events: HashMap<u32, Vec<&str>> = HashMap::new();
+        let mut event_listing = vec![];
+        for event in events.iter().filter(|event| supported && seen.insert(event.id())) {
+            if !matches!(self.objects.get(&event.id()), Some(object) if object.kind == HIRC_Event) {
+                continue;
+            }
+
+            let media = match self.played_media(event.id(), &children, big_endian) {
+                Ok(media) => media,
+                Err(_) => continue
+            };
+
+            for id in &media {
+                media_events.entry(*id).or_default().push(&event.name);
+            }
+            event_listing.push(json!({ "name": event.name, "id": event.id(), "media": media }));
+        }
+
+        let media: Vec<Value> = self.media.iter().map(|media| json!({
+            "id": media.id,
+            "size": media.data.len(),
+            "events": if supported { json!(media_events.get(&media.id).cloned().unwrap_or_default()) } else { Value::Null }
+        })).collect();
+
+        json!({
+            "id": self.id,
+            "version": self.version,
+            "media": media,
+            "events": if supported { json!(event_listing) } else { Value::Null }
+        })
+    }
+
+    fn collect_media(&self, id: u32, children: &HashMap<u32, Vec<u32>>, big_endian: bool, media: &mut Vec<u32>, depth: usize) -> Result<()> {
+        let object = match self.objects.get(&id) {
+            Some(object) if depth < MAX_HIERARCHY_DEPTH => object,
+            _ => return Ok(())
+        };
+
+        if object.kind == HIRC_Sound {
+            let mut archive = self.object_archive(object, big_endian);
+            let _plugin = archive.read_u32()?;
+            let _stream_type = archive.read_u8()?;
+            media.push(archive.read_u32()?);
+            return Ok(());
+        }
+
+        for child in children.get(&id).into_iter().flatten() {
+            self.collect_media(*child, children, big_endian, media, depth + 1)?;
+        }
+
+        Ok(())
+    }
+
+    /// The children of the containers, from the parent IDs of the sounds and containers.
+    fn children(&self, big_endian: bool) -> HashMap<u32, Vec<u32>> {
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for (id, object) in &self.objects {
+            if let Some(parent) = self.parent(object, big_endian).filter(|parent| *parent != 0) {
+                children.entry(parent).or_default().push(*id);
+            }
+        }
+
+        for ids in children.values_mut() {
+            ids.sort_unstable();
+        }
+
+        children
+    }
+
+    /// The `DirectParentID` of the node base parameters of sounds and containers.
+    fn parent(&self, object: &FHircObject, big_endian: bool) -> Option<u32> {
+        let mut archive = self.object_archive(object, big_endian);
+        match object.kind {
+            HIRC_Sound => {
+                let plugin = archive.read_u32().ok()?;
+                archive.seek(SeekFrom::Current(1 + 4 + 4 + 1)).ok()?;
+                // Source plugins are followed by their parameters.
+                if plugin & 0x0F == 2 {
+                    let size = archive.read_u32().ok()?;
+                    archive.seek(SeekFrom::Current(i64::from(size))).ok()?;
+                }
+            },
+            HIRC_RandomSequenceContainer | HIRC_SwitchContainer | HIRC_ActorMixer | HIRC_LayerContainer => {},
+            _ => return None
+        }
+
+        let _override_parent_fx = archive.read_u8().ok()?;
+        let effects = archive.read_u8().ok()?;
+        if effects > 0 {
+            archive.seek(SeekFrom::Current(1 + 7 * i64::from(effects))).ok()?;
+        }
+
+        let _override_bus = archive.read_u32().ok()?;
+        archive.read_u32().ok()
+    }
+
+    fn object_archive(&self, object: &FHircObject, big_endian: bool) -> FByteArchive {
+        let mut archive = FByteArchive::new(object.data.clone());
+        archive.set_big_endian(big_endian);
+
+        archive
+    }
+
+}
+
+fn read_hierarchy(archive: &mut FByteArchive) -> Result<HashMap<u32, FHircObject>> {
+    let count = archive.read_u32()?;
+    let mut objects = HashMap::new();
+    for _ in 0..count {
+        let kind = archive.read_u8()?;
+        let size = usize::try_from(archive.read_u32()?)?;
+        if size < 4 {
+            return Err(Box::new(ParserError::new(&format!("Invalid hierarchy object size {}", size))));
+        }
+
+        let id = archive.read_u32()?;
+        let mut data = vec![0u8; size - 4];
+        archive.read_bytes_vec(&mut data)?;
+        objects.insert(id, FHircObject { kind, data });
+    }
+
+    Ok(objects)
+}
+
+/// A count stored 7 bits per byte, the high bit flagging another byte.
+fn read_variable_count(archive: &mut FByteArchive) -> Result<u32> {
+    let mut value = 0u32;
+    for _ in 0..5 {
+        let byte = archive.read_u8()?;
+        value = (value << 7) | u32::from(byte & 0x7F);
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(Box::new(ParserError::new("Invalid variable-length count")))
+}
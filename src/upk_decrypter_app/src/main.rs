@@ -1,22 +1,41 @@
-use clap::{ArgEnum, ArgMatches, Command, command, arg};
+use clap::{Arg, ArgEnum, ArgMatches, Command, command, arg};
 use simple_logger::SimpleLogger;
 use stopwatch::Stopwatch;
 use threadpool::ThreadPool;
 
-use core::panic;
-use std::io::{BufReader, BufRead};
-use std::path::Path;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::io::{BufReader, BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::fs::File;
 
 use upk_decrypter::{DefaultFileProvider, FileProvider};
 use upk_decrypter::encryption::FAesKey;
-use upk_decrypter::file::GameFile;
+use upk_decrypter::file::{GameFile, OsGameFile};
+use upk_decrypter::package::{OutputMode, UnPackage};
+use upk_decrypter::reader::FPackageReader;
+use upk_decrypter::staticmesh::UStaticMesh;
+use upk_decrypter::skeletalmesh::USkeletalMesh;
+use upk_decrypter::animation::{UAnimSequence, UAnimSet};
+use upk_decrypter::collision::build_level_collision;
+use upk_decrypter::car::{FCarArchetype, read_car_archetypes};
+use upk_decrypter::swf::USwfMovie;
+use upk_decrypter::wwise::{FSoundBank, UAkBank, UAkEvent};
+use upk_decrypter::wem::{CodebookLibrary, FWem};
+use upk_decrypter::script::object_key;
+use upk_decrypter::disassembler::{FNativeTable, function_to_string, state_to_string};
+use upk_decrypter::sdk::{FSdk, FSdkPackage, read_known_sizes};
+use upk_decrypter::hierarchy::{FClassTree, read_classes};
+use upk_decrypter::product::{read_products, normalize_products, write_products_csv, write_products_json};
+use upk_decrypter::texture::{UTexture2D, UTextureCube};
 use upk_decrypter::Result;
 
 mod epic;
 use epic::find_rocketleague_dir;
 
+/// The packages declaring the native functions bytecode calls by index, operators included.
+const NATIVE_PACKAGES: [&str; 2] = ["Core", "Engine"];
+
 #[derive(Debug, Copy, Clone, ArgEnum, PartialEq)]
 enum FileProviderType {
     Files,
@@ -31,36 +50,850 @@ impl FileProviderType {
 
 }
 
-impl std::str::FromStr for FileProviderType {
-    type Err = String;
+#[derive(Debug, Copy, Clone, ArgEnum, PartialEq)]
+enum TextureFormat {
+    Png,
+    Dds
+}
+
+impl TextureFormat {
 
-    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        for variant in Self::value_variants() {
-            if variant.to_possible_value().unwrap().matches(s, true) {
-                return Ok(*variant);
-            }
+    pub fn extension(self) -> &'static str {
+        match self {
+            TextureFormat::Png => "png",
+            TextureFormat::Dds => "dds"
+        }
+    }
+
+}
+
+#[derive(Debug, Copy, Clone, ArgEnum, PartialEq)]
+enum CollisionFormat {
+    Obj,
+    Bin
+}
+
+impl CollisionFormat {
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            CollisionFormat::Obj => "obj",
+            CollisionFormat::Bin => "bin"
+        }
+    }
+
+}
+
+#[derive(Debug, Copy, Clone, ArgEnum, PartialEq)]
+enum DatabaseFormat {
+    Json,
+    Csv
+}
+
+impl DatabaseFormat {
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            DatabaseFormat::Json => "json",
+            DatabaseFormat::Csv => "csv"
         }
+    }
+
+}
+
+#[derive(Debug, Copy, Clone, ArgEnum, PartialEq)]
+enum SdkLanguage {
+    Rust,
+    Cpp
+}
+
+impl SdkLanguage {
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            SdkLanguage::Rust => "rs",
+            SdkLanguage::Cpp => "hpp"
+        }
+    }
 
-        Err(format!("Invalid variant: {}", s))
+}
+
+#[derive(Debug, Copy, Clone, ArgEnum, PartialEq)]
+enum HierarchyFormat {
+    Tree,
+    Json
+}
+
+impl HierarchyFormat {
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            HierarchyFormat::Tree => "txt",
+            HierarchyFormat::Json => "json"
+        }
     }
+
 }
 
 fn main() -> Result<()> {
     SimpleLogger::new().init()?;
     let matches = command!()
         .subcommand(get_decrypt_command())
+        .subcommand(get_extract_command())
+        .subcommand(get_mesh_command())
+        .subcommand(get_collision_command())
+        .subcommand(get_cars_command())
+        .subcommand(get_products_command())
+        .subcommand(get_movies_command())
+        .subcommand(get_banks_command())
+        .subcommand(get_audio_command())
+        .subcommand(get_script_command())
+        .subcommand(get_sdk_command())
+        .subcommand(get_classes_command())
         .get_matches();
 
+    let mut sw = Stopwatch::start_new();
     match matches.subcommand() {
         Some(("decrypt", sm)) => decrypt(sm)?,
+        Some(("extract", sm)) => extract(sm)?,
+        Some(("mesh", sm)) => mesh(sm)?,
+        Some(("collision", sm)) => collision(sm)?,
+        Some(("cars", sm)) => cars(sm)?,
+        Some(("products", sm)) => products(sm)?,
+        Some(("movies", sm)) => movies(sm)?,
+        Some(("banks", sm)) => banks(sm)?,
+        Some(("audio", sm)) => audio(sm)?,
+        Some(("script", sm)) => script(sm)?,
+        Some(("sdk", sm)) => sdk(sm)?,
+        Some(("classes", sm)) => classes(sm)?,
         _ => todo!(),
     }
+    sw.stop();
 
+    log::info!("Finished in {}ms", sw.elapsed().as_millis());
     Ok(())
 }
 
 fn decrypt(args: &ArgMatches) -> Result<()> {
-    let provider_type: FileProviderType = args.value_of_t("provider")?;
+    let mode = if args.is_present("uncompressed") {
+        OutputMode::Uncompressed
+    } else {
+        OutputMode::Raw
+    };
+
+    for_each_package(args, "*_T_SF.upk", move |provider, file| {
+        match provider.save_package_with_mode(file.get_filename().as_str(), mode)? {
+            package if package.encrypted => log::info!("saved package {}", file.file_name),
+            _ => log::info!("saved package {} (was not encrypted)", file.file_name)
+        }
+
+        Ok(())
+    })
+}
+
+fn extract(args: &ArgMatches) -> Result<()> {
+    let pattern: String = args.value_of_t("pattern")?;
+    let output = PathBuf::from(args.value_of_t::<String>("output")?);
+    let mip = args.value_of_t::<usize>("mip").ok();
+    let format: TextureFormat = arg_enum(args, "format")?;
+
+    for_each_package(args, &pattern, move |provider, file| {
+        let count = extract_textures(provider, file, &output, format, mip)?;
+        if count > 0 {
+            log::info!("extracted {} textures from {}", count, file.file_name);
+        }
+
+        Ok(())
+    })
+}
+
+fn mesh(args: &ArgMatches) -> Result<()> {
+    let pattern: String = args.value_of_t("pattern")?;
+    let output = PathBuf::from(args.value_of_t::<String>("output")?);
+    let lod: usize = args.value_of_t("lod")?;
+
+    for_each_package(args, &pattern, move |provider, file| {
+        let count = extract_meshes(provider, file, &output, lod)?;
+        if count > 0 {
+            log::info!("extracted {} meshes from {}", count, file.file_name);
+        }
+
+        Ok(())
+    })
+}
+
+fn collision(args: &ArgMatches) -> Result<()> {
+    let output = PathBuf::from(args.value_of_t::<String>("output")?);
+    let format: CollisionFormat = arg_enum(args, "format")?;
+    let map = args.value_of_t::<String>("map").ok();
+
+    // Every package is scanned so the meshes that maps import can be resolved, only the maps are built.
+    let built = Arc::new(Mutex::new(0usize));
+    let counter = built.clone();
+    let filter = map.clone();
+    for_each_package(args, "*.upk", move |provider, file| {
+        let name = file.file_name.strip_suffix(&format!(".{}", file.extension)).unwrap_or(&file.file_name);
+        let is_map = match &filter {
+            Some(map) => name.eq_ignore_ascii_case(map),
+            None => name.to_lowercase().ends_with("_p")
+        };
+
+        if is_map {
+            let triangles = save_collision(provider, file, &output.join(name).with_extension(format.extension()), format)?;
+            log::info!("wrote collision for {} ({} triangles)", name, triangles);
+            *counter.lock().unwrap() += 1;
+        }
+
+        Ok(())
+    })?;
+
+    if let Some(map) = map.filter(|_| *built.lock().unwrap() == 0) {
+        log::warn!("no map package named {} was found", map);
+    }
+
+    Ok(())
+}
+
+/// Writes the collision of a map package to `target`, returning how many triangles it has.
+fn save_collision(provider: &DefaultFileProvider, file: &OsGameFile, target: &Path, format: CollisionFormat) -> Result<usize> {
+    let mesh = build_level_collision(provider, &file.file_name)?;
+
+    let mut writer = BufWriter::new(File::create(target)?);
+    match format {
+        CollisionFormat::Obj => mesh.write_obj(&mut writer)?,
+        CollisionFormat::Bin => mesh.write_binary(&mut writer)?
+    }
+    writer.flush()?;
+
+    Ok(mesh.triangle_count())
+}
+
+fn cars(args: &ArgMatches) -> Result<()> {
+    let pattern: String = args.value_of_t("pattern")?;
+    let output = PathBuf::from(args.value_of_t::<String>("output")?);
+
+    for_each_package(args, &pattern, move |provider, file| {
+        let count = save_car_archetypes(provider, file, &output)?;
+        if count > 0 {
+            log::info!("wrote {} car archetypes from {}", count, file.file_name);
+        }
+
+        Ok(())
+    })
+}
+
+/// Writes the car archetypes of a package to `output/<package>.json`, returning how many were written.
+fn save_car_archetypes(provider: &DefaultFileProvider, file: &OsGameFile, output: &Path) -> Result<usize> {
+    let (package, mut reader) = provider.open_package_reader(&file.file_name)?;
+    let archetypes = read_car_archetypes(&package, &mut reader)?;
+    if archetypes.is_empty() {
+        return Ok(0);
+    }
+
+    let package_name = file.file_name.strip_suffix(&format!(".{}", file.extension)).unwrap_or(&file.file_name);
+    let document: Vec<_> = archetypes.iter().map(FCarArchetype::to_json).collect();
+    let mut writer = BufWriter::new(File::create(output.join(package_name).with_extension("json"))?);
+    serde_json::to_writer_pretty(&mut writer, &document)?;
+    writer.flush()?;
+
+    Ok(archetypes.len())
+}
+
+fn products(args: &ArgMatches) -> Result<()> {
+    let pattern: String = args.value_of_t("pattern")?;
+    let output = PathBuf::from(args.value_of_t::<String>("output")?);
+    let format: DatabaseFormat = arg_enum(args, "format")?;
+
+    let products = Arc::new(Mutex::new(Vec::new()));
+    let found_products = products.clone();
+    for_each_package(args, &pattern, move |provider, file| {
+        let (package, mut reader) = provider.open_package_reader(&file.file_name)?;
+        let found = read_products(&package, &mut reader)?;
+        if !found.is_empty() {
+            log::info!("found {} products in {}", found.len(), file.file_name);
+            found_products.lock().unwrap().extend(found);
+        }
+
+        Ok(())
+    })?;
+
+    let mut products = std::mem::take(&mut *products.lock().unwrap());
+    normalize_products(&mut products);
+
+    let target = output.join("products").with_extension(format.extension());
+    let mut writer = BufWriter::new(File::create(&target)?);
+    match format {
+        DatabaseFormat::Json => write_products_json(&products, &mut writer)?,
+        DatabaseFormat::Csv => write_products_csv(&products, &mut writer)?
+    }
+    writer.flush()?;
+
+    log::info!("wrote {} products to {}", products.len(), target.display());
+    Ok(())
+}
+
+fn movies(args: &ArgMatches) -> Result<()> {
+    let pattern: String = args.value_of_t("pattern")?;
+    let output = PathBuf::from(args.value_of_t::<String>("output")?);
+
+    for_each_package(args, &pattern, move |provider, file| {
+        let count = extract_movies(provider, file, &output)?;
+        if count > 0 {
+            log::info!("extracted {} movies from {}", count, file.file_name);
+        }
+
+        Ok(())
+    })
+}
+
+/// Writes every movie of a package to `output/<package>/<path>.gfx`, with a `.swf` copy and the
+/// imported textures listed in `<path>.textures.txt`. Returns how many movies were written.
+fn extract_movies(provider: &DefaultFileProvider, file: &OsGameFile, output: &Path) -> Result<usize> {
+    let (package, mut reader) = provider.open_package_reader(file.get_filename())?;
+    let package_name = file.file_name.trim_end_matches(&format!(".{}", file.extension)).to_owned();
+    let directory = output.join(&package_name);
+
+    let mut count = 0;
+    for (index, export) in package.exports.iter().enumerate() {
+        if !matches!(package.get_class_name(export).as_deref(), Some("SwfMovie" | "GFxMovieInfo")) {
+            continue;
+        }
+
+        let path = package.get_object_path(i32::try_from(index)? + 1).unwrap_or_else(|| format!("Movie_{}", index));
+        std::fs::create_dir_all(&directory)?;
+
+        let result = reader.read_export(export).and_then(|data| USwfMovie::read(&package, data)).and_then(|movie| {
+            std::fs::write(directory.join(format!("{}.gfx", path)), &movie.data)?;
+            std::fs::write(directory.join(format!("{}.swf", path)), movie.to_swf()?)?;
+
+            let textures = movie.referenced_textures(&package);
+            if !textures.is_empty() {
+                std::fs::write(directory.join(format!("{}.textures.txt", path)), textures.join("\n") + "\n")?;
+            }
+
+            Ok(())
+        });
+
+        match result {
+            Ok(()) => count += 1,
+            Err(err) => log::warn!("failed to extract movie {}.{}: {}", package_name, path, err)
+        }
+    }
+
+    Ok(count)
+}
+
+fn banks(args: &ArgMatches) -> Result<()> {
+    let pattern: String = args.value_of_t("pattern")?;
+    let output = PathBuf::from(args.value_of_t::<String>("output")?);
+
+    // Events are often in other packages than the bank holding them, so they're all read first.
+    let events = Arc::new(Mutex::new(HashMap::new()));
+    let found_events = events.clone();
+    for_each_package(args, &pattern, move |provider, file| {
+        let found = read_events(provider, file)?;
+        let mut events = found_events.lock().unwrap();
+        for event in found {
+            if let Some(bank) = event.required_bank.clone() {
+                events.entry(bank).or_insert_with(Vec::new).push(event);
+            }
+        }
+
+        Ok(())
+    })?;
+
+    // The packages finish in any order, sorting the events keeps the listings the same between runs.
+    let mut events: HashMap<String, Vec<UAkEvent>> = std::mem::take(&mut *events.lock().unwrap());
+    for bank_events in events.values_mut() {
+        bank_events.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    log::info!("found {} events in {} banks", events.values().map(Vec::len).sum::<usize>(), events.len());
+
+    let events = Arc::new(events);
+    for_each_package(args, &pattern, move |provider, file| {
+        let count = extract_banks(provider, file, &output, &events)?;
+        if count > 0 {
+            log::info!("extracted {} sound banks from {}", count, file.file_name);
+        }
+
+        Ok(())
+    })
+}
+
+/// Reads the `AkEvent`s of a package, skipping the ones that can't be read.
+fn read_events(provider: &DefaultFileProvider, file: &OsGameFile) -> Result<Vec<UAkEvent>> {
+    let (package, mut reader) = provider.open_package_reader(file.get_filename())?;
+    let package_name = file.file_name.trim_end_matches(&format!(".{}", file.extension)).to_owned();
+
+    let mut events = vec![];
+    for export in package.exports.iter().filter(|export| package.get_class_name(export).as_deref() == Some("AkEvent")) {
+        let name = package.get_name(&export.object_name).unwrap_or_default();
+        match reader.read_export(export).and_then(|data| UAkEvent::read(&package, &package_name, &name, data)) {
+            Ok(event) => events.push(event),
+            Err(err) => log::warn!("failed to read event {}.{}: {}", package_name, name, err)
+        }
+    }
+
+    Ok(events)
+}
+
+/// Writes every sound bank of a package to `output/<package>/<path>.bnk`, its media to
+/// `<path>/<id>.wem` and a listing of the media and the events playing them to `<path>.json`,
+/// the events coming from `events` by the bank they require. Returns how many banks were written.
+fn extract_banks(provider: &DefaultFileProvider, file: &OsGameFile, output: &Path, events: &HashMap<String, Vec<UAkEvent>>) -> Result<usize> {
+    let (package, mut reader) = provider.open_package_reader(file.get_filename())?;
+    let package_name = file.file_name.trim_end_matches(&format!(".{}", file.extension)).to_owned();
+    let directory = output.join(&package_name);
+
+    let mut count = 0;
+    for (index, export) in package.exports.iter().enumerate() {
+        if package.get_class_name(export).as_deref() != Some("AkBank") {
+            continue;
+        }
+
+        let path = package.get_object_path(i32::try_from(index)? + 1).unwrap_or_else(|| format!("Bank_{}", index));
+        std::fs::create_dir_all(&directory)?;
+
+        let result = reader.read_export(export).and_then(|data| UAkBank::read(&package, data)).and_then(|mut bank| {
+            bank.load_package_data(&mut reader, package.is_big_endian())?;
+            std::fs::write(directory.join(format!("{}.bnk", path)), bank.data())?;
+
+            let sound_bank = FSoundBank::parse(bank.data(), package.is_big_endian())?;
+            let media_directory = directory.join(&path);
+            if !sound_bank.media.is_empty() {
+                std::fs::create_dir_all(&media_directory)?;
+            }
+
+            for media in &sound_bank.media {
+                std::fs::write(media_directory.join(format!("{}.wem", media.id)), &media.data)?;
+            }
+
+            let bank_events = object_key(&package, &package_name, i32::try_from(index)? + 1).and_then(|key| events.get(&key));
+            let listing = sound_bank.to_json(bank_events.map_or(&[], Vec::as_slice), package.is_big_endian());
+            std::fs::write(directory.join(format!("{}.json", path)), serde_json::to_vec_pretty(&listing)?)?;
+
+            Ok(())
+        });
+
+        match result {
+            Ok(()) => count += 1,
+            Err(err) => log::warn!("failed to extract sound bank {}.{}: {}", package_name, path, err)
+        }
+    }
+
+    Ok(count)
+}
+
+fn audio(args: &ArgMatches) -> Result<()> {
+    let input = match args.value_of_t::<String>("input") {
+        Ok(input) => PathBuf::from(input),
+        Err(_) => PathBuf::from(find_rocketleague_dir()?)
+    };
+    let output = PathBuf::from(args.value_of_t::<String>("output")?);
+    let codebooks = match args.value_of_t::<String>("codebooks") {
+        Ok(path) => {
+            let library = CodebookLibrary::from_bytes(std::fs::read(&path)?)?;
+            log::info!("loaded {} codebooks from {}", library.len(), path);
+            Some(library)
+        },
+        Err(_) => None
+    };
+
+    let mut files = vec![];
+    find_wem_files(&input, &mut files)?;
+    log::info!("scanned directory {}, found {} files", input.display(), files.len());
+
+    let converted = Arc::new(Mutex::new(0usize));
+    let counter = converted.clone();
+    run_on_threads(args, files, move |file| {
+        let target = output.join(file.strip_prefix(&input).unwrap_or(&file));
+        match convert_wem(&file, &target, codebooks.as_ref()) {
+            Ok(()) => *counter.lock().unwrap() += 1,
+            Err(err) => log::warn!("failed to convert {}: {}", file.display(), err)
+        }
+    });
+
+    log::info!("converted {} media files", converted.lock().unwrap());
+    Ok(())
+}
+
+/// Collects the `.wem` files under `directory`, recursively.
+fn find_wem_files(directory: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_wem_files(&path, files)?;
+        } else if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("wem")) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a media file next to `target` as `.ogg` or `.wav`, depending on its codec.
+fn convert_wem(file: &Path, target: &Path, codebooks: Option<&CodebookLibrary>) -> Result<()> {
+    let wem = FWem::parse(std::fs::read(file)?)?;
+    let (format, audio) = wem.to_audio(codebooks)?;
+
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(target.with_extension(format.extension()), audio)?;
+
+    Ok(())
+}
+
+fn script(args: &ArgMatches) -> Result<()> {
+    let pattern: String = args.value_of_t("pattern")?;
+    let output = PathBuf::from(args.value_of_t::<String>("output")?);
+
+    // The native functions are declared by the core packages, whichever packages are disassembled.
+    let native_provider = create_provider(args, "*.upk")?;
+    let mut natives = FNativeTable::new();
+    for name in NATIVE_PACKAGES {
+        match native_provider.find_package(name) {
+            Some(file) => {
+                let (package, mut reader) = native_provider.open_package_reader(file.get_filename())?;
+                log::info!("loaded {} native functions from {}", natives.add_package(&package, &mut reader)?, file.file_name);
+            },
+            None => log::warn!("no package named {} was found, calls to its native functions will show as their index", name)
+        }
+    }
+
+    for_each_package(args, &pattern, move |provider, file| {
+        let count = disassemble_package(provider, &natives, file, &output)?;
+        if count > 0 {
+            log::info!("disassembled {} classes from {}", count, file.file_name);
+        }
+
+        Ok(())
+    })
+}
+
+/// Writes the pseudo-code of the functions and states of every class of a package to
+/// `output/<package>/<class>.uc`, the class's own state code first. Returns how many classes were written.
+fn disassemble_package(provider: &DefaultFileProvider, natives: &FNativeTable, file: &OsGameFile, output: &Path) -> Result<usize> {
+    let (package, mut reader) = provider.open_package_reader(file.get_filename())?;
+    let package_name = file.file_name.trim_end_matches(&format!(".{}", file.extension)).to_owned();
+    let directory = output.join(&package_name);
+
+    let mut classes: Vec<(i32, Vec<String>)> = vec![];
+    for (index, export) in package.exports.iter().enumerate() {
+        let index = i32::try_from(index)? + 1;
+        let class = package.get_class_name(export).unwrap_or_default();
+        let result = match class.as_str() {
+            "Function" => function_to_string(&package, &mut reader, natives, index),
+            "State" | "Class" => state_to_string(&package, &mut reader, natives, index),
+            _ => continue
+        };
+
+        let text = match result {
+            Ok(text) => text,
+            Err(err) => {
+                log::warn!("failed to disassemble {}.{}: {}", package_name, package.get_object_path(index).unwrap_or_default(), err);
+                continue;
+            }
+        };
+
+        // Everything goes to the class it's declared in, the outermost one.
+        let mut owner = index;
+        while let Some(outer) = usize::try_from(owner - 1).ok().and_then(|owner| package.exports.get(owner)).map(|export| export.outer_index).filter(|outer| *outer > 0) {
+            owner = outer;
+        }
+
+        match classes.iter_mut().find(|(class, _)| *class == owner) {
+            Some((_, texts)) if class == "Class" => texts.insert(0, text),
+            Some((_, texts)) => texts.push(text),
+            None => classes.push((owner, vec![text]))
+        }
+    }
+
+    for (class, texts) in &classes {
+        std::fs::create_dir_all(&directory)?;
+        let name = package.get_object_name(*class).unwrap_or_else(|| format!("Class_{}", class));
+        std::fs::write(directory.join(format!("{}.uc", name)), texts.join("\n"))?;
+    }
+
+    Ok(classes.len())
+}
+
+fn sdk(args: &ArgMatches) -> Result<()> {
+    let pattern: String = args.value_of_t("pattern")?;
+    let output = PathBuf::from(args.value_of_t::<String>("output")?);
+    let language: SdkLanguage = arg_enum(args, "language")?;
+
+    let packages = Arc::new(Mutex::new(Vec::new()));
+    let found_packages = packages.clone();
+    for_each_package(args, &pattern, move |provider, file| {
+        let package_name = file.file_name.trim_end_matches(&format!(".{}", file.extension)).to_owned();
+        let (package, mut reader) = provider.open_package_reader(&file.file_name)?;
+        let found = FSdkPackage::read(&package, &mut reader, &package_name)?;
+        if !found.is_empty() {
+            log::info!("found {} structs and {} enums in {}", found.structs.len(), found.enums.len(), file.file_name);
+            found_packages.lock().unwrap().push(found);
+        }
+
+        Ok(())
+    })?;
+
+    // The packages finish in any order, sorting them keeps the output the same between runs.
+    let mut packages = std::mem::take(&mut *packages.lock().unwrap());
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut sdk = FSdk::new();
+    for package in packages {
+        sdk.add_package(package);
+    }
+
+    if let Some(sizes) = args.value_of("sizes") {
+        let sizes = read_known_sizes(&std::fs::read_to_string(sizes)?)?;
+        log::info!("loaded {} known sizes", sizes.len());
+        sdk.set_known_sizes(sizes);
+    }
+
+    let missing = sdk.layout();
+    if let Some(first) = missing.first() {
+        log::warn!("skipped {} structs deriving from or embedding a struct of a package that wasn't loaded, like {}", missing.len(), first);
+    }
+
+    let unverified = sdk.structs().filter(|item| !item.is_verified).count();
+    if unverified > 0 {
+        log::warn!("the layouts of {} classes rest on native classes of unknown size, give their sizes with --sizes to verify them", unverified);
+    }
+
+    std::fs::create_dir_all(&output)?;
+    let target = output.join("sdk").with_extension(language.extension());
+    let mut writer = BufWriter::new(File::create(&target)?);
+    match language {
+        SdkLanguage::Rust => sdk.write_rust(&mut writer)?,
+        SdkLanguage::Cpp => sdk.write_cpp(&mut writer)?
+    }
+    writer.flush()?;
+
+    log::info!("wrote {} structs and {} enums to {}", sdk.structs().count(), sdk.enums().len(), target.display());
+    Ok(())
+}
+
+fn classes(args: &ArgMatches) -> Result<()> {
+    let pattern: String = args.value_of_t("pattern")?;
+    let output = PathBuf::from(args.value_of_t::<String>("output")?);
+    let format: HierarchyFormat = arg_enum(args, "format")?;
+    let root: Option<String> = args.value_of("class").map(String::from);
+
+    let packages = Arc::new(Mutex::new(Vec::new()));
+    let found_packages = packages.clone();
+    for_each_package(args, &pattern, move |provider, file| {
+        let package_name = file.file_name.trim_end_matches(&format!(".{}", file.extension)).to_owned();
+        let (package, mut reader) = provider.open_package_reader(&file.file_name)?;
+        let found = read_classes(&package, &mut reader, &package_name)?;
+        if !found.is_empty() {
+            log::info!("found {} classes in {}", found.len(), file.file_name);
+            found_packages.lock().unwrap().push((package_name, found));
+        }
+
+        Ok(())
+    })?;
+
+    // The packages finish in any order, sorting them keeps the output the same between runs.
+    let mut packages = std::mem::take(&mut *packages.lock().unwrap());
+    packages.sort_by(|a, b| a.0.cmp(&b.0));
+    let tree = FClassTree::new(packages.into_iter().flat_map(|(_, classes)| classes).collect());
+
+    let root = match root {
+        Some(name) => {
+            let index = tree.find(&name).ok_or_else(|| format!("No class named {} in the packages", name))?;
+            let ancestors: Vec<&str> = tree.ancestors(index).iter().map(|class| class.name.as_str()).collect();
+            if !ancestors.is_empty() {
+                log::info!("{} extends {}", tree.get(index).map_or(name.as_str(), |class| class.name.as_str()), ancestors.join(", "));
+            }
+            Some(index)
+        },
+        None => None
+    };
+
+    let target = output.join("classes").with_extension(format.extension());
+    let mut writer = BufWriter::new(File::create(&target)?);
+    match format {
+        HierarchyFormat::Tree => tree.write_tree(&mut writer, root, args.is_present("defaults"))?,
+        HierarchyFormat::Json => writeln!(writer, "{}", serde_json::to_string_pretty(&tree.to_json(root))?)?
+    }
+    writer.flush()?;
+
+    log::info!("wrote {} classes to {}", tree.len(), target.display());
+    Ok(())
+}
+
+/// Writes every static and skeletal mesh of a package to `output/<package>/<path>.glb`, returning how many were written.
+/// Skeletal meshes get the animations of the package's anim sets that move their bones.
+fn extract_meshes(provider: &DefaultFileProvider, file: &OsGameFile, output: &Path, lod: usize) -> Result<usize> {
+    let (package, mut reader) = provider.open_package_reader(file.get_filename())?;
+    let package_name = file.file_name.trim_end_matches(&format!(".{}", file.extension)).to_owned();
+    let directory = output.join(&package_name);
+    let animations = read_animations(&package, &mut reader, &package_name)?;
+
+    let mut count = 0;
+    for (index, export) in package.exports.iter().enumerate() {
+        let class_name = package.get_class_name(export);
+        if !matches!(class_name.as_deref(), Some("StaticMesh" | "SkeletalMesh")) {
+            continue;
+        }
+
+        let path = package.get_object_path(i32::try_from(index)? + 1).unwrap_or_else(|| format!("Mesh_{}", index));
+        let target = directory.join(format!("{}.glb", path));
+        std::fs::create_dir_all(&directory)?;
+
+        let result = reader.read_export(export).and_then(|data| if class_name.as_deref() == Some("StaticMesh") {
+            UStaticMesh::read(&package, data).and_then(|mesh| mesh.save_glb(&package, &target, lod))
+        } else {
+            USkeletalMesh::read(&package, data).and_then(|mesh| {
+                let animations: Vec<(&UAnimSet, &UAnimSequence)> = animations.iter()
+                    .filter(|(set, _)| set.track_bone_names.iter().any(|bone| mesh.find_bone(bone).is_some()))
+                    .flat_map(|(set, sequences)| sequences.iter().map(move |sequence| (set, sequence)))
+                    .collect();
+                mesh.save_glb(&package, &target, lod, &animations)
+            })
+        });
+
+        match result {
+            Ok(()) => count += 1,
+            Err(err) => log::warn!("failed to extract mesh {}.{}: {}", package_name, path, err)
+        }
+    }
+
+    Ok(count)
+}
+
+/// Reads the anim sets of a package along with their sequences, skipping the ones that can't be decoded.
+fn read_animations(package: &UnPackage<OsGameFile>, reader: &mut FPackageReader, package_name: &str) -> Result<Vec<(UAnimSet, Vec<UAnimSequence>)>> {
+    let mut animations = vec![];
+    for (index, export) in package.exports.iter().enumerate() {
+        if package.get_class_name(export).as_deref() != Some("AnimSet") {
+            continue;
+        }
+
+        let set = match reader.read_export(export).and_then(|data| UAnimSet::read(package, data)) {
+            Ok(set) => set,
+            Err(err) => {
+                log::warn!("failed to read anim set {}.{}: {}", package_name, package.get_object_path(i32::try_from(index)? + 1).unwrap_or_default(), err);
+                continue;
+            }
+        };
+
+        let mut sequences = vec![];
+        for sequence in &set.sequences {
+            let export = match usize::try_from(*sequence).ok().and_then(|sequence| sequence.checked_sub(1)).and_then(|sequence| package.exports.get(sequence)) {
+                Some(export) => export,
+                None => continue
+            };
+
+            match reader.read_export(export).and_then(|data| UAnimSequence::read(package, data)) {
+                Ok(sequence) => sequences.push(sequence),
+                Err(err) => log::warn!("failed to read animation {}.{}: {}", package_name, package.get_object_path(*sequence).unwrap_or_default(), err)
+            }
+        }
+
+        animations.push((set, sequences));
+    }
+
+    Ok(animations)
+}
+
+/// Writes every texture of a package to `output/<package>/<path>.<format>`, returning how many were written.
+/// Without `mip` the largest available mip is used. Cubemaps are only written as DDS.
+fn extract_textures(provider: &DefaultFileProvider, file: &OsGameFile, output: &Path, format: TextureFormat, mip: Option<usize>) -> Result<usize> {
+    let (package, mut reader) = provider.open_package_reader(file.get_filename())?;
+    let package_name = file.file_name.trim_end_matches(&format!(".{}", file.extension)).to_owned();
+    let directory = output.join(&package_name);
+
+    let mut count = 0;
+    for (index, export) in package.exports.iter().enumerate() {
+        let class_name = package.get_class_name(export);
+        let is_cube = match class_name.as_deref() {
+            Some("Texture2D") => false,
+            Some("TextureCube") if format == TextureFormat::Dds => true,
+            _ => continue
+        };
+
+        let path = package.get_object_path(i32::try_from(index)? + 1).unwrap_or_else(|| format!("Texture_{}", index));
+        let target = directory.join(format!("{}.{}", path, format.extension()));
+        let result = reader.read_export(export).and_then(|data| if is_cube {
+            save_cube(provider, &package, &mut reader, data, &target, mip)
+        } else {
+            save_texture(provider, &package, &mut reader, data, &target, format, mip)
+        });
+
+        match result {
+            Ok(()) => count += 1,
+            Err(err) => log::warn!("failed to extract texture {}.{}: {}", package_name, path, err)
+        }
+    }
+
+    Ok(count)
+}
+
+fn save_texture(provider: &DefaultFileProvider, package: &UnPackage<OsGameFile>, reader: &mut FPackageReader, data: Vec<u8>, target: &Path, format: TextureFormat, mip: Option<usize>) -> Result<()> {
+    let texture = read_texture(provider, package, reader, data)?;
+    let mip_index = mip.or_else(|| texture.first_available_mip()).ok_or("no mips are available")?;
+
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    match format {
+        TextureFormat::Png => texture.save_png(target, mip_index),
+        TextureFormat::Dds => texture.save_dds(target, mip_index)
+    }
+}
+
+/// Reads a texture along with the mips at the end of the package and in its texture file cache.
+/// A missing cache only leaves the streamed mips out, the ones in the package are still usable.
+fn read_texture(provider: &DefaultFileProvider, package: &UnPackage<OsGameFile>, reader: &mut FPackageReader, data: Vec<u8>) -> Result<UTexture2D> {
+    let mut texture = UTexture2D::read(package, data)?;
+    texture.load_package_mips(reader, package.is_big_endian())?;
+    if let Err(err) = provider.load_texture_mips(package, &mut texture) {
+        log::warn!("streamed mips skipped: {}", err);
+    }
+
+    Ok(texture)
+}
+
+fn save_cube(provider: &DefaultFileProvider, package: &UnPackage<OsGameFile>, reader: &mut FPackageReader, data: Vec<u8>, target: &Path, mip: Option<usize>) -> Result<()> {
+    let cube = UTextureCube::read(package, data)?;
+    let mut faces = Vec::with_capacity(cube.faces.len());
+    for (position, face) in cube.faces.iter().enumerate() {
+        let export = usize::try_from(*face).ok()
+            .and_then(|face| face.checked_sub(1))
+            .and_then(|face| package.exports.get(face))
+            .ok_or("a face of the cubemap is not in the package")?;
+
+        // Only this cubemap fails, the other textures of the package are still written.
+        let texture = reader.read_export(export).and_then(|data| read_texture(provider, package, reader, data))
+            .map_err(|err| format!("face {} ({}) can't be read: {}", position, package.get_object_path(*face).unwrap_or_default(), err))?;
+        faces.push(texture);
+    }
+
+    let mip_index = match mip {
+        Some(mip) => mip,
+        None => faces.iter().filter_map(UTexture2D::first_available_mip).max().ok_or("no mips are available")?
+    };
+
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    cube.save_dds(target, &faces, mip_index)
+}
+
+/// The value of an argument taking the variants of an `ArgEnum`, ignoring case.
+fn arg_enum<T: ArgEnum>(args: &ArgMatches, id: &str) -> Result<T> {
+    let value: String = args.value_of_t(id)?;
+    Ok(T::from_str(&value, true)?)
+}
+
+/// Sets up a provider with the input files and keys from the common arguments.
+fn create_provider(args: &ArgMatches, pattern: &str) -> Result<DefaultFileProvider> {
+    let provider_type: FileProviderType = arg_enum(args, "provider")?;
     assert!(provider_type.is_physical(), "StreamedFileProvider is currently not supported.");
 
     let output: String = args.value_of_t("output")?;
@@ -68,7 +901,10 @@ fn decrypt(args: &ArgMatches) -> Result<()> {
         std::fs::create_dir_all(&output)?;
     }
 
-    let input: String = args.value_of_t("input").unwrap_or(find_rocketleague_dir()?);
+    let input: String = match args.value_of_t("input") {
+        Ok(input) => input,
+        Err(_) => find_rocketleague_dir()?
+    };
     let keys: String = args.value_of_t("keys")?;
 
     log::info!("using encryption keys file: {}", &keys);
@@ -78,7 +914,7 @@ fn decrypt(args: &ArgMatches) -> Result<()> {
     }
     
     let mut file_provider = DefaultFileProvider::new(&output, &input);
-    let files_found = file_provider.scan_files_with_pattern("*_T_SF.upk")?;
+    let files_found = file_provider.scan_files_with_pattern(pattern)?;
     log::info!("scanned directory {}, found {} files", &input, files_found);
 
     let keys = load_aes_keys(&keys)?;
@@ -88,42 +924,64 @@ fn decrypt(args: &ArgMatches) -> Result<()> {
     }
     log::info!("loaded {} aes keys", num_keys);
 
-    let processors = match args.value_of_t::<usize>("threads") {
-        Ok(val) => val,
-        Err(_) => num_cpus::get(),
-    };
+    // Only decrypt inflates whole packages, the other commands read the chunks they need.
+    if args.is_valid_arg("chunk-threads") {
+        let chunk_threads = args.value_of_t::<usize>("chunk-threads").unwrap_or(1);
+        file_provider.set_decompression_threads(chunk_threads);
+        log::info!("decompressing with {} threads per package", chunk_threads);
+    }
+
+    Ok(file_provider)
+}
+
+/// Runs `process` on every package matching `pattern` on the thread pool, logging the packages
+/// it fails on. Returns once all of them are done.
+fn for_each_package<F>(args: &ArgMatches, pattern: &str, process: F) -> Result<()>
+where F: Fn(&DefaultFileProvider, &OsGameFile) -> Result<()> + Send + Sync + 'static {
+    let provider = create_provider(args, pattern)?;
+    let files = provider.files.clone();
+    run_on_threads(args, files, move |file| {
+        if let Err(err) = process(&provider, &file) {
+            log::warn!("failed to process package {}: {}", file.file_name, err);
+        }
+    });
 
+    Ok(())
+}
+
+/// Runs `process` on every item on a pool of `--threads` threads and waits for all of them.
+fn run_on_threads<T, F>(args: &ArgMatches, items: Vec<T>, process: F)
+where T: Send + 'static, F: Fn(T) + Send + Sync + 'static {
+    let processors = thread_count(args);
     let thread_pool = ThreadPool::new(processors);
     log::info!("running with {} threads", processors);
 
-    let mut sw = Stopwatch::start_new();
-    let files = file_provider.files.clone();
-    let arc = Arc::new(file_provider);
-    for file in files {
-        let provider = arc.clone();
-        thread_pool.execute(move || {
-            if provider.save_package(file.get_filename().as_str()).is_ok() {
-                log::info!("saved package {}", file.file_name);
-            }
-        });
+    let process = Arc::new(process);
+    for item in items {
+        let process = process.clone();
+        thread_pool.execute(move || process(item));
     }
 
     thread_pool.join();
-    sw.stop();
+}
 
-    log::info!("Finished in {}ms", sw.elapsed().as_millis());
-    Ok(())
+fn thread_count(args: &ArgMatches) -> usize {
+    match args.value_of_t::<usize>("threads") {
+        Ok(val) => val,
+        Err(_) => num_cpus::get(),
+    }
 }
 
-fn get_decrypt_command() -> Command<'static> {
-    Command::new("decrypt")
-    .about("Decrypts all the upk files in the input directory.")
+/// The arguments of every command reading packages: the input directory, the keys, the provider,
+/// the thread count and the output directory, which is `default_output` unless given.
+fn common_args(command: Command<'static>, default_output: &'static str, output_help: &'static str) -> Command<'static> {
+    command
     .arg(arg!(-i --input <INPUT>).id("input")
         .help("The input directory with all the upk files.")
         .required(false))
     .arg(arg!(-o --output <OUTPUT>).id("output")
-        .help("The output directory where all the decrypted files will be written to")
-        .default_value("./out")
+        .help(output_help)
+        .default_value(default_output)
         .required(false))
     .arg(arg!(-k --keys <KEYS>).id("keys")
         .help("The file with all the encryption keys")
@@ -135,7 +993,25 @@ fn get_decrypt_command() -> Command<'static> {
         .default_value("Files")
         .required(false))
     .arg(arg!(-t --threads <THREADS>).id("threads")
-        .help("The numbers of threads that will decrypt the packages")
+        .help("The numbers of threads that will process the packages")
+        .required(false))
+}
+
+fn pattern_arg(default_pattern: &'static str, help: &'static str) -> Arg<'static> {
+    arg!(--pattern <PATTERN>).id("pattern")
+        .help(help)
+        .default_value(default_pattern)
+        .required(false)
+}
+
+fn get_decrypt_command() -> Command<'static> {
+    common_args(Command::new("decrypt"), "./out", "The output directory where all the decrypted files will be written to")
+    .about("Decrypts all the upk files in the input directory.")
+    .arg(arg!(-c --"chunk-threads" <CHUNK_THREADS>).id("chunk-threads")
+        .help("The numbers of threads that decompress the blocks of a single package")
+        .required(false))
+    .arg(arg!(-u --uncompressed).id("uncompressed")
+        .help("Rewrite the package summary so the output opens as a plain uncompressed package in third-party tools")
         .required(false))
 }
 
@@ -143,7 +1019,6 @@ fn load_aes_keys(path: &str) -> Result<Vec<FAesKey>> {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
     let keys = reader.lines()
-        .into_iter()
         .map(std::result::Result::unwrap)
         .map(|line| FAesKey::from_base64(&line).unwrap())
         .collect();
@@ -157,4 +1032,127 @@ fn path_exists_validator(path: &str) -> std::result::Result<(), String> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+fn get_extract_command() -> Command<'static> {
+    common_args(Command::new("extract"), "./extracted", "The output directory, every package gets a folder with its textures")
+    .about("Extracts the textures of all the upk files in the input directory.")
+    .arg(pattern_arg("*.upk", "The packages to extract from"))
+    .arg(arg!(-f --format <FORMAT>).id("format")
+        .help("The format the textures are written in, DDS keeps the original compression, all the mips and cubemaps")
+        .possible_values(["png", "dds"])
+        .default_value("png")
+        .required(false))
+    .arg(arg!(-m --mip <MIP>).id("mip")
+        .help("The mip level to export, 0 is the largest. Defaults to the largest one available")
+        .required(false))
+}
+
+fn get_mesh_command() -> Command<'static> {
+    common_args(Command::new("mesh"), "./meshes", "The output directory, every package gets a folder with its meshes")
+    .about("Converts the static and skeletal meshes of all the upk files in the input directory to glTF, with the animations of their packages.")
+    .arg(pattern_arg("*.upk", "The packages to extract from"))
+    .arg(arg!(-l --lod <LOD>).id("lod")
+        .help("The level of detail to export, 0 is the most detailed")
+        .default_value("0")
+        .required(false))
+}
+
+fn get_collision_command() -> Command<'static> {
+    common_args(Command::new("collision"), "./collision", "The output directory, every map gets a file")
+    .about("Writes the collision of maps as one triangle soup per map, in world space and Unreal units.")
+    .arg(arg!(--map <MAP>).id("map")
+        .help("The map package to export, like Stadium_P. Defaults to every package ending in _P")
+        .required(false))
+    .arg(arg!(-f --format <FORMAT>).id("format")
+        .help("OBJ, or little-endian vertex and triangle counts followed by the float vertices and u32 indices")
+        .possible_values(["obj", "bin"])
+        .default_value("obj")
+        .required(false))
+}
+
+fn get_cars_command() -> Command<'static> {
+    common_args(Command::new("cars"), "./cars", "The output directory")
+    .about("Writes the hitbox, wheel, suspension and mass properties of the car archetypes to JSON, one file per package.")
+    .arg(pattern_arg("TAGame*.upk", "The packages to search for car archetypes"))
+}
+
+fn get_products_command() -> Command<'static> {
+    common_args(Command::new("products"), "./products", "The output directory the database is written to")
+    .about("Writes the product assets of all the upk files in the input directory to a single item database.")
+    .arg(pattern_arg("*.upk", "The packages to search for products"))
+    .arg(arg!(-f --format <FORMAT>).id("format")
+        .help("The format of the database")
+        .possible_values(["json", "csv"])
+        .default_value("json")
+        .required(false))
+}
+
+fn get_movies_command() -> Command<'static> {
+    common_args(Command::new("movies"), "./movies", "The output directory, every package gets a folder with its movies")
+    .about("Extracts the Scaleform movies of all the upk files in the input directory as GFx and SWF files, with the textures they import.")
+    .arg(pattern_arg("*.upk", "The packages to extract from"))
+}
+
+fn get_banks_command() -> Command<'static> {
+    common_args(Command::new("banks"), "./banks", "The output directory, every package gets a folder with its banks")
+    .about("Extracts the Wwise sound banks of all the upk files in the input directory, with their embedded media and the events playing them.")
+    .arg(pattern_arg("*.upk", "The packages to extract from"))
+}
+
+fn get_audio_command() -> Command<'static> {
+    Command::new("audio")
+    .about("Converts the Wwise media (.wem) files of the input directory to Ogg Vorbis or WAV.")
+    .arg(arg!(-i --input <INPUT>).id("input")
+        .help("The input directory with the .wem files, searched recursively, like the output of banks.")
+        .required(false))
+    .arg(arg!(-o --output <OUTPUT>).id("output")
+        .help("The output directory, the folders of the input directory are kept")
+        .default_value("./audio")
+        .required(false))
+    .arg(arg!(--codebooks <CODEBOOKS>).id("codebooks")
+        .help("A packed codebook library, for the Vorbis files referencing external codebooks")
+        .required(false)
+        .validator(path_exists_validator))
+    .arg(arg!(-t --threads <THREADS>).id("threads")
+        .help("The numbers of threads that will convert the files")
+        .required(false))
+}
+
+fn get_script_command() -> Command<'static> {
+    common_args(Command::new("script"), "./script", "The output directory, every package gets a folder with a file per class")
+    .about("Disassembles the UnrealScript functions and states of all the upk files in the input directory into pseudo-code.")
+    .arg(pattern_arg("*.upk", "The packages to disassemble, operators are named when Core and Engine are in the input directory"))
+}
+
+fn get_sdk_command() -> Command<'static> {
+    common_args(Command::new("sdk"), "./sdk", "The output directory the definitions are written to")
+    .about("Writes the memory layout of the classes, structs and enums of all the upk files in the input directory as Rust or C++ definitions.")
+    .arg(pattern_arg("*.upk", "The packages to read the types from, classes deriving from the types of a package left out are skipped"))
+    .arg(arg!(-l --language <LANGUAGE>).id("language")
+        .help("The language of the definitions")
+        .possible_values(["rust", "cpp"])
+        .default_value("rust")
+        .required(false))
+    .arg(arg!(--sizes <SIZES>).id("sizes")
+        .help("A file of the known sizes of native classes, one `Package.Class 0x60` per line, which are asserted in the definitions")
+        .required(false)
+        .validator(path_exists_validator))
+}
+
+fn get_classes_command() -> Command<'static> {
+    common_args(Command::new("classes"), "./classes", "The output directory the hierarchy is written to")
+    .about("Writes the class hierarchy of all the upk files in the input directory, with the default properties of every class.")
+    .arg(pattern_arg("*.upk", "The packages to read the classes from, the classes deriving from a class left out are listed as roots"))
+    .arg(arg!(-f --format <FORMAT>).id("format")
+        .help("The format of the hierarchy, JSON always has the default properties")
+        .possible_values(["tree", "json"])
+        .default_value("tree")
+        .required(false))
+    .arg(arg!(-c --class <CLASS>).id("class")
+        .help("Only the class with this name, or package.name, and its subclasses")
+        .required(false))
+    .arg(arg!(-d --defaults).id("defaults")
+        .help("List the default properties under every class of the tree")
+        .required(false))
+}